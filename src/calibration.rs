@@ -0,0 +1,129 @@
+//! Per-LED corrections for strips whose LEDs don't match, written by hand
+//! or measured with `camera-calibrate`.
+//!
+//! ```toml
+//! # Red, green and blue scale for each LED from the data input; LEDs past
+//! # the end of the list are left alone.
+//! scale = [[1.0, 1.0, 1.0], [1.0, 0.92, 0.85]]
+//! # LEDs that are failing and kept off.
+//! dead = [40]
+//! ```
+
+use crate::error::{Error, Result};
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// Corrections applied to each LED as frames are written.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Calibration {
+    /// Red, green and blue multipliers by LED position on the wire.
+    pub scale: Vec<[f32; 3]>,
+    /// Positions of LEDs that are always off.
+    pub dead: Vec<usize>,
+}
+
+impl Calibration {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Calibration> {
+        let path = path.as_ref();
+        let name = path.display().to_string();
+        let text = fs::read_to_string(path).map_err(|e| Error::open(&name, e))?;
+        let calibration: Calibration = toml::from_str(&text).map_err(|e| Error::Config {
+            path: name.clone(),
+            message: e.to_string(),
+        })?;
+        if let Some(bad) = calibration
+            .scale
+            .iter()
+            .flatten()
+            .find(|s| !s.is_finite() || **s < 0.0)
+        {
+            return Err(Error::Config {
+                path: name,
+                message: format!("scale {} must be 0 or more", bad),
+            });
+        }
+        Ok(calibration)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let text = toml::to_string(self).map_err(|e| Error::Config {
+            path: path.display().to_string(),
+            message: e.to_string(),
+        })?;
+        fs::write(path, text)?;
+        Ok(())
+    }
+
+    /// Scale factors that even out each LED's measured red, green and blue
+    /// `levels`, dimming the brighter LEDs to match the dimmer ones; LEDs
+    /// without levels are dead. With `balance` the channels are matched to
+    /// each other too, so full white looks white to whatever measured them.
+    pub fn from_levels(levels: &[Option<[f32; 3]>], balance: bool) -> Calibration {
+        let mut targets = [0.0f32; 3];
+        for (c, target) in targets.iter_mut().enumerate() {
+            let mut values: Vec<f32> = levels
+                .iter()
+                .flatten()
+                .map(|l| l[c])
+                .filter(|v| *v > 0.0)
+                .collect();
+            values.sort_by(f32::total_cmp);
+            // The tenth percentile rather than the dimmest, so one failing
+            // LED doesn't dim all the rest.
+            *target = values.get(values.len() / 10).copied().unwrap_or(0.0);
+        }
+        if balance {
+            let lowest = targets
+                .iter()
+                .copied()
+                .filter(|t| *t > 0.0)
+                .fold(f32::INFINITY, f32::min);
+            targets
+                .iter_mut()
+                .filter(|t| **t > 0.0)
+                .for_each(|t| *t = lowest);
+        }
+        let scale = levels
+            .iter()
+            .map(|level| {
+                let mut scale = [1.0; 3];
+                if let Some(level) = level {
+                    for c in 0..3 {
+                        if level[c] > 0.0 && targets[c] > 0.0 {
+                            scale[c] = (targets[c] / level[c]).min(1.0);
+                        }
+                    }
+                }
+                scale
+            })
+            .collect();
+        let dead = (0..levels.len()).filter(|i| levels[*i].is_none()).collect();
+        Calibration { scale, dead }
+    }
+
+    /// Red, green and blue multipliers for the LED at `index`.
+    pub fn scale_at(&self, index: usize) -> [f32; 3] {
+        self.scale.get(index).copied().unwrap_or([1.0; 3])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn evens_out_measured_levels() {
+        let levels = [Some([100.0, 50.0, 200.0]), None, Some([200.0, 50.0, 0.0])];
+        let even = Calibration::from_levels(&levels, false);
+        assert_eq!(even.scale, vec![[1.0, 1.0, 1.0], [1.0; 3], [0.5, 1.0, 1.0]]);
+        assert_eq!(even.dead, vec![1]);
+
+        let balanced = Calibration::from_levels(&levels, true);
+        assert_eq!(balanced.scale[0], [0.5, 1.0, 0.25]);
+        assert_eq!(balanced.scale[2], [0.25, 1.0, 1.0]);
+    }
+}
@@ -0,0 +1,203 @@
+//! Frames from a webcam pointed at a strip, and finding and measuring the
+//! LEDs in them, for calibrating strips by camera.
+//!
+//! Capture needs the `camera` feature. Lock the camera's exposure and white
+//! balance first so every frame is measured alike.
+
+use crate::error::{Error, Result};
+
+#[cfg(feature = "camera")]
+mod v4l2;
+
+#[cfg(feature = "camera")]
+pub use v4l2::Camera;
+
+/// A V4L2 video device streaming frames.
+#[cfg(not(feature = "camera"))]
+pub enum Camera {}
+
+#[cfg(not(feature = "camera"))]
+impl Camera {
+    pub fn open(_path: &str) -> Result<Camera> {
+        Err(Error::Unsupported("built without the camera feature"))
+    }
+
+    /// Frames queued by the device, which may predate a change to the LEDs.
+    pub fn buffers(&self) -> usize {
+        match *self {}
+    }
+
+    /// The next frame from the device.
+    pub fn capture(&mut self) -> Result<Image> {
+        match *self {}
+    }
+}
+
+/// An RGB image with channels from 0 to 255.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    /// Rows from the top, each from the left.
+    pub pixels: Vec<[f32; 3]>,
+}
+
+/// Channels summed, as a rough brightness.
+fn luma(pixel: &[f32; 3]) -> f32 {
+    pixel[0] + pixel[1] + pixel[2]
+}
+
+impl Image {
+    /// Light in this image that isn't in `dark`, taken of the same scene.
+    pub fn difference(&self, dark: &Image) -> Result<Image> {
+        if (self.width, self.height) != (dark.width, dark.height) {
+            return Err(Error::Device(format!(
+                "camera frame changed size from {}x{} to {}x{}",
+                dark.width, dark.height, self.width, self.height
+            )));
+        }
+        let pixels = self
+            .pixels
+            .iter()
+            .zip(&dark.pixels)
+            .map(|(lit, dark)| {
+                let mut pixel = [0.0; 3];
+                for c in 0..3 {
+                    pixel[c] = (lit[c] - dark[c]).max(0.0);
+                }
+                pixel
+            })
+            .collect();
+        Ok(Image {
+            width: self.width,
+            height: self.height,
+            pixels,
+        })
+    }
+
+    /// Pixels within `radius` of `at`, with their positions.
+    fn around(&self, at: (f32, f32), radius: f32) -> impl Iterator<Item = (f32, f32, &[f32; 3])> {
+        let span = |centre: f32, size: usize| {
+            let low = (centre - radius).floor().max(0.0) as usize;
+            let high = ((centre + radius).ceil().max(0.0) as usize).min(size.saturating_sub(1));
+            low..=high
+        };
+        let (width, height) = (self.width, self.height);
+        let columns = span(at.0, width);
+        span(at.1, height)
+            .flat_map(move |y| columns.clone().map(move |x| (x, y)))
+            // An empty image still gives a span of one.
+            .filter(move |(x, y)| *x < width && *y < height)
+            .map(move |(x, y)| (x as f32, y as f32, &self.pixels[y * self.width + x]))
+            .filter(move |(x, y, _)| (x - at.0).hypot(y - at.1) <= radius)
+    }
+
+    /// Centre of the brightest spot: pixels within `radius` of the
+    /// brightest one weighted by how far they are over half its
+    /// brightness. `None` when no pixel's channels sum past `threshold`.
+    pub fn locate(&self, threshold: f32, radius: f32) -> Option<(f32, f32)> {
+        let (brightest, peak) =
+            self.pixels
+                .iter()
+                .map(luma)
+                .enumerate()
+                .fold(
+                    (0, 0.0),
+                    |best, (i, l)| if l > best.1 { (i, l) } else { best },
+                );
+        if peak < threshold {
+            return None;
+        }
+        let at = (
+            (brightest % self.width) as f32,
+            (brightest / self.width) as f32,
+        );
+        let (mut x, mut y, mut total) = (0.0, 0.0, 0.0);
+        for (px, py, pixel) in self.around(at, radius) {
+            let weight = (luma(pixel) - peak / 2.0).max(0.0);
+            x += px * weight;
+            y += py * weight;
+            total += weight;
+        }
+        Some((x / total, y / total))
+    }
+
+    /// Each channel summed over the pixels within `radius` of `at`.
+    pub fn measure(&self, at: (f32, f32), radius: f32) -> [f32; 3] {
+        let mut sum = [0.0; 3];
+        for (_, _, pixel) in self.around(at, radius) {
+            for c in 0..3 {
+                sum[c] += pixel[c];
+            }
+        }
+        sum
+    }
+}
+
+/// Radius to measure LEDs over: half the typical distance between
+/// neighbouring LEDs found, so their light doesn't mix, but at least 2
+/// pixels and at most `max`.
+pub fn spot_radius(spots: &[Option<(f32, f32)>], max: f32) -> f32 {
+    let mut gaps: Vec<f32> = spots
+        .windows(2)
+        .filter_map(|pair| match pair {
+            [Some(a), Some(b)] => Some((a.0 - b.0).hypot(a.1 - b.1)),
+            _ => None,
+        })
+        .collect();
+    if gaps.is_empty() {
+        return max.max(2.0);
+    }
+    gaps.sort_by(f32::total_cmp);
+    (gaps[gaps.len() / 2] / 2.0).clamp(2.0, max.max(2.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A dark image with a spot of `peak` centred on `at`.
+    fn spot(at: (f32, f32), peak: f32) -> Image {
+        let (width, height) = (40, 30);
+        let pixels = (0..width * height)
+            .map(|i| {
+                let d = ((i % width) as f32 - at.0).hypot((i / width) as f32 - at.1);
+                let level = peak * (-d * d / 8.0).exp();
+                [level, level / 2.0, 0.0]
+            })
+            .collect();
+        Image {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    #[test]
+    fn finds_and_measures_spots() {
+        let dark = spot((0.0, 0.0), 0.0);
+        let lit = spot((12.5, 20.0), 200.0).difference(&dark).unwrap();
+        let (x, y) = lit.locate(60.0, 6.0).unwrap();
+        assert!(
+            (x - 12.5).abs() < 0.1 && (y - 20.0).abs() < 0.1,
+            "{} {}",
+            x,
+            y
+        );
+        assert_eq!(spot((5.0, 5.0), 10.0).locate(60.0, 6.0), None);
+
+        let [red, green, blue] = lit.measure((x, y), 6.0);
+        assert!(red > 200.0 && (green - red / 2.0).abs() < 1.0e-3 && blue == 0.0);
+        assert!(spot((0.0, 0.0), 0.0)
+            .difference(&Image {
+                width: 1,
+                height: 1,
+                pixels: vec![[0.0; 3]],
+            })
+            .is_err());
+
+        let spots = [Some((0.0, 0.0)), Some((10.0, 0.0)), None, Some((30.0, 0.0))];
+        assert_eq!(spot_radius(&spots, 12.0), 5.0);
+        assert_eq!(spot_radius(&[None], 12.0), 12.0);
+    }
+}
@@ -0,0 +1,369 @@
+//! Capture through the Video4Linux2 streaming interface, with frames in
+//! memory mapped buffers.
+
+use super::Image;
+use crate::error::{Error, Result};
+
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::mem;
+use std::os::raw::{c_int, c_ulong, c_void};
+use std::os::unix::io::AsRawFd;
+use std::ptr;
+
+const BUF_TYPE_VIDEO_CAPTURE: u32 = 1;
+const MEMORY_MMAP: u32 = 1;
+const FIELD_NONE: u32 = 1;
+const CAP_VIDEO_CAPTURE: u32 = 0x0000_0001;
+const CAP_STREAMING: u32 = 0x0400_0000;
+const CAP_DEVICE_CAPS: u32 = 0x8000_0000;
+
+/// Packed 4:2:2 YUV, which nearly every webcam offers uncompressed.
+const PIX_FMT_YUYV: u32 = fourcc(b"YUYV");
+
+/// Size asked for; the driver picks the nearest it has.
+const WIDTH: u32 = 640;
+const HEIGHT: u32 = 480;
+
+/// Buffers asked for; the driver may give more or fewer.
+const BUFFERS: u32 = 4;
+
+const fn fourcc(code: &[u8; 4]) -> u32 {
+    code[0] as u32 | (code[1] as u32) << 8 | (code[2] as u32) << 16 | (code[3] as u32) << 24
+}
+
+const IOC_WRITE: u32 = 1;
+const IOC_READ: u32 = 2;
+
+/// Request number for V4L2 ioctl `nr` passing a `T`.
+const fn ioc<T>(dir: u32, nr: u32) -> u32 {
+    dir << 30 | (mem::size_of::<T>() as u32) << 16 | (b'V' as u32) << 8 | nr
+}
+
+#[repr(C)]
+struct Capability {
+    driver: [u8; 16],
+    card: [u8; 32],
+    bus_info: [u8; 32],
+    version: u32,
+    capabilities: u32,
+    device_caps: u32,
+    reserved: [u32; 3],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct PixFormat {
+    width: u32,
+    height: u32,
+    pixelformat: u32,
+    field: u32,
+    bytesperline: u32,
+    sizeimage: u32,
+    colorspace: u32,
+    private: u32,
+    flags: u32,
+    ycbcr_enc: u32,
+    quantization: u32,
+    xfer_func: u32,
+}
+
+/// The kernel's format union: 200 bytes, aligned for the pointers some of
+/// its members hold.
+#[repr(C)]
+struct FormatUnion {
+    pix: PixFormat,
+    rest: [u8; 200 - mem::size_of::<PixFormat>()],
+    align: [c_ulong; 0],
+}
+
+#[repr(C)]
+struct Format {
+    kind: u32,
+    fmt: FormatUnion,
+}
+
+#[repr(C)]
+struct RequestBuffers {
+    count: u32,
+    kind: u32,
+    memory: u32,
+    capabilities: u32,
+    flags: u8,
+    reserved: [u8; 3],
+}
+
+#[repr(C)]
+struct Timecode {
+    kind: u32,
+    flags: u32,
+    frames: u8,
+    seconds: u8,
+    minutes: u8,
+    hours: u8,
+    userbits: [u8; 4],
+}
+
+#[repr(C)]
+union BufferLocation {
+    offset: u32,
+    userptr: c_ulong,
+}
+
+#[repr(C)]
+struct Buffer {
+    index: u32,
+    kind: u32,
+    bytesused: u32,
+    flags: u32,
+    field: u32,
+    timestamp: libc::timeval,
+    timecode: Timecode,
+    sequence: u32,
+    memory: u32,
+    m: BufferLocation,
+    length: u32,
+    reserved2: u32,
+    request_fd: u32,
+}
+
+// The sizes the kernel expects, on the 64-bit targets they're known for.
+#[cfg(target_pointer_width = "64")]
+const _: () = assert!(
+    mem::size_of::<Capability>() == 104
+        && mem::size_of::<Format>() == 208
+        && mem::size_of::<RequestBuffers>() == 20
+        && mem::size_of::<Buffer>() == 88
+);
+
+const VIDIOC_QUERYCAP: u32 = ioc::<Capability>(IOC_READ, 0);
+const VIDIOC_S_FMT: u32 = ioc::<Format>(IOC_READ | IOC_WRITE, 5);
+const VIDIOC_REQBUFS: u32 = ioc::<RequestBuffers>(IOC_READ | IOC_WRITE, 8);
+const VIDIOC_QUERYBUF: u32 = ioc::<Buffer>(IOC_READ | IOC_WRITE, 9);
+const VIDIOC_QBUF: u32 = ioc::<Buffer>(IOC_READ | IOC_WRITE, 15);
+const VIDIOC_DQBUF: u32 = ioc::<Buffer>(IOC_READ | IOC_WRITE, 17);
+const VIDIOC_STREAMON: u32 = ioc::<c_int>(IOC_WRITE, 18);
+const VIDIOC_STREAMOFF: u32 = ioc::<c_int>(IOC_WRITE, 19);
+
+/// The kernel structures above, made only of integers, arrays and unions
+/// of them. Private, so nothing outside this module can be marked.
+///
+/// # Safety
+///
+/// Only for types that all zeroes is a valid value of.
+unsafe trait Plain {}
+
+unsafe impl Plain for Capability {}
+unsafe impl Plain for Format {}
+unsafe impl Plain for RequestBuffers {}
+unsafe impl Plain for Buffer {}
+
+/// A zeroed kernel structure, ready to fill in.
+fn zeroed<T: Plain>() -> T {
+    // Safe as all zeroes is a valid `Plain` value.
+    unsafe { mem::zeroed() }
+}
+
+/// A V4L2 video device streaming frames.
+pub struct Camera {
+    file: File,
+    path: String,
+    width: usize,
+    height: usize,
+    bytes_per_line: usize,
+    /// Mapped buffers and their lengths, by buffer index.
+    buffers: Vec<(*mut c_void, usize)>,
+}
+
+impl Camera {
+    pub fn open(path: &str) -> Result<Camera> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(path)
+            .map_err(|e| Error::open(path, e))?;
+        let mut camera = Camera {
+            file,
+            path: path.to_string(),
+            width: 0,
+            height: 0,
+            bytes_per_line: 0,
+            buffers: Vec::new(),
+        };
+
+        let mut capability: Capability = zeroed();
+        camera.ioctl("VIDIOC_QUERYCAP", VIDIOC_QUERYCAP, &mut capability)?;
+        let caps = if capability.capabilities & CAP_DEVICE_CAPS != 0 {
+            capability.device_caps
+        } else {
+            capability.capabilities
+        };
+        if caps & CAP_VIDEO_CAPTURE == 0 || caps & CAP_STREAMING == 0 {
+            return Err(Error::Device(format!("{} can't stream video", path)));
+        }
+
+        let mut format: Format = zeroed();
+        format.kind = BUF_TYPE_VIDEO_CAPTURE;
+        format.fmt.pix.width = WIDTH;
+        format.fmt.pix.height = HEIGHT;
+        format.fmt.pix.pixelformat = PIX_FMT_YUYV;
+        format.fmt.pix.field = FIELD_NONE;
+        camera.ioctl("VIDIOC_S_FMT", VIDIOC_S_FMT, &mut format)?;
+        let pix = format.fmt.pix;
+        if pix.pixelformat != PIX_FMT_YUYV {
+            return Err(Error::Device(format!("{} doesn't offer YUYV frames", path)));
+        }
+        camera.width = pix.width as usize;
+        camera.height = pix.height as usize;
+        camera.bytes_per_line = (pix.bytesperline as usize).max(camera.width * 2);
+
+        let mut request: RequestBuffers = zeroed();
+        request.count = BUFFERS;
+        request.kind = BUF_TYPE_VIDEO_CAPTURE;
+        request.memory = MEMORY_MMAP;
+        camera.ioctl("VIDIOC_REQBUFS", VIDIOC_REQBUFS, &mut request)?;
+        if request.count == 0 {
+            return Err(Error::Device(format!("{} gave no buffers", path)));
+        }
+        for index in 0..request.count {
+            let mut buffer = camera.buffer();
+            buffer.index = index;
+            camera.ioctl("VIDIOC_QUERYBUF", VIDIOC_QUERYBUF, &mut buffer)?;
+            let length = buffer.length as usize;
+            // Safe as the offset and length are the kernel's own for this
+            // buffer; the mapping is undone when the camera is dropped.
+            let data = unsafe {
+                libc::mmap(
+                    ptr::null_mut(),
+                    length,
+                    libc::PROT_READ | libc::PROT_WRITE,
+                    libc::MAP_SHARED,
+                    camera.file.as_raw_fd(),
+                    buffer.m.offset as libc::off_t,
+                )
+            };
+            if data == libc::MAP_FAILED {
+                return Err(Error::Device(format!(
+                    "unable to map buffer of {}: {}",
+                    path,
+                    io::Error::last_os_error()
+                )));
+            }
+            camera.buffers.push((data, length));
+            camera.ioctl("VIDIOC_QBUF", VIDIOC_QBUF, &mut buffer)?;
+        }
+        let mut kind = BUF_TYPE_VIDEO_CAPTURE as c_int;
+        camera.ioctl("VIDIOC_STREAMON", VIDIOC_STREAMON, &mut kind)?;
+        info!(
+            "camera {}: {}x{}, {} buffers",
+            path,
+            camera.width,
+            camera.height,
+            camera.buffers.len()
+        );
+        Ok(camera)
+    }
+
+    /// Frames queued by the device, which may predate a change to the LEDs.
+    pub fn buffers(&self) -> usize {
+        self.buffers.len()
+    }
+
+    /// The next frame from the device, waiting for one if none is ready.
+    pub fn capture(&mut self) -> Result<Image> {
+        let mut buffer = self.buffer();
+        self.ioctl("VIDIOC_DQBUF", VIDIOC_DQBUF, &mut buffer)?;
+        let image = match self.buffers.get(buffer.index as usize) {
+            Some(&(data, length)) => {
+                // Safe while the buffer is dequeued: the driver won't write
+                // to it until it is queued again below.
+                let data = unsafe {
+                    std::slice::from_raw_parts(
+                        data as *const u8,
+                        (buffer.bytesused as usize).min(length),
+                    )
+                };
+                self.convert(data)
+            }
+            None => Err(Error::Device(format!(
+                "{} returned unknown buffer {}",
+                self.path, buffer.index
+            ))),
+        };
+        self.ioctl("VIDIOC_QBUF", VIDIOC_QBUF, &mut buffer)?;
+        image
+    }
+
+    fn buffer(&self) -> Buffer {
+        let mut buffer: Buffer = zeroed();
+        buffer.kind = BUF_TYPE_VIDEO_CAPTURE;
+        buffer.memory = MEMORY_MMAP;
+        buffer
+    }
+
+    /// RGB for a YUYV frame, using the BT.601 studio range most webcams
+    /// send.
+    fn convert(&self, data: &[u8]) -> Result<Image> {
+        if data.len() < self.bytes_per_line * self.height {
+            return Err(Error::Device(format!(
+                "short frame from {}: {} bytes",
+                self.path,
+                data.len()
+            )));
+        }
+        let mut pixels = Vec::with_capacity(self.width * self.height);
+        for (line, row) in data
+            .chunks(self.bytes_per_line)
+            .take(self.height)
+            .enumerate()
+        {
+            for pair in row[..self.width * 2].chunks_exact(4) {
+                let u = f32::from(pair[1]) - 128.0;
+                let v = f32::from(pair[3]) - 128.0;
+                for &y in &[pair[0], pair[2]] {
+                    let y = 1.164 * (f32::from(y) - 16.0);
+                    pixels.push([
+                        (y + 1.596 * v).clamp(0.0, 255.0),
+                        (y - 0.392 * u - 0.813 * v).clamp(0.0, 255.0),
+                        (y + 2.017 * u).clamp(0.0, 255.0),
+                    ]);
+                }
+            }
+            // An odd width leaves the row's last pixel out of the pairs;
+            // it is left black so the next row starts in its place.
+            pixels.resize((line + 1) * self.width, [0.0; 3]);
+        }
+        Ok(Image {
+            width: self.width,
+            height: self.height,
+            pixels,
+        })
+    }
+
+    fn ioctl<T>(&self, name: &str, request: u32, arg: &mut T) -> Result<()> {
+        loop {
+            // Safe as each request is only used with the structure its
+            // number was made from.
+            let result = unsafe { libc::ioctl(self.file.as_raw_fd(), request as _, arg as *mut T) };
+            if result != -1 {
+                return Ok(());
+            }
+            let e = io::Error::last_os_error();
+            if e.kind() != io::ErrorKind::Interrupted {
+                return Err(Error::Device(format!("{} on {}: {}", name, self.path, e)));
+            }
+        }
+    }
+}
+
+impl Drop for Camera {
+    fn drop(&mut self) {
+        let mut kind = BUF_TYPE_VIDEO_CAPTURE as c_int;
+        let _ = self.ioctl("VIDIOC_STREAMOFF", VIDIOC_STREAMOFF, &mut kind);
+        for &(data, length) in &self.buffers {
+            unsafe {
+                libc::munmap(data, length);
+            }
+        }
+    }
+}
@@ -0,0 +1,349 @@
+use crate::color_names::COLOR_NAMES;
+
+use std::fmt;
+use std::str::FromStr;
+
+/// A pixel as red, green and blue intensities.
+///
+/// Drivers turn these into their own wire format, such as the P9813 flag
+/// byte, when a frame is written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Color {
+    pub red: u8,
+    pub green: u8,
+    pub blue: u8,
+}
+
+impl Color {
+    pub const BLACK: Color = Color::new(0, 0, 0);
+    pub const WHITE: Color = Color::new(0xff, 0xff, 0xff);
+
+    pub const fn new(red: u8, green: u8, blue: u8) -> Self {
+        Color { red, green, blue }
+    }
+
+    /// The color written 0xrrggbb.
+    pub const fn from_rgb(value: u32) -> Self {
+        Color::new((value >> 16) as u8, (value >> 8) as u8, value as u8)
+    }
+
+    /// Blend towards `other`: `t` of 0 gives this color and 1 gives `other`.
+    pub fn lerp(self, other: Color, t: f64) -> Color {
+        let t = t.clamp(0.0, 1.0);
+        let mix = |a: u8, b: u8| (f64::from(a) + (f64::from(b) - f64::from(a)) * t).round() as u8;
+        Color::new(
+            mix(self.red, other.red),
+            mix(self.green, other.green),
+            mix(self.blue, other.blue),
+        )
+    }
+
+    /// Multiply every channel by `factor`, rounding down and saturating.
+    pub fn scale(self, factor: f64) -> Color {
+        let scale = |v: u8| (f64::from(v) * factor).clamp(0.0, 255.0) as u8;
+        Color::new(scale(self.red), scale(self.green), scale(self.blue))
+    }
+
+    /// Add `other` channel by channel, saturating at full intensity.
+    pub fn saturating_add(self, other: Color) -> Color {
+        Color::new(
+            self.red.saturating_add(other.red),
+            self.green.saturating_add(other.green),
+            self.blue.saturating_add(other.blue),
+        )
+    }
+
+    /// This color with `white` on a dedicated white LED.
+    pub fn with_white(self, white: u8) -> ColorRgbw {
+        ColorRgbw::new(self.red, self.green, self.blue, white)
+    }
+}
+
+impl From<(u8, u8, u8)> for Color {
+    fn from((red, green, blue): (u8, u8, u8)) -> Self {
+        Color::new(red, green, blue)
+    }
+}
+
+impl From<Color> for (u8, u8, u8) {
+    fn from(color: Color) -> Self {
+        (color.red, color.green, color.blue)
+    }
+}
+
+impl From<[u8; 3]> for Color {
+    fn from([red, green, blue]: [u8; 3]) -> Self {
+        Color::new(red, green, blue)
+    }
+}
+
+impl From<Color> for [u8; 3] {
+    fn from(color: Color) -> Self {
+        [color.red, color.green, color.blue]
+    }
+}
+
+/// One `rgb()` component: 0 to 255, or a percentage.
+fn parse_component(s: &str) -> std::result::Result<u8, String> {
+    let (number, full) = match s.strip_suffix('%') {
+        Some(percent) => (percent, 100.0),
+        None => (s, 255.0),
+    };
+    match number.trim().parse::<f64>() {
+        Ok(v) if (0.0..=full).contains(&v) => Ok((v * 255.0 / full).round() as u8),
+        _ => Err(format!("bad rgb() component {}", s)),
+    }
+}
+
+/// Parse a CSS color: "#rgb", "#rrggbb" or "rrggbb", "rgb(255, 136, 0)"
+/// with numbers or percentages, or a name such as "coral".
+impl FromStr for Color {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let lower = s.trim().to_ascii_lowercase();
+        if let Some((_, value)) = COLOR_NAMES.iter().find(|(name, _)| *name == lower) {
+            return Ok(Color::from_rgb(*value));
+        }
+        if let Some(args) = lower
+            .strip_prefix("rgb(")
+            .and_then(|rest| rest.strip_suffix(')'))
+        {
+            let parts: Vec<&str> = args.split([',', ' ']).filter(|p| !p.is_empty()).collect();
+            if parts.len() != 3 {
+                return Err(format!("expected three components in {}", s));
+            }
+            return Ok(Color::new(
+                parse_component(parts[0])?,
+                parse_component(parts[1])?,
+                parse_component(parts[2])?,
+            ));
+        }
+        let hex = match lower.strip_prefix('#') {
+            Some(hex) if hex.len() == 3 => hex.chars().flat_map(|c| [c, c]).collect(),
+            Some(hex) => hex.to_string(),
+            None => lower.clone(),
+        };
+        match u32::from_str_radix(&hex, 16) {
+            Ok(value) if hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()) => {
+                Ok(Color::from_rgb(value))
+            }
+            _ => Err(format!(
+                "expected a color name, #rgb, #rrggbb or rgb(), got {}",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.red, self.green, self.blue)
+    }
+}
+
+/// A pixel with 16 bits per channel, kept through effects, scaling and
+/// gamma correction so slow fades don't step; drivers still get 8 bits.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Color16 {
+    pub red: u16,
+    pub green: u16,
+    pub blue: u16,
+}
+
+impl Color16 {
+    pub const fn new(red: u16, green: u16, blue: u16) -> Self {
+        Color16 { red, green, blue }
+    }
+
+    /// Multiply every channel by `factor`, rounding and saturating.
+    pub fn scale(self, factor: f64) -> Color16 {
+        let scale = |v: u16| (f64::from(v) * factor).round().clamp(0.0, 65535.0) as u16;
+        Color16::new(scale(self.red), scale(self.green), scale(self.blue))
+    }
+
+    /// Blend towards `other` by `t`, clamped to 0 to 1.
+    pub fn lerp(self, other: Color16, t: f64) -> Color16 {
+        let t = t.clamp(0.0, 1.0);
+        let mix =
+            |a: u16, b: u16| (f64::from(a) + (f64::from(b) - f64::from(a)) * t).round() as u16;
+        Color16::new(
+            mix(self.red, other.red),
+            mix(self.green, other.green),
+            mix(self.blue, other.blue),
+        )
+    }
+
+    /// Add `other` channel by channel, saturating at full intensity.
+    pub fn saturating_add(self, other: Color16) -> Color16 {
+        Color16::new(
+            self.red.saturating_add(other.red),
+            self.green.saturating_add(other.green),
+            self.blue.saturating_add(other.blue),
+        )
+    }
+
+    /// The nearest 8-bit color.
+    pub fn to_color(self) -> Color {
+        let round = |v: u16| ((u32::from(v) + 128) / 257) as u8;
+        Color::new(round(self.red), round(self.green), round(self.blue))
+    }
+}
+
+impl From<Color> for Color16 {
+    fn from(color: Color) -> Self {
+        let widen = |v: u8| u16::from(v) * 257;
+        Color16::new(widen(color.red), widen(color.green), widen(color.blue))
+    }
+}
+
+impl From<(u8, u8, u8)> for Color16 {
+    fn from(rgb: (u8, u8, u8)) -> Self {
+        Color::from(rgb).into()
+    }
+}
+
+/// A color temperature to balance output to, such as 2700K for warm
+/// household bulbs; 6500K leaves colors unchanged.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WhitePoint {
+    kelvin: f64,
+}
+
+impl WhitePoint {
+    /// Daylight white, which needs no correction.
+    pub const NEUTRAL: WhitePoint = WhitePoint { kelvin: 6500.0 };
+
+    /// A white point at `kelvin`, limited to 1000K to 40000K.
+    pub fn new(kelvin: f64) -> Self {
+        WhitePoint {
+            kelvin: kelvin.clamp(1000.0, 40000.0),
+        }
+    }
+
+    pub fn kelvin(&self) -> f64 {
+        self.kelvin
+    }
+
+    /// Red, green and blue multipliers, at most 1, that shift daylight
+    /// white to this temperature.
+    pub fn balance(&self) -> (f64, f64, f64) {
+        let (red, green, blue) = blackbody(self.kelvin);
+        let (n_red, n_green, n_blue) = blackbody(WhitePoint::NEUTRAL.kelvin);
+        let (red, green, blue) = (red / n_red, green / n_green, blue / n_blue);
+        let max = red.max(green).max(blue);
+        (red / max, green / max, blue / max)
+    }
+}
+
+impl FromStr for WhitePoint {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let number = s.trim().trim_end_matches(['K', 'k']);
+        match number.parse::<f64>() {
+            Ok(kelvin) if (1000.0..=40000.0).contains(&kelvin) => Ok(WhitePoint::new(kelvin)),
+            Ok(_) => Err(format!("white point {} is outside 1000K to 40000K", s)),
+            Err(e) => Err(format!("bad white point {}: {}", s, e)),
+        }
+    }
+}
+
+impl fmt::Display for WhitePoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}K", self.kelvin)
+    }
+}
+
+/// Approximate color of a black body at `kelvin`, after Tanner Helland's
+/// fit to the CIE 1964 color matching data.
+fn blackbody(kelvin: f64) -> (f64, f64, f64) {
+    let t = kelvin / 100.0;
+    let red = if t <= 66.0 {
+        255.0
+    } else {
+        329.698727446 * (t - 60.0).powf(-0.1332047592)
+    };
+    let green = if t <= 66.0 {
+        99.4708025861 * t.ln() - 161.1195681661
+    } else {
+        288.1221695283 * (t - 60.0).powf(-0.0755148492)
+    };
+    let blue = if t >= 66.0 {
+        255.0
+    } else if t <= 19.0 {
+        0.0
+    } else {
+        138.5177312231 * (t - 10.0).ln() - 305.0447927307
+    };
+    let channel = |v: f64| v.clamp(0.0, 255.0);
+    (channel(red), channel(green), channel(blue))
+}
+
+/// A pixel for strips with a dedicated white LED alongside red, green and blue.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ColorRgbw {
+    pub red: u8,
+    pub green: u8,
+    pub blue: u8,
+    pub white: u8,
+}
+
+impl ColorRgbw {
+    pub fn new(red: u8, green: u8, blue: u8, white: u8) -> Self {
+        ColorRgbw {
+            red,
+            green,
+            blue,
+            white,
+        }
+    }
+
+    /// Approximate this color on an RGB-only strip by mixing the white
+    /// component into each of the color channels.
+    pub fn to_rgb(self) -> Color {
+        Color::new(self.red, self.green, self.blue)
+            .saturating_add(Color::new(self.white, self.white, self.white))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_css_colors() {
+        let orange = Color::new(0xff, 0x88, 0x00);
+        for s in &[
+            "#ff8800",
+            "FF8800",
+            "#f80",
+            "rgb(255, 136, 0)",
+            "rgb(100% 53.3% 0%)",
+        ] {
+            assert_eq!(s.parse::<Color>(), Ok(orange), "{}", s);
+        }
+        assert_eq!("RebeccaPurple".parse(), Ok(Color::new(0x66, 0x33, 0x99)));
+        assert_eq!(orange.to_string().parse(), Ok(orange));
+        for s in &[
+            "#ff88",
+            "+12345",
+            "rgb(256, 0, 0)",
+            "rgb(1, 2)",
+            "notacolor",
+        ] {
+            assert!(s.parse::<Color>().is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn lerp_and_scale() {
+        let from = Color::new(0, 100, 255);
+        let to = Color::new(255, 100, 0);
+        assert_eq!(from.lerp(to, 0.0), from);
+        assert_eq!(from.lerp(to, 1.0), to);
+        assert_eq!(from.lerp(to, 0.5), Color::new(128, 100, 128));
+        assert_eq!(from.scale(0.5), Color::new(0, 50, 127));
+        assert_eq!(from.scale(2.0), Color::new(0, 200, 255));
+    }
+}
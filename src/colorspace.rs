@@ -0,0 +1,227 @@
+//! Conversions between RGB and the HSV, HSL and OkLCH color spaces.
+//!
+//! RGB components run from 0 to 1 and are taken to be sRGB encoded, as
+//! effects produce them before gamma correction. Hues are in degrees.
+
+use crate::color::Color;
+
+/// RGB for `hue`, `saturation` and `value`, each but the hue from 0 to 1.
+pub fn hsv_to_rgb(hue: f64, saturation: f64, value: f64) -> (f64, f64, f64) {
+    if saturation < 1.0e-6 {
+        return (value, value, value);
+    }
+
+    let hue = hue.rem_euclid(360.0) / 60.0;
+    let i = hue.floor();
+    let frac = hue - i;
+    let p = value * (1.0 - saturation);
+    let q = value * (1.0 - saturation * frac);
+    let t = value * (1.0 - saturation * (1.0 - frac));
+
+    match i as u8 {
+        0 => (value, t, p),
+        1 => (q, value, p),
+        2 => (p, value, t),
+        3 => (p, q, value),
+        4 => (t, p, value),
+        _ => (value, p, q),
+    }
+}
+
+/// Hue, saturation and value for an RGB color.
+pub fn rgb_to_hsv(red: f64, green: f64, blue: f64) -> (f64, f64, f64) {
+    let max = red.max(green).max(blue);
+    let min = red.min(green).min(blue);
+    let saturation = if max > 0.0 { (max - min) / max } else { 0.0 };
+    (hue_of(red, green, blue, max, min), saturation, max)
+}
+
+/// RGB for `hue`, `saturation` and `lightness`, each but the hue from 0
+/// to 1.
+pub fn hsl_to_rgb(hue: f64, saturation: f64, lightness: f64) -> (f64, f64, f64) {
+    let value = lightness + saturation * lightness.min(1.0 - lightness);
+    let saturation = if value > 0.0 {
+        2.0 * (1.0 - lightness / value)
+    } else {
+        0.0
+    };
+    hsv_to_rgb(hue, saturation, value)
+}
+
+/// Hue, saturation and lightness for an RGB color.
+pub fn rgb_to_hsl(red: f64, green: f64, blue: f64) -> (f64, f64, f64) {
+    let max = red.max(green).max(blue);
+    let min = red.min(green).min(blue);
+    let lightness = (max + min) / 2.0;
+    let saturation = if lightness > 0.0 && lightness < 1.0 {
+        (max - lightness) / lightness.min(1.0 - lightness)
+    } else {
+        0.0
+    };
+    (hue_of(red, green, blue, max, min), saturation, lightness)
+}
+
+/// The hue shared by HSV and HSL, 0 for grays.
+fn hue_of(red: f64, green: f64, blue: f64, max: f64, min: f64) -> f64 {
+    let chroma = max - min;
+    if chroma <= 0.0 {
+        return 0.0;
+    }
+    let hue = if max == red {
+        (green - blue) / chroma
+    } else if max == green {
+        (blue - red) / chroma + 2.0
+    } else {
+        (red - green) / chroma + 4.0
+    };
+    (hue * 60.0).rem_euclid(360.0)
+}
+
+fn srgb_to_linear(v: f64) -> f64 {
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(v: f64) -> f64 {
+    if v <= 0.0031308 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Lightness, chroma and hue in Björn Ottosson's OkLCH space for an RGB
+/// color. Lightness runs from 0 to 1 and chroma to about 0.37.
+pub fn rgb_to_oklch(red: f64, green: f64, blue: f64) -> (f64, f64, f64) {
+    let (r, g, b) = (
+        srgb_to_linear(red),
+        srgb_to_linear(green),
+        srgb_to_linear(blue),
+    );
+    let l = (0.4122214708 * r + 0.5363325363 * g + 0.0514459929 * b).cbrt();
+    let m = (0.2119034982 * r + 0.6806995451 * g + 0.1073969566 * b).cbrt();
+    let s = (0.0883024619 * r + 0.2817188376 * g + 0.6299787005 * b).cbrt();
+    let lightness = 0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s;
+    let a = 1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s;
+    let b = 0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s;
+    let chroma = a.hypot(b);
+    let hue = if chroma < 1.0e-9 {
+        0.0
+    } else {
+        b.atan2(a).to_degrees().rem_euclid(360.0)
+    };
+    (lightness, chroma, hue)
+}
+
+/// RGB for an OkLCH color; colors outside the sRGB gamut are clipped.
+pub fn oklch_to_rgb(lightness: f64, chroma: f64, hue: f64) -> (f64, f64, f64) {
+    let (a, b) = (
+        chroma * hue.to_radians().cos(),
+        chroma * hue.to_radians().sin(),
+    );
+    let l = (lightness + 0.3963377774 * a + 0.2158037573 * b).powi(3);
+    let m = (lightness - 0.1055613458 * a - 0.0638541728 * b).powi(3);
+    let s = (lightness - 0.0894841775 * a - 1.2914855480 * b).powi(3);
+    let r = 4.0767416621 * l - 3.3077115913 * m + 0.2309699292 * s;
+    let g = -1.2684380046 * l + 2.6097574011 * m - 0.3413193965 * s;
+    let b = -0.0041960863 * l - 0.7034186147 * m + 1.7076147010 * s;
+    let channel = |v: f64| linear_to_srgb(v.clamp(0.0, 1.0));
+    (channel(r), channel(g), channel(b))
+}
+
+/// Blend from `from` to `to` through OkLCH, taking the shorter way around
+/// the hue circle, so fades between hues keep their brightness and
+/// saturation instead of passing through gray.
+pub fn mix_oklch(from: Color, to: Color, t: f64) -> Color {
+    let unit = |c: Color| {
+        rgb_to_oklch(
+            f64::from(c.red) / 255.0,
+            f64::from(c.green) / 255.0,
+            f64::from(c.blue) / 255.0,
+        )
+    };
+    let t = t.clamp(0.0, 1.0);
+    let (l1, c1, mut h1) = unit(from);
+    let (l2, c2, mut h2) = unit(to);
+    // A gray has no hue of its own, so take the other color's.
+    if c1 < 1.0e-4 {
+        h1 = h2;
+    }
+    if c2 < 1.0e-4 {
+        h2 = h1;
+    }
+    let turn = (h2 - h1 + 540.0).rem_euclid(360.0) - 180.0;
+    let (r, g, b) = oklch_to_rgb(l1 + (l2 - l1) * t, c1 + (c2 - c1) * t, h1 + turn * t);
+    let level = |v: f64| (v * 255.0).round() as u8;
+    Color::new(level(r), level(g), level(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: (f64, f64, f64), b: (f64, f64, f64)) {
+        let close = |x: f64, y: f64| (x - y).abs() < 1.0e-6;
+        assert!(
+            close(a.0, b.0) && close(a.1, b.1) && close(a.2, b.2),
+            "{:?} != {:?}",
+            a,
+            b
+        );
+    }
+
+    #[test]
+    fn hsv_sector_boundaries() {
+        let primaries = [
+            (0.0, (1.0, 0.0, 0.0)),
+            (60.0, (1.0, 1.0, 0.0)),
+            (120.0, (0.0, 1.0, 0.0)),
+            (180.0, (0.0, 1.0, 1.0)),
+            (240.0, (0.0, 0.0, 1.0)),
+            (300.0, (1.0, 0.0, 1.0)),
+            (360.0, (1.0, 0.0, 0.0)),
+        ];
+        for (hue, rgb) in primaries.iter() {
+            assert_close(hsv_to_rgb(*hue, 1.0, 1.0), *rgb);
+            let (h, s, v) = rgb_to_hsv(rgb.0, rgb.1, rgb.2);
+            assert_close((h, s, v), (hue % 360.0, 1.0, 1.0));
+        }
+        // Just either side of a boundary stays continuous.
+        let (r, g, _) = hsv_to_rgb(60.0 - 1.0e-9, 1.0, 1.0);
+        assert!((r - 1.0).abs() < 1.0e-6 && (g - 1.0).abs() < 1.0e-6);
+    }
+
+    #[test]
+    fn round_trips() {
+        for i in 0..=24 {
+            let hue = i as f64 * 15.0;
+            for &(s, v) in &[(1.0, 1.0), (0.5, 0.8), (0.25, 0.3)] {
+                let (r, g, b) = hsv_to_rgb(hue, s, v);
+                let (h2, s2, v2) = rgb_to_hsv(r, g, b);
+                assert_close(hsv_to_rgb(h2, s2, v2), (r, g, b));
+
+                let (h, s, l) = rgb_to_hsl(r, g, b);
+                assert_close(hsl_to_rgb(h, s, l), (r, g, b));
+
+                let (l, c, h) = rgb_to_oklch(r, g, b);
+                assert_close(oklch_to_rgb(l, c, h), (r, g, b));
+            }
+        }
+    }
+
+    #[test]
+    fn oklch_reference_values() {
+        let (l, c, h) = rgb_to_oklch(1.0, 1.0, 1.0);
+        assert!((l - 1.0).abs() < 1.0e-4 && c < 1.0e-4, "{} {} {}", l, c, h);
+        // sRGB red is about L 0.628, C 0.258, h 29.2.
+        let (l, c, h) = rgb_to_oklch(1.0, 0.0, 0.0);
+        assert!((l - 0.628).abs() < 1.0e-3);
+        assert!((c - 0.258).abs() < 1.0e-3);
+        assert!((h - 29.2).abs() < 0.1);
+        let red = Color::new(255, 0, 0);
+        assert_eq!(mix_oklch(red, Color::new(0, 0, 255), 0.0), red);
+    }
+}
@@ -0,0 +1,1149 @@
+//! Settings loaded from a TOML configuration file.
+//!
+//! ```toml
+//! latitude = 45.52
+//! longitude = -122.68
+//! # Effects may be stacked: "rainbow + twinkle add 50%".
+//! effect = "rainbow"
+//! palette = "ocean"
+//! # More palettes, named after their files: .json, .gpl or .cpt.
+//! palette_files = ["palettes/sunset.gpl"]
+//! # Or as colors: "#rgb", "#rrggbb", "rgb(255, 136, 0)" or CSS names.
+//! palettes = { dusk = ["midnightblue", "#c71585", "rgb(255, 140, 0)"] }
+//! transition_seconds = 2.5
+//! # Runs every effect at half speed; may be changed while running.
+//! speed = 0.5
+//!
+//! # Defaults for every strip.
+//! [strip]
+//! chip = "apa102"
+//! num_leds = 120
+//! # Run the APA102s at half current, keeping every PWM step for low light.
+//! global_brightness = 15
+//! gamma = 2.2
+//! gamma_green = 2.4
+//! white_point = "2700K"
+//! # "cie" for the CIE L* lightness curve instead of gamma.
+//! curve_blue = "cie"
+//! dither = true
+//!
+//! # Optional extra strips; without any a single strip uses the defaults.
+//! [[strips]]
+//! path = "/dev/spidev0.1"
+//! num_leds = 30
+//! # LEDs hidden behind the shelf.
+//! blackout = ["0-3", "29"]
+//! # Per-LED scale factors, relative to this file.
+//! calibration = "shelf-calibration.toml"
+//!
+//! # Knobs for the effects that take them, as "blink effects" lists.
+//! [params]
+//! palette = "ocean"
+//!
+//! # Selected with "blink preset movie".
+//! [preset.movie]
+//! effect = "rainbow"
+//! palette = "heat"
+//! max_brightness = 64
+//!
+//! # Cycled through instead of the effect, fading between them; bare
+//! # numbers are seconds.
+//! [playlist]
+//! effects = ["rainbow: 5m", "rainbow: 90s"]
+//! shuffle = true
+//!
+//! # Palettes the effect moves through, fading to the next every five
+//! # minutes, in a random order.
+//! [palette_cycle]
+//! palettes = ["ocean", "forest", "party"]
+//! seconds = 300
+//! shuffle = true
+//!
+//! # Stretches of LEDs with their own effect, or color, and brightness.
+//! [zones.window]
+//! leds = "0-29"
+//! effect = "rainbow"
+//! palette = "heat"
+//!
+//! [zones.shelf]
+//! leds = "30-75"
+//! color = "orange"
+//! brightness = 0.5
+//!
+//! [schedule]
+//! sunrise_ramp_minutes = 120
+//! sunset_ramp_minutes = 180
+//! # linear, quad, cubic, sine, expo or bounce.
+//! ramp_easing = "sine"
+//! # Seconds brightness changes are smoothed over.
+//! smoothing_seconds = 2
+//! # Fade blue down to 30% over an hour from 21:00, and back by 07:00.
+//! night_start = "21:00"
+//! night_end = "07:00"
+//! night_blue = 0.3
+//! ```
+
+use crate::calibration::Calibration;
+use crate::color::{Color, WhitePoint};
+use crate::driver::{
+    self, Backend, Chip, ColorOrder, LedDriver, Reordered, SpiOptions, Tee, Terminal,
+};
+use crate::easing::Easing;
+use crate::effect::{self, Params};
+use crate::error::{Error, Result};
+use crate::gamma::{Curve, GammaTable};
+use crate::palette::PaletteCycle;
+use crate::playlist::{Entry, Playlist};
+use crate::schedule::Schedule;
+use crate::strip::{Mirror, PixelRange, PowerBudget, Strip, WhiteExtraction};
+use crate::tonemap::SoftClip;
+
+use serde::de::{self, Deserializer};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use toml_edit::{DocumentMut, TomlError};
+
+/// Number of LEDs on a strip when none is configured.
+pub const DEFAULT_NUM_LEDS: usize = 76;
+
+/// Gamma exponent used when none is configured.
+pub const DEFAULT_GAMMA: f64 = 2.2;
+
+/// Deserialize the types that already parse from command line strings.
+macro_rules! deserialize_from_str {
+    ($($t:ty),*) => {
+        $(
+            impl<'de> Deserialize<'de> for $t {
+                fn deserialize<D: Deserializer<'de>>(d: D) -> std::result::Result<Self, D::Error> {
+                    let s = String::deserialize(d)?;
+                    s.parse().map_err(de::Error::custom)
+                }
+            }
+        )*
+    };
+}
+
+deserialize_from_str!(
+    Backend,
+    Chip,
+    Color,
+    ColorOrder,
+    Curve,
+    Easing,
+    Entry,
+    Layout,
+    Mirror,
+    PixelRange,
+    WhiteExtraction,
+    WhitePoint
+);
+
+/// A parameter value as written in the file: text, a number or a flag.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ParamValue {
+    Integer(i64),
+    Number(f64),
+    Flag(bool),
+    Text(String),
+}
+
+impl<'de> Deserialize<'de> for Params {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> std::result::Result<Self, D::Error> {
+        let values = BTreeMap::<String, ParamValue>::deserialize(d)?;
+        Ok(values
+            .into_iter()
+            .map(|(key, value)| {
+                let value = match value {
+                    ParamValue::Integer(v) => v.to_string(),
+                    ParamValue::Number(v) => v.to_string(),
+                    ParamValue::Flag(v) => v.to_string(),
+                    ParamValue::Text(v) => v,
+                };
+                (key, value)
+            })
+            .collect())
+    }
+}
+
+/// Settings for one strip.
+///
+/// Every field is optional so a strip only gives what differs from the
+/// defaults it is merged with.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StripConfig {
+    pub driver: Option<Backend>,
+    pub chip: Option<Chip>,
+    /// The spidev device, FTDI description or, for the PCA9685, I2C bus.
+    pub path: Option<String>,
+    pub spi_speed_hz: Option<u32>,
+    pub spi_chunk_size: Option<usize>,
+    pub num_leds: Option<usize>,
+    pub color_order: Option<ColorOrder>,
+    pub gamma: Option<f64>,
+    /// Per-channel exponents overriding `gamma`.
+    #[serde(alias = "gamma_r")]
+    pub gamma_red: Option<f64>,
+    #[serde(alias = "gamma_g")]
+    pub gamma_green: Option<f64>,
+    #[serde(alias = "gamma_b")]
+    pub gamma_blue: Option<f64>,
+    /// Lightness curve, "gamma" or "cie", with per-channel overrides.
+    pub curve: Option<Curve>,
+    #[serde(alias = "curve_r")]
+    pub curve_red: Option<Curve>,
+    #[serde(alias = "curve_g")]
+    pub curve_green: Option<Curve>,
+    #[serde(alias = "curve_b")]
+    pub curve_blue: Option<Curve>,
+    /// Cap on every channel after gamma correction, 0 to 255.
+    pub max_brightness: Option<u8>,
+    /// Current level APA102 and SK9822 chips send with every LED, 0 to 31.
+    pub global_brightness: Option<u8>,
+    /// Color temperature to balance output to, such as "2700K".
+    pub white_point: Option<WhitePoint>,
+    /// How much common white RGBW strips move to the white LED: off, min
+    /// or half.
+    pub white_extraction: Option<WhiteExtraction>,
+    /// Color temperature of the white LED on RGBW strips.
+    pub white_led: Option<WhitePoint>,
+    /// Level from 0 to 1 above which bright colors are compressed, keeping
+    /// their hue, instead of clipping at full.
+    pub soft_knee: Option<f64>,
+    /// Current the strip's power supply can give; frames are dimmed to fit.
+    pub max_milliamps: Option<f64>,
+    /// Current drawn by one color channel at full brightness.
+    pub milliamps_per_channel: Option<f64>,
+    /// Index 0 is the far end of the strip.
+    pub reverse: Option<bool>,
+    /// LEDs index 0 is moved along the strip, wrapping at the end.
+    pub offset: Option<usize>,
+    pub mirror: Option<Mirror>,
+    /// LED positions that are always off, counted from the data input.
+    pub blackout: Option<Vec<PixelRange>>,
+    /// Dither low levels over time instead of rounding them away.
+    pub dither: Option<bool>,
+    /// File of per-LED scale factors and dead LEDs.
+    pub calibration: Option<PathBuf>,
+    /// Also draw the strip in the terminal.
+    pub preview: Option<bool>,
+}
+
+impl StripConfig {
+    /// Fill any unset fields from `defaults`.
+    pub fn or(self, defaults: &StripConfig) -> StripConfig {
+        StripConfig {
+            driver: self.driver.or(defaults.driver),
+            chip: self.chip.or(defaults.chip),
+            path: self.path.or_else(|| defaults.path.clone()),
+            spi_speed_hz: self.spi_speed_hz.or(defaults.spi_speed_hz),
+            spi_chunk_size: self.spi_chunk_size.or(defaults.spi_chunk_size),
+            num_leds: self.num_leds.or(defaults.num_leds),
+            color_order: self.color_order.or(defaults.color_order),
+            gamma: self.gamma.or(defaults.gamma),
+            gamma_red: self.gamma_red.or(defaults.gamma_red),
+            gamma_green: self.gamma_green.or(defaults.gamma_green),
+            gamma_blue: self.gamma_blue.or(defaults.gamma_blue),
+            curve: self.curve.or(defaults.curve),
+            curve_red: self.curve_red.or(defaults.curve_red),
+            curve_green: self.curve_green.or(defaults.curve_green),
+            curve_blue: self.curve_blue.or(defaults.curve_blue),
+            max_brightness: self.max_brightness.or(defaults.max_brightness),
+            global_brightness: self.global_brightness.or(defaults.global_brightness),
+            white_point: self.white_point.or(defaults.white_point),
+            white_extraction: self.white_extraction.or(defaults.white_extraction),
+            white_led: self.white_led.or(defaults.white_led),
+            soft_knee: self.soft_knee.or(defaults.soft_knee),
+            max_milliamps: self.max_milliamps.or(defaults.max_milliamps),
+            milliamps_per_channel: self
+                .milliamps_per_channel
+                .or(defaults.milliamps_per_channel),
+            reverse: self.reverse.or(defaults.reverse),
+            offset: self.offset.or(defaults.offset),
+            mirror: self.mirror.or(defaults.mirror),
+            blackout: self.blackout.or_else(|| defaults.blackout.clone()),
+            dither: self.dither.or(defaults.dither),
+            calibration: self.calibration.or_else(|| defaults.calibration.clone()),
+            preview: self.preview.or(defaults.preview),
+        }
+    }
+
+    pub fn driver(&self) -> Backend {
+        self.driver.unwrap_or(Backend::Spidev)
+    }
+
+    pub fn chip(&self) -> Chip {
+        self.chip.unwrap_or(Chip::P9813)
+    }
+
+    /// The configured path or the usual one for the driver and chip.
+    pub fn path(&self) -> &str {
+        if let Some(path) = &self.path {
+            return path;
+        }
+        match (self.driver(), self.chip()) {
+            (Backend::Ftdi, _) => "Single RS232-HS",
+            (_, Chip::Pca9685) => "/dev/i2c-1",
+            _ => "/dev/spidev0.0",
+        }
+    }
+
+    pub fn num_leds(&self) -> usize {
+        self.num_leds.unwrap_or(DEFAULT_NUM_LEDS)
+    }
+
+    /// Number of pixels effects set, which is half the LEDs when mirrored.
+    pub fn pixels(&self) -> usize {
+        match self.mirror.unwrap_or_default() {
+            Mirror::Off => self.num_leds(),
+            Mirror::Ends | Mirror::Center => self.num_leds().div_ceil(2),
+        }
+    }
+
+    pub fn gamma(&self) -> f64 {
+        self.gamma.unwrap_or(DEFAULT_GAMMA)
+    }
+
+    /// Red, green and blue exponents.
+    pub fn gammas(&self) -> (f64, f64, f64) {
+        let gamma = self.gamma();
+        (
+            self.gamma_red.unwrap_or(gamma),
+            self.gamma_green.unwrap_or(gamma),
+            self.gamma_blue.unwrap_or(gamma),
+        )
+    }
+
+    /// Red, green and blue lightness curves.
+    pub fn curves(&self) -> (Curve, Curve, Curve) {
+        let curve = self.curve.unwrap_or_default();
+        (
+            self.curve_red.unwrap_or(curve),
+            self.curve_green.unwrap_or(curve),
+            self.curve_blue.unwrap_or(curve),
+        )
+    }
+
+    pub fn max_brightness(&self) -> u8 {
+        self.max_brightness.unwrap_or(255)
+    }
+
+    pub fn global_brightness(&self) -> u8 {
+        self.global_brightness.unwrap_or(31)
+    }
+
+    pub fn power_budget(&self) -> Option<PowerBudget> {
+        let mut budget = PowerBudget::new(self.max_milliamps?);
+        if let Some(milliamps) = self.milliamps_per_channel {
+            budget.milliamps_per_channel = milliamps;
+        }
+        Some(budget)
+    }
+
+    /// The gamma table for the configured exponents.
+    pub fn gamma_table(&self) -> GammaTable {
+        let (red, green, blue) = self.gammas();
+        let (red_curve, green_curve, blue_curve) = self.curves();
+        GammaTable::with_curves((red_curve, red), (green_curve, green), (blue_curve, blue))
+    }
+
+    /// Whether changing from `other` needs the driver reopened, rather than
+    /// only resizing the strip or replacing its gamma table.
+    pub fn needs_reopen(&self, other: &StripConfig) -> bool {
+        self.driver() != other.driver()
+            || self.chip() != other.chip()
+            || self.path() != other.path()
+            || self.spi_speed_hz != other.spi_speed_hz
+            || self.spi_chunk_size != other.spi_chunk_size
+            || self.color_order.unwrap_or_default() != other.color_order.unwrap_or_default()
+            || self.preview != other.preview
+    }
+
+    /// Settings that can't work, described for the user.
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        let (red, green, blue) = self.gammas();
+        for (name, gamma) in &[
+            ("gamma_red", red),
+            ("gamma_green", green),
+            ("gamma_blue", blue),
+        ] {
+            if !(*gamma > 0.0 && gamma.is_finite()) {
+                problems.push(format!("{} must be above 0, not {}", name, gamma));
+            }
+        }
+        if self.global_brightness() > 31 {
+            problems.push(format!(
+                "global_brightness must be from 0 to 31, not {}",
+                self.global_brightness()
+            ));
+        }
+        if let Some(knee) = self.soft_knee {
+            if !(0.0..=1.0).contains(&knee) {
+                problems.push(format!("soft_knee must be from 0 to 1, not {}", knee));
+            }
+        }
+        if let Some(budget) = self.power_budget() {
+            let idle = self.num_leds() as f64 * budget.idle_milliamps;
+            if budget.max_milliamps <= idle {
+                problems.push(format!(
+                    "max_milliamps {} is below the {} mA the LEDs draw while dark",
+                    budget.max_milliamps, idle
+                ));
+            }
+        }
+        for range in self.blackout.iter().flatten() {
+            if range.last >= self.num_leds() {
+                problems.push(format!(
+                    "blackout {} is past the end of the strip ({} LEDs)",
+                    range,
+                    self.num_leds()
+                ));
+            }
+        }
+        if let Some(path) = &self.calibration {
+            match Calibration::load(path) {
+                Ok(calibration) => {
+                    if calibration.scale.len() > self.num_leds() {
+                        problems.push(format!(
+                            "calibration has {} LEDs, more than the strip's {}",
+                            calibration.scale.len(),
+                            self.num_leds()
+                        ));
+                    }
+                }
+                Err(e) => problems.push(e.to_string()),
+            }
+        }
+        if self.num_leds() == 0 {
+            problems.push("num_leds must be above 0".to_string());
+        }
+        let needs_path = match self.driver() {
+            Backend::Spidev => true,
+            Backend::Ftdi | Backend::Mock | Backend::Sim | Backend::Terminal => false,
+        };
+        if needs_path && !Path::new(self.path()).exists() {
+            problems.push(format!("{} does not exist", self.path()));
+        }
+        problems
+    }
+
+    /// Open the strip's driver and wrap it in a `Strip`.
+    pub fn open(&self) -> Result<Strip> {
+        let chip = self.chip();
+        let mut driver = match self.driver() {
+            Backend::Spidev => {
+                let options = SpiOptions {
+                    speed_hz: self.spi_speed_hz,
+                    chunk_size: self.spi_chunk_size,
+                };
+                driver::open(chip, self.path(), options)
+            }
+            Backend::Ftdi => driver::open_ftdi(chip, self.path(), self.spi_speed_hz),
+            Backend::Mock => driver::open_mock(chip),
+            Backend::Sim => driver::open_sim(),
+            Backend::Terminal => Ok(Box::new(Terminal::new()) as Box<dyn LedDriver>),
+        }?;
+        let order = self.color_order.unwrap_or_default();
+        if order != ColorOrder::Rgb {
+            driver = Box::new(Reordered::new(driver, order));
+        }
+        // The preview shows colors before reordering, as the effect meant them.
+        if self.preview == Some(true) {
+            driver = Box::new(Tee::new(driver, Terminal::new()));
+        }
+        let mut strip = Strip::new(driver, self.num_leds(), self.gamma_table());
+        self.apply(&mut strip)?;
+        Ok(strip)
+    }
+
+    /// Apply the settings that don't need the driver reopened to `strip`.
+    pub fn apply(&self, strip: &mut Strip) -> Result<()> {
+        strip.set_gamma_table(self.gamma_table());
+        strip.set_max_brightness(self.max_brightness());
+        strip.set_global_brightness(self.global_brightness());
+        strip.set_white_point(self.white_point.unwrap_or(WhitePoint::NEUTRAL));
+        strip.set_white_extraction(
+            self.white_extraction.unwrap_or_default(),
+            self.white_led.unwrap_or(WhitePoint::NEUTRAL),
+        );
+        strip.set_soft_clip(SoftClip::new(self.soft_knee.unwrap_or(1.0)));
+        strip.set_power_budget(self.power_budget());
+        strip.set_reverse(self.reverse == Some(true));
+        strip.set_offset(self.offset.unwrap_or(0));
+        strip.set_mirror(self.mirror.unwrap_or_default());
+        strip.set_dither(self.dither == Some(true));
+        strip.set_blackout(self.blackout.as_deref().unwrap_or_default());
+        strip.set_calibration(match &self.calibration {
+            Some(path) => Calibration::load(path)?,
+            None => Calibration::default(),
+        });
+        strip.resize(self.num_leds())
+    }
+}
+
+fn parse_value<T: FromStr>(key: &str, value: &str) -> std::result::Result<T, String>
+where
+    T::Err: fmt::Display,
+{
+    value
+        .parse()
+        .map_err(|e| format!("bad {} {}: {}", key, value, e))
+}
+
+/// Parse the command line form, comma separated key=value pairs such as
+/// "path=/dev/spidev0.1,chip=apa102,leds=120".
+impl FromStr for StripConfig {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let mut strip = StripConfig::default();
+        for pair in s.split(',').filter(|p| !p.is_empty()) {
+            let mut kv = pair.splitn(2, '=');
+            let key = kv.next().unwrap_or_default().trim();
+            let value = kv
+                .next()
+                .ok_or_else(|| format!("expected key=value, got {}", pair))?
+                .trim();
+            match key {
+                "driver" => strip.driver = Some(value.parse()?),
+                "chip" => strip.chip = Some(value.parse()?),
+                "path" => strip.path = Some(value.to_string()),
+                "speed" => strip.spi_speed_hz = Some(parse_value(key, value)?),
+                "chunk" => strip.spi_chunk_size = Some(parse_value(key, value)?),
+                "leds" => strip.num_leds = Some(parse_value(key, value)?),
+                "order" => strip.color_order = Some(value.parse()?),
+                "gamma" => strip.gamma = Some(parse_value(key, value)?),
+                "gamma_r" => strip.gamma_red = Some(parse_value(key, value)?),
+                "gamma_g" => strip.gamma_green = Some(parse_value(key, value)?),
+                "gamma_b" => strip.gamma_blue = Some(parse_value(key, value)?),
+                "curve" => strip.curve = Some(value.parse()?),
+                "curve_r" => strip.curve_red = Some(value.parse()?),
+                "curve_g" => strip.curve_green = Some(value.parse()?),
+                "curve_b" => strip.curve_blue = Some(value.parse()?),
+                "max_brightness" => strip.max_brightness = Some(parse_value(key, value)?),
+                "global_brightness" => strip.global_brightness = Some(parse_value(key, value)?),
+                "white_point" => strip.white_point = Some(value.parse()?),
+                "white_extraction" => strip.white_extraction = Some(value.parse()?),
+                "white_led" => strip.white_led = Some(value.parse()?),
+                "soft_knee" => strip.soft_knee = Some(parse_value(key, value)?),
+                "max_milliamps" => strip.max_milliamps = Some(parse_value(key, value)?),
+                "milliamps_per_channel" => {
+                    strip.milliamps_per_channel = Some(parse_value(key, value)?)
+                }
+                "reverse" => strip.reverse = Some(parse_value(key, value)?),
+                "offset" => strip.offset = Some(parse_value(key, value)?),
+                "mirror" => strip.mirror = Some(value.parse()?),
+                // Commas separate keys, so ranges are separated with ';'.
+                "blackout" => {
+                    strip.blackout = Some(
+                        value
+                            .split(';')
+                            .map(str::parse)
+                            .collect::<std::result::Result<_, _>>()?,
+                    )
+                }
+                "dither" => strip.dither = Some(parse_value(key, value)?),
+                "calibration" => strip.calibration = Some(PathBuf::from(value)),
+                "preview" => strip.preview = Some(parse_value(key, value)?),
+                _ => return Err(format!("unknown strip key: {}", key)),
+            }
+        }
+        Ok(strip)
+    }
+}
+
+/// How the animation is spread over several strips.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Layout {
+    /// One animation shown on every strip.
+    #[default]
+    Same,
+    /// A separate animation sized to each strip.
+    Independent,
+}
+
+impl FromStr for Layout {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "same" => Ok(Layout::Same),
+            "independent" => Ok(Layout::Independent),
+            _ => Err(format!("unknown layout: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for Layout {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Layout::Same => "same",
+            Layout::Independent => "independent",
+        })
+    }
+}
+
+/// A named set of settings chosen together with "blink preset".
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Preset {
+    /// Effect to run.
+    pub effect: Option<String>,
+    /// Palette the effect draws its colors from.
+    pub palette: Option<String>,
+    /// Brightest any channel may be, on every strip.
+    pub max_brightness: Option<u8>,
+    /// Effect parameters, over those in `[params]`.
+    pub params: Params,
+}
+
+/// Names of the presets built in, for holidays; a preset in the config
+/// file of the same name is used over one.
+pub const BUILTIN_PRESETS: &[&str] =
+    &["christmas", "halloween", "hanukkah", "diwali", "valentines"];
+
+impl Preset {
+    /// The built-in preset called `name`.
+    pub fn builtin(name: &str) -> Option<Preset> {
+        let (effect, palette, params): (&str, Option<&str>, &[(&str, &str)]) = match name {
+            "christmas" => (
+                "chase",
+                None,
+                &[("colors", "red, lime"), ("spacing", "2"), ("steps", "3")],
+            ),
+            "halloween" => (
+                "twinkle",
+                Some("halloween"),
+                &[
+                    ("density", "0.4"),
+                    ("decay", "0.4"),
+                    ("background", "#0a0014"),
+                ],
+            ),
+            "hanukkah" => (
+                "running",
+                None,
+                &[
+                    ("colors", "blue, white"),
+                    ("wavelength", "24"),
+                    ("speeds", "4, 3"),
+                ],
+            ),
+            "diwali" => (
+                "candle + twinkle add",
+                Some("diwali"),
+                &[("density", "0.05")],
+            ),
+            "valentines" => ("breathe", Some("valentines"), &[("period", "4")]),
+            _ => return None,
+        };
+        let mut preset = Preset {
+            effect: Some(effect.to_string()),
+            palette: palette.map(str::to_string),
+            ..Preset::default()
+        };
+        for (key, value) in params {
+            preset.params.set(key, value);
+        }
+        Some(preset)
+    }
+}
+
+/// A named stretch of LEDs with its own effect or color, from a
+/// `[zones.<name>]` table.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ZoneConfig {
+    /// LEDs covered, counted along the animation.
+    pub leds: Option<PixelRange>,
+    /// Effect run in the zone; without it or a color the zone runs its own
+    /// copy of the main effect.
+    pub effect: Option<String>,
+    /// Color shown instead of an effect.
+    pub color: Option<Color>,
+    /// Palette the zone's effect draws from, in place of the main one.
+    pub palette: Option<String>,
+    /// From 0 to 1 [default: 1].
+    pub brightness: Option<f64>,
+}
+
+impl ZoneConfig {
+    /// Fill in settings missing here from `base`.
+    pub fn or(self, base: &ZoneConfig) -> ZoneConfig {
+        ZoneConfig {
+            leds: self.leds.or(base.leds),
+            effect: self.effect.or_else(|| base.effect.clone()),
+            color: self.color.or(base.color),
+            palette: self.palette.or_else(|| base.palette.clone()),
+            brightness: self.brightness.or(base.brightness),
+        }
+    }
+
+    /// Brightness to show the zone at.
+    pub fn brightness(&self) -> f64 {
+        self.brightness.unwrap_or(1.0)
+    }
+
+    /// Settings that can't work, described for the user.
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.leds.is_none() {
+            problems.push("no leds given".to_string());
+        }
+        if self.effect.is_some() && self.color.is_some() {
+            problems.push("has both an effect and a color".to_string());
+        }
+        if !(0.0..=1.0).contains(&self.brightness()) {
+            problems.push(format!(
+                "brightness must be from 0 to 1, not {}",
+                self.brightness()
+            ));
+        }
+        problems
+    }
+}
+
+/// Parse the command line form, comma separated key=value pairs such as
+/// "leds=0-29,effect=rainbow,brightness=0.5".
+impl FromStr for ZoneConfig {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let mut zone = ZoneConfig::default();
+        for pair in s.split(',').filter(|p| !p.is_empty()) {
+            let mut kv = pair.splitn(2, '=');
+            let key = kv.next().unwrap_or_default().trim();
+            let value = kv
+                .next()
+                .ok_or_else(|| format!("expected key=value, got {}", pair))?
+                .trim();
+            match key {
+                "leds" => zone.leds = Some(value.parse()?),
+                "effect" => {
+                    effect::check(value)?;
+                    zone.effect = Some(value.to_string());
+                }
+                "color" => zone.color = Some(value.parse()?),
+                "palette" => zone.palette = Some(value.to_string()),
+                "brightness" => zone.brightness = Some(parse_value(key, value)?),
+                _ => return Err(format!("unknown zone key: {}", key)),
+            }
+        }
+        Ok(zone)
+    }
+}
+
+/// The contents of a configuration file.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Latitude used for sunrise calculations.
+    pub latitude: Option<f64>,
+    /// Longitude used for sunrise calculations.
+    pub longitude: Option<f64>,
+    /// Effect run by default.
+    pub effect: Option<String>,
+    /// Palette effects draw their colors from; without one they use the
+    /// full color wheel.
+    pub palette: Option<String>,
+    /// Palette files, relative to the config file, each named after the
+    /// file without its extension.
+    pub palette_files: Vec<PathBuf>,
+    /// Palettes given as lists of colors, spaced evenly.
+    pub palettes: BTreeMap<String, Vec<Color>>,
+    pub layout: Option<Layout>,
+    /// Seconds to fade over when the effect, color or power changes; 0
+    /// cuts straight over.
+    pub transition_seconds: Option<f64>,
+    /// Multiplies how fast every effect runs.
+    pub speed: Option<f64>,
+    /// Parameters for the effects, each read by those that take it;
+    /// "palette" picks the palette.
+    pub params: Params,
+    /// Defaults applied to every strip.
+    pub strip: StripConfig,
+    /// Strips to drive; when empty a single strip uses `strip`.
+    pub strips: Vec<StripConfig>,
+    pub schedule: Schedule,
+    /// Presets by name, from `[preset.<name>]` tables.
+    pub preset: BTreeMap<String, Preset>,
+    /// Effects cycled through in place of `effect`, unless an effect or
+    /// preset is chosen.
+    pub playlist: Option<Playlist>,
+    /// Zones by name, from `[zones.<name>]` tables.
+    pub zones: BTreeMap<String, ZoneConfig>,
+    /// Palettes moved through in place of `palette`, unless one is chosen
+    /// on the command line.
+    pub palette_cycle: Option<PaletteCycle>,
+}
+
+/// Write per-channel gamma exponents for strip `index` into the config file
+/// at `path`, keeping its comments and layout. Files without a `[[strips]]`
+/// array get them in the `[strip]` defaults.
+pub fn write_gammas<P: AsRef<Path>>(path: P, index: usize, gammas: (f64, f64, f64)) -> Result<()> {
+    let path = path.as_ref();
+    let name = path.display().to_string();
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(Error::open(&name, e)),
+    };
+    let config_error = |message: String| Error::Config {
+        path: name.clone(),
+        message,
+    };
+    let mut doc: DocumentMut = text
+        .parse()
+        .map_err(|e: TomlError| config_error(e.to_string()))?;
+    let table = match doc.get_mut("strips") {
+        Some(item) => item
+            .as_array_of_tables_mut()
+            .and_then(|strips| strips.get_mut(index))
+            .ok_or_else(|| config_error(format!("no strip {} in [[strips]]", index)))?,
+        None => doc
+            .entry("strip")
+            .or_insert_with(toml_edit::table)
+            .as_table_mut()
+            .ok_or_else(|| config_error("strip is not a table".to_string()))?,
+    };
+    // Two decimals is finer than anyone can see.
+    let round = |g: f64| (g * 100.0).round() / 100.0;
+    table["gamma_red"] = toml_edit::value(round(gammas.0));
+    table["gamma_green"] = toml_edit::value(round(gammas.1));
+    table["gamma_blue"] = toml_edit::value(round(gammas.2));
+    fs::write(path, doc.to_string()).map_err(|e| Error::open(&name, e))
+}
+
+/// Effect run when none is configured.
+pub const DEFAULT_EFFECT: &str = "rainbow";
+
+/// Seconds a change of effect, color or power fades over when none is
+/// configured.
+pub const DEFAULT_TRANSITION_SECONDS: f64 = 1.0;
+
+impl Config {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Config> {
+        let path = path.as_ref();
+        let name = path.display().to_string();
+        let text = fs::read_to_string(path).map_err(|e| Error::open(&name, e))?;
+        let config: Config = toml::from_str(&text).map_err(|e| Error::Config {
+            path: name.clone(),
+            message: e.to_string(),
+        })?;
+        let effects = config
+            .effect
+            .iter()
+            .chain(config.preset.values().filter_map(|p| p.effect.as_ref()))
+            .chain(
+                config
+                    .playlist
+                    .iter()
+                    .flat_map(|p| &p.effects)
+                    .map(|e| &e.effect),
+            )
+            .chain(config.zones.values().filter_map(|z| z.effect.as_ref()));
+        for effect in effects {
+            if let Err(message) = effect::check(effect) {
+                return Err(Error::Config {
+                    path: name,
+                    message,
+                });
+            }
+        }
+        if config
+            .playlist
+            .as_ref()
+            .is_some_and(|p| p.effects.is_empty())
+        {
+            return Err(Error::Config {
+                path: name,
+                message: "playlist has no effects".to_string(),
+            });
+        }
+        if config
+            .palette_cycle
+            .as_ref()
+            .is_some_and(|c| c.palettes.is_empty())
+        {
+            return Err(Error::Config {
+                path: name,
+                message: "palette_cycle has no palettes".to_string(),
+            });
+        }
+        if let Some((palette, _)) = config.palettes.iter().find(|(_, c)| c.is_empty()) {
+            return Err(Error::Config {
+                path: name,
+                message: format!("palette {} has no colors", palette),
+            });
+        }
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::palette::Palette;
+
+    /// A path for `name` in a directory of its own under the system's
+    /// temporary directory.
+    fn temp_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("led-strip-config-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir.join(name)
+    }
+
+    #[test]
+    fn writes_only_the_gammas() {
+        let path = temp_path("gammas.toml");
+        let text = "\
+# Porch lights.
+latitude = 45.5 # roughly
+
+[strip]
+# Measured by eye.
+gamma = 2.2
+num_leds = 30
+";
+        fs::write(&path, text).unwrap();
+        write_gammas(&path, 0, (2.0, 2.345, 2.6)).unwrap();
+        let gammas = "gamma_red = 2.0\ngamma_green = 2.35\ngamma_blue = 2.6\n";
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            text.to_string() + gammas
+        );
+        // Written again, they change in place.
+        write_gammas(&path, 0, (1.8, 2.35, 2.6)).unwrap();
+        let again = text.to_string() + &gammas.replace("2.0", "1.8");
+        assert_eq!(fs::read_to_string(&path).unwrap(), again);
+
+        let text = "[[strips]]\nnum_leds = 30\n\n[[strips]]\n# Shelf.\nnum_leds = 8\n";
+        fs::write(&path, text).unwrap();
+        write_gammas(&path, 1, (2.0, 2.35, 2.6)).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            text.to_string() + gammas
+        );
+        assert!(write_gammas(&path, 2, (2.0, 2.0, 2.0)).is_err());
+        fs::remove_file(&path).unwrap();
+    }
+
+    /// The example at the top of this file.
+    fn example() -> String {
+        include_str!("config.rs")
+            .lines()
+            .skip_while(|line| *line != "//! ```toml")
+            .skip(1)
+            .take_while(|line| *line != "//! ```")
+            .map(|line| line.trim_start_matches("//!").trim_start_matches(' '))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn loads_the_example() {
+        let path = temp_path("example.toml");
+        fs::write(&path, example()).unwrap();
+        let config = Config::load(&path).unwrap();
+        assert_eq!(config.latitude, Some(45.52));
+        assert_eq!(config.effect.as_deref(), Some("rainbow"));
+        assert_eq!(config.strip.chip, Some(Chip::Apa102));
+        assert_eq!(config.strip.curve_blue, Some(Curve::Cie));
+        assert_eq!(config.strips[0].num_leds(), 30);
+        assert_eq!(config.strips[0].blackout.as_ref().unwrap().len(), 2);
+        assert_eq!(config.preset["movie"].max_brightness, Some(64));
+        assert_eq!(
+            config.params.get("palette", String::new()).unwrap(),
+            "ocean"
+        );
+        assert_eq!(config.playlist.unwrap().effects.len(), 2);
+        assert_eq!(config.zones["shelf"].brightness, Some(0.5));
+        assert_eq!(config.schedule.ramp_easing, Easing::Sine);
+
+        for text in &[
+            "brightnes = 3",
+            "[strip]\nnum_led = 30",
+            "[[strips]]\npath = \"/dev/spidev0.1\"\ncurve = \"log\"",
+            "[schedule]\nsunset = 3",
+            "[preset.movie]\ncolor = \"red\"",
+            "effect = \"rainbow +\"",
+        ] {
+            fs::write(&path, text).unwrap();
+            assert!(Config::load(&path).is_err(), "{}", text);
+        }
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn builtin_presets_run() {
+        for name in BUILTIN_PRESETS {
+            let preset = Preset::builtin(name).unwrap();
+            let effect = preset.effect.as_deref().unwrap();
+            let palette = preset
+                .palette
+                .as_deref()
+                .map(|p| Palette::builtin(p).unwrap());
+            if let Err(e) = effect::create(effect, palette, &preset.params) {
+                panic!("{}: {}", name, e);
+            }
+            assert!(
+                effect::unused_params(&[effect], &preset.params).is_empty(),
+                "{}",
+                name
+            );
+        }
+        assert!(Preset::builtin("easter").is_none());
+    }
+
+    #[test]
+    fn command_line_goes_over_the_file() {
+        let file: StripConfig =
+            toml::from_str("num_leds = 30\ngamma = 2.4\nchip = \"apa102\"").unwrap();
+        let command_line = StripConfig {
+            num_leds: Some(50),
+            dither: Some(true),
+            ..StripConfig::default()
+        };
+        let defaults = command_line.or(&file);
+        assert_eq!(defaults.num_leds(), 50);
+        assert_eq!(defaults.gamma(), 2.4);
+        assert_eq!(defaults.chip(), Chip::Apa102);
+        assert_eq!(defaults.dither, Some(true));
+        assert_eq!(defaults.reverse, None);
+
+        // A strip of its own keeps its keys over both.
+        let own = StripConfig {
+            num_leds: Some(8),
+            ..StripConfig::default()
+        };
+        let strip = own.or(&defaults);
+        assert_eq!((strip.num_leds(), strip.gamma()), (8, 2.4));
+    }
+
+    #[test]
+    fn parses_strip_specs() {
+        let spec = "driver=mock,chip=apa102,path=/dev/spidev0.1,speed=8000000,chunk=4096,\
+                    leds=120,order=bgr,gamma=2.2,gamma_r=2.0,gamma_g=2.4,gamma_b=2.6,curve=cie,\
+                    curve_r=gamma,curve_g=cie,curve_b=gamma,max_brightness=200,\
+                    global_brightness=15,white_point=2700K,white_extraction=half,\
+                    white_led=4000,soft_knee=0.8,max_milliamps=2000,\
+                    milliamps_per_channel=20,reverse=true,offset=3,mirror=center,\
+                    blackout=0-4;10,dither=true,calibration=cal.toml,preview=false";
+        assert_eq!(
+            spec.parse::<StripConfig>().unwrap(),
+            StripConfig {
+                driver: Some(Backend::Mock),
+                chip: Some(Chip::Apa102),
+                path: Some("/dev/spidev0.1".to_string()),
+                spi_speed_hz: Some(8_000_000),
+                spi_chunk_size: Some(4096),
+                num_leds: Some(120),
+                color_order: Some(ColorOrder::Bgr),
+                gamma: Some(2.2),
+                gamma_red: Some(2.0),
+                gamma_green: Some(2.4),
+                gamma_blue: Some(2.6),
+                curve: Some(Curve::Cie),
+                curve_red: Some(Curve::Gamma),
+                curve_green: Some(Curve::Cie),
+                curve_blue: Some(Curve::Gamma),
+                max_brightness: Some(200),
+                global_brightness: Some(15),
+                white_point: Some(WhitePoint::new(2700.0)),
+                white_extraction: Some(WhiteExtraction::Half),
+                white_led: Some(WhitePoint::new(4000.0)),
+                soft_knee: Some(0.8),
+                max_milliamps: Some(2000.0),
+                milliamps_per_channel: Some(20.0),
+                reverse: Some(true),
+                offset: Some(3),
+                mirror: Some(Mirror::Center),
+                blackout: Some(vec![
+                    PixelRange { first: 0, last: 4 },
+                    PixelRange {
+                        first: 10,
+                        last: 10
+                    },
+                ]),
+                dither: Some(true),
+                calibration: Some(PathBuf::from("cal.toml")),
+                preview: Some(false),
+            }
+        );
+        // Spaces around keys and values, and empty pairs, are passed over.
+        assert_eq!(
+            " leds = 8 ,,chip= ws2812,".parse(),
+            Ok(StripConfig {
+                num_leds: Some(8),
+                chip: Some(Chip::Ws2812),
+                ..StripConfig::default()
+            })
+        );
+        assert_eq!("".parse(), Ok(StripConfig::default()));
+
+        let error = |spec: &str| spec.parse::<StripConfig>().unwrap_err();
+        assert_eq!(
+            error("leds=8,brightness=3"),
+            "unknown strip key: brightness"
+        );
+        assert_eq!(error("leds=8,reverse"), "expected key=value, got reverse");
+        assert!(error("leds=eight").starts_with("bad leds eight: "));
+        assert!(error("global_brightness=300").starts_with("bad global_brightness 300: "));
+        assert_eq!(error("chip=ws2811"), "unknown chip: ws2811");
+        assert_eq!(error("mirror=both"), "unknown mirror mode: both");
+        // Ranges after a comma would be taken for a key.
+        assert_eq!(error("blackout=0-4,10"), "expected key=value, got 10");
+        assert_eq!(error("blackout=4-0"), "pixel range 4-0 runs backwards");
+    }
+
+    #[test]
+    fn reports_strip_problems() {
+        let problems = |spec: &str| spec.parse::<StripConfig>().unwrap().problems();
+        assert_eq!(problems("driver=mock"), Vec::<String>::new());
+        assert_eq!(
+            problems("driver=mock,gamma=0,gamma_g=2,global_brightness=32,soft_knee=1.5"),
+            [
+                "gamma_red must be above 0, not 0",
+                "gamma_blue must be above 0, not 0",
+                "global_brightness must be from 0 to 31, not 32",
+                "soft_knee must be from 0 to 1, not 1.5",
+            ]
+        );
+        assert_eq!(
+            problems("driver=mock,leds=10,max_milliamps=10,blackout=2-3;10"),
+            [
+                "max_milliamps 10 is below the 10 mA the LEDs draw while dark",
+                "blackout 10 is past the end of the strip (10 LEDs)",
+            ]
+        );
+        assert_eq!(problems("driver=mock,leds=0"), ["num_leds must be above 0"]);
+        assert_eq!(
+            problems("path=/dev/no-such-spidev"),
+            ["/dev/no-such-spidev does not exist"]
+        );
+
+        let path = temp_path("calibration.toml");
+        let mut spec = format!("driver=mock,leds=2,calibration={}", path.display());
+        assert_eq!(problems(&spec).len(), 1, "missing file");
+        Calibration {
+            scale: vec![[1.0; 3]; 3],
+            dead: Vec::new(),
+        }
+        .save(&path)
+        .unwrap();
+        assert_eq!(
+            problems(&spec),
+            ["calibration has 3 LEDs, more than the strip's 2"]
+        );
+        spec = spec.replace("leds=2", "leds=3");
+        assert_eq!(problems(&spec), Vec::<String>::new());
+        fs::remove_file(&path).unwrap();
+    }
+}
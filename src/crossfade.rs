@@ -0,0 +1,104 @@
+//! Fades from the frame shown before a change to the frames after it.
+
+use crate::color::Color16;
+use crate::easing::Easing;
+
+use std::time::{Duration, Instant};
+
+/// Blends each new frame with a snapshot taken when a change happened,
+/// moving from the snapshot to the new frames over the fade's duration.
+#[derive(Debug, Clone)]
+pub struct Crossfade {
+    duration: Duration,
+    easing: Easing,
+    from: Vec<Color16>,
+    started: Option<Instant>,
+}
+
+impl Crossfade {
+    pub fn new(duration: Duration) -> Self {
+        Crossfade {
+            duration,
+            easing: Easing::Sine,
+            from: Vec::new(),
+            started: None,
+        }
+    }
+
+    /// Blend along `easing` instead of the default sine curve.
+    pub fn with_easing(mut self, easing: Easing) -> Self {
+        self.easing = easing;
+        self
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// Change the length of fades, including one already running.
+    pub fn set_duration(&mut self, duration: Duration) {
+        self.duration = duration;
+    }
+
+    /// Fade from `from`, the frame last shown, starting at `now`. Pixels
+    /// past its end fade in from black.
+    pub fn start(&mut self, from: Vec<Color16>, now: Instant) {
+        if self.duration > Duration::ZERO {
+            self.from = from;
+            self.started = Some(now);
+        } else {
+            self.started = None;
+        }
+    }
+
+    /// Blend `frame` with the frame faded from, returning whether the fade
+    /// is still running at `now`.
+    pub fn apply(&mut self, frame: &mut [Color16], now: Instant) -> bool {
+        let started = match self.started {
+            Some(started) => started,
+            None => return false,
+        };
+        let t = now.saturating_duration_since(started).as_secs_f64() / self.duration.as_secs_f64();
+        if t.is_nan() || t >= 1.0 {
+            self.started = None;
+            self.from.clear();
+            return false;
+        }
+        let t = self.easing.apply(t);
+        for (i, color) in frame.iter_mut().enumerate() {
+            let from = self.from.get(i).copied().unwrap_or_default();
+            *color = from.lerp(*color, t);
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fades_over_the_duration() {
+        let start = Instant::now();
+        let white = Color16::new(65535, 65535, 65535);
+        let mut fade = Crossfade::new(Duration::from_secs(2));
+        fade.start(vec![white], start);
+
+        let mut frame = vec![Color16::default(); 2];
+        assert!(fade.apply(&mut frame, start));
+        assert_eq!(frame, vec![white, Color16::default()]);
+
+        let mut frame = vec![Color16::default(), white];
+        assert!(fade.apply(&mut frame, start + Duration::from_secs(1)));
+        // Halfway through, both pixels are halfway there.
+        assert!(frame.iter().all(|c| (32767..=32768).contains(&c.red)));
+
+        let mut frame = vec![Color16::default()];
+        assert!(!fade.apply(&mut frame, start + Duration::from_secs(2)));
+        assert_eq!(frame, vec![Color16::default()]);
+
+        let mut cut = Crossfade::new(Duration::ZERO);
+        cut.start(vec![white], start);
+        assert!(!cut.apply(&mut frame, start));
+    }
+}
@@ -0,0 +1,104 @@
+use super::spi::{bus_error, SpidevBus};
+use super::{LedDriver, DEFAULT_SPI_HZ};
+use crate::color::Color;
+use crate::error::Result;
+
+use embedded_hal::spi::SpiBus;
+
+/// Driver for APA102 and the compatible SK9822 chip.
+pub struct Apa102<B = SpidevBus> {
+    spi: B,
+    brightness: u8,
+    bytes: Vec<u8>,
+}
+
+impl<B: SpiBus> Apa102<B> {
+    pub fn new(spi: B) -> Self {
+        Apa102 {
+            spi,
+            brightness: 31,
+            bytes: Vec::new(),
+        }
+    }
+}
+
+impl Apa102 {
+    /// Open and configure the SPI device at `path`.
+    pub fn open(path: &str) -> Result<Self> {
+        Ok(Apa102::new(SpidevBus::open(path, DEFAULT_SPI_HZ)?))
+    }
+}
+
+impl<B: SpiBus + Send> LedDriver for Apa102<B> {
+    fn write_frame(&mut self, pixels: &[Color]) -> Result<()> {
+        self.bytes.clear();
+        // Start frame.
+        self.bytes.extend_from_slice(&[0x00; 4]);
+        for pixel in pixels {
+            self.bytes.extend_from_slice(&[
+                0xE0 | self.brightness,
+                pixel.blue,
+                pixel.green,
+                pixel.red,
+            ]);
+        }
+        // SK9822 latches on a zero frame; APA102 then needs half a clock per
+        // LED to push the data through to the end of the strip.
+        self.bytes.extend_from_slice(&[0x00; 4]);
+        self.bytes
+            .extend(std::iter::repeat_n(0x00, pixels.len().div_ceil(16)));
+        trace!("bytes: {:02x?}", self.bytes);
+        self.spi.write(&self.bytes).map_err(bus_error)
+    }
+
+    /// Clamped to 31; lower levels dim the LEDs by their current, leaving
+    /// every PWM step for the colors.
+    fn set_global_brightness(&mut self, brightness: u8) {
+        self.brightness = brightness.min(31);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::driver::{Chip, MockBus};
+
+    #[test]
+    fn pixel_wire_format() {
+        let bus = MockBus::validating(Chip::Apa102);
+        let mut driver = Apa102::new(bus.clone());
+        driver
+            .write_frame(&[Color::new(0x12, 0x34, 0x56), Color::new(0xff, 0, 0)])
+            .unwrap();
+        #[rustfmt::skip]
+        let expected = vec![
+            0x00, 0x00, 0x00, 0x00,
+            0xff, 0x56, 0x34, 0x12,
+            0xff, 0x00, 0x00, 0xff,
+            0x00, 0x00, 0x00, 0x00,
+            0x00,
+        ];
+        assert_eq!(bus.last_frame().unwrap(), expected);
+
+        driver.set_global_brightness(7);
+        driver.write_frame(&[Color::new(1, 2, 3)]).unwrap();
+        assert_eq!(bus.last_frame().unwrap()[4..8], [0xe7, 3, 2, 1]);
+        driver.set_global_brightness(40);
+        driver.write_frame(&[Color::new(1, 2, 3)]).unwrap();
+        assert_eq!(bus.last_frame().unwrap()[4], 0xff);
+    }
+
+    #[test]
+    fn end_frame_grows_with_the_strip() {
+        let bus = MockBus::validating(Chip::Apa102);
+        let mut driver = Apa102::new(bus.clone());
+        for (leds, end) in &[(0, 4), (16, 5), (17, 6), (120, 12)] {
+            driver
+                .write_frame(&vec![Color::new(1, 2, 3); *leds])
+                .unwrap();
+            let frame = bus.last_frame().unwrap();
+            assert_eq!(frame.len(), 4 + 4 * leds + end, "{} leds", leds);
+            assert!(frame[4 + 4 * leds..].iter().all(|b| *b == 0));
+        }
+    }
+}
@@ -0,0 +1,27 @@
+//! Backend for FT232H/FT2232H USB adapters with the MPSSE engine in SPI mode.
+//!
+//! Wire SCK to D0 and MOSI to D1.
+
+use super::{from_bus, Chip, LedDriver};
+use crate::error::{Error, Result};
+
+use ftdi_embedded_hal::libftd2xx::{Ft2232h, Ft232h};
+use ftdi_embedded_hal::FtHal;
+use std::fmt::Debug;
+
+fn ftdi_error<E: Debug>(e: E) -> Error {
+    Error::Device(format!("FTDI: {:?}", e))
+}
+
+/// Open the FTDI adapter whose USB description is `description` (for
+/// example "Single RS232-HS" for an FT232H or "Dual RS232-HS A" for the
+/// first port of an FT2232H) and drive `chip` from it at `speed_hz`.
+pub fn open(chip: Chip, description: &str, speed_hz: u32) -> Result<Box<dyn LedDriver>> {
+    if let Ok(device) = Ft232h::with_description(description) {
+        let hal = FtHal::init_freq(device, speed_hz).map_err(ftdi_error)?;
+        return from_bus(chip, hal.spi().map_err(ftdi_error)?);
+    }
+    let device = Ft2232h::with_description(description).map_err(ftdi_error)?;
+    let hal = FtHal::init_freq(device, speed_hz).map_err(ftdi_error)?;
+    from_bus(chip, hal.spi().map_err(ftdi_error)?)
+}
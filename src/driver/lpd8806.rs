@@ -0,0 +1,85 @@
+use super::spi::{bus_error, SpidevBus};
+use super::{LedDriver, DEFAULT_SPI_HZ};
+use crate::color::Color;
+use crate::error::Result;
+
+use embedded_hal::spi::SpiBus;
+
+/// Driver for the LPD8806 chip.
+///
+/// Each channel carries 7 bits of color with the high bit set, sent in
+/// green, red, blue order.
+pub struct Lpd8806<B = SpidevBus> {
+    spi: B,
+    bytes: Vec<u8>,
+}
+
+impl<B: SpiBus> Lpd8806<B> {
+    pub fn new(spi: B) -> Self {
+        Lpd8806 {
+            spi,
+            bytes: Vec::new(),
+        }
+    }
+}
+
+impl Lpd8806 {
+    /// Open and configure the SPI device at `path`.
+    pub fn open(path: &str) -> Result<Self> {
+        Ok(Lpd8806::new(SpidevBus::open(path, DEFAULT_SPI_HZ)?))
+    }
+}
+
+impl<B: SpiBus + Send> LedDriver for Lpd8806<B> {
+    fn write_frame(&mut self, pixels: &[Color]) -> Result<()> {
+        // One zero byte latches 32 LEDs; the number of latch bytes therefore
+        // grows with the length of the strip.
+        let latch = pixels.len().div_ceil(32);
+        self.bytes.clear();
+        // Reset the chips so the first byte is taken as the first pixel.
+        self.bytes.extend(std::iter::repeat_n(0x00, latch));
+        for pixel in pixels {
+            self.bytes.extend_from_slice(&[
+                0x80 | (pixel.green >> 1),
+                0x80 | (pixel.red >> 1),
+                0x80 | (pixel.blue >> 1),
+            ]);
+        }
+        self.bytes.extend(std::iter::repeat_n(0x00, latch));
+        trace!("bytes: {:02x?}", self.bytes);
+        self.spi.write(&self.bytes).map_err(bus_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::driver::{Chip, MockBus};
+
+    #[test]
+    fn pixel_wire_format() {
+        let bus = MockBus::validating(Chip::Lpd8806);
+        let mut driver = Lpd8806::new(bus.clone());
+        driver
+            .write_frame(&[Color::new(0xff, 0x80, 0x01), Color::new(0, 0, 0)])
+            .unwrap();
+        // Green, red then blue, as their top seven bits.
+        let expected = vec![0x00, 0xc0, 0xff, 0x80, 0x80, 0x80, 0x80, 0x00];
+        assert_eq!(bus.last_frame().unwrap(), expected);
+    }
+
+    #[test]
+    fn latch_grows_with_the_strip() {
+        let bus = MockBus::validating(Chip::Lpd8806);
+        let mut driver = Lpd8806::new(bus.clone());
+        for (leds, latch) in &[(32, 1), (33, 2), (160, 5)] {
+            driver
+                .write_frame(&vec![Color::new(9, 9, 9); *leds])
+                .unwrap();
+            let frame = bus.last_frame().unwrap();
+            assert_eq!(frame.len(), 3 * leds + 2 * latch, "{} leds", leds);
+            assert!(frame[..*latch].iter().all(|b| *b == 0));
+            assert!(frame[frame.len() - latch..].iter().all(|b| *b == 0));
+        }
+    }
+}
@@ -0,0 +1,316 @@
+//! An in-memory SPI bus for running without hardware.
+
+use super::ws2812::LATCH_BYTES;
+use super::Chip;
+
+use embedded_hal::spi::{self, ErrorKind, ErrorType, SpiBus};
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+/// Number of frames a `MockBus` keeps before discarding the oldest.
+const DEFAULT_CAPACITY: usize = 64;
+
+/// Error from a `MockBus` when a written frame is malformed.
+#[derive(Debug)]
+pub struct MockError(pub String);
+
+impl fmt::Display for MockError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for MockError {}
+
+impl spi::Error for MockError {
+    fn kind(&self) -> ErrorKind {
+        ErrorKind::Other
+    }
+}
+
+struct Recording {
+    frames: VecDeque<Vec<u8>>,
+    count: usize,
+}
+
+/// An `SpiBus` that records every write as a frame.
+///
+/// Clones share the same recording so a test can keep a handle while the
+/// driver owns the bus. When built for a chip each frame is checked against
+/// that chip's framing and rejected with an error if it is malformed.
+#[derive(Clone)]
+pub struct MockBus {
+    chip: Option<Chip>,
+    capacity: usize,
+    recording: Arc<Mutex<Recording>>,
+}
+
+impl MockBus {
+    /// A bus that records frames without checking them.
+    pub fn new() -> Self {
+        MockBus {
+            chip: None,
+            capacity: DEFAULT_CAPACITY,
+            recording: Arc::new(Mutex::new(Recording {
+                frames: VecDeque::new(),
+                count: 0,
+            })),
+        }
+    }
+
+    /// A bus that checks every frame against the framing of `chip`.
+    pub fn validating(chip: Chip) -> Self {
+        MockBus {
+            chip: Some(chip),
+            ..MockBus::new()
+        }
+    }
+
+    /// Keep at most `capacity` of the most recent frames.
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        self
+    }
+
+    /// The retained frames, oldest first.
+    pub fn frames(&self) -> Vec<Vec<u8>> {
+        self.recording
+            .lock()
+            .unwrap()
+            .frames
+            .iter()
+            .cloned()
+            .collect()
+    }
+
+    pub fn last_frame(&self) -> Option<Vec<u8>> {
+        self.recording.lock().unwrap().frames.back().cloned()
+    }
+
+    /// Total number of frames written, including discarded ones.
+    pub fn frame_count(&self) -> usize {
+        self.recording.lock().unwrap().count
+    }
+}
+
+impl Default for MockBus {
+    fn default() -> Self {
+        MockBus::new()
+    }
+}
+
+/// Check that `bytes` is a well formed frame for `chip`.
+pub fn validate(chip: Chip, bytes: &[u8]) -> Result<(), String> {
+    match chip {
+        Chip::P9813 => validate_p9813(bytes),
+        Chip::Apa102 => validate_apa102(bytes),
+        Chip::Lpd8806 => validate_lpd8806(bytes),
+        Chip::Ws2812 => validate_single_wire("ws2812", 3, bytes),
+        Chip::Sk6812 => validate_single_wire("sk6812", 4, bytes),
+        Chip::Pca9685 => Err("pca9685: driven over I2C, not SPI".to_string()),
+    }
+}
+
+fn validate_p9813(bytes: &[u8]) -> Result<(), String> {
+    if !bytes.len().is_multiple_of(4) || bytes.len() < 12 {
+        return Err(format!("p9813: bad frame length {}", bytes.len()));
+    }
+    if bytes[..4].iter().any(|b| *b != 0) {
+        return Err("p9813: missing start frame".to_string());
+    }
+    if bytes[bytes.len() - 8..].iter().any(|b| *b != 0) {
+        return Err("p9813: missing end frame".to_string());
+    }
+    for (i, pixel) in bytes[4..bytes.len() - 8].chunks(4).enumerate() {
+        let (flag, blue, green, red) = (pixel[0], pixel[1], pixel[2], pixel[3]);
+        let expected = !(((red & 0xC0) >> 6) | ((green & 0xC0) >> 4) | ((blue & 0xC0) >> 2));
+        if flag != expected {
+            return Err(format!(
+                "p9813: pixel {} has flag {:02x}, expected {:02x}",
+                i, flag, expected
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn validate_apa102(bytes: &[u8]) -> Result<(), String> {
+    if bytes.len() < 8 || bytes[..4].iter().any(|b| *b != 0) {
+        return Err("apa102: missing start frame".to_string());
+    }
+    let body = &bytes[4..];
+    let pixels = body
+        .chunks(4)
+        .take_while(|p| p.len() == 4 && p[0] & 0xE0 == 0xE0);
+    let num_leds = pixels.count();
+    let end = &body[num_leds * 4..];
+    if end.len() != 4 + num_leds.div_ceil(16) || end.iter().any(|b| *b != 0) {
+        return Err(format!("apa102: bad end frame for {} leds", num_leds));
+    }
+    Ok(())
+}
+
+fn validate_lpd8806(bytes: &[u8]) -> Result<(), String> {
+    let latch = bytes.iter().take_while(|b| **b == 0).count();
+    let body = &bytes[latch..];
+    let data = body.iter().take_while(|b| **b & 0x80 != 0).count();
+    if !data.is_multiple_of(3) {
+        return Err(format!("lpd8806: {} data bytes is not whole pixels", data));
+    }
+    let expected = (data / 3).div_ceil(32);
+    if latch != expected || body[data..].len() != expected || body[data..].iter().any(|b| *b != 0) {
+        return Err(format!("lpd8806: bad latch for {} leds", data / 3));
+    }
+    Ok(())
+}
+
+/// Check a WS2812 style frame of pixels `channels` bytes long: a low byte,
+/// every bit as a `110` or `100` symbol, then the latch.
+fn validate_single_wire(name: &str, channels: usize, bytes: &[u8]) -> Result<(), String> {
+    if bytes.len() < 1 + LATCH_BYTES || bytes[0] != 0 {
+        return Err(format!("{}: missing leading low byte", name));
+    }
+    let (body, latch) = bytes[1..].split_at(bytes.len() - 1 - LATCH_BYTES);
+    if latch.iter().any(|b| *b != 0) {
+        return Err(format!("{}: missing {} byte latch", name, LATCH_BYTES));
+    }
+    if !body.len().is_multiple_of(3 * channels) {
+        return Err(format!(
+            "{}: {} bytes is not whole pixels",
+            name,
+            body.len()
+        ));
+    }
+    for (i, encoded) in body.chunks(3).enumerate() {
+        let bits = u32::from(encoded[0]) << 16 | u32::from(encoded[1]) << 8 | u32::from(encoded[2]);
+        // Each symbol starts high and ends low.
+        if (0..8).any(|k| (bits >> (3 * k)) & 0b101 != 0b100) {
+            return Err(format!(
+                "{}: byte {} of pixel {} has a bad symbol in {:06x}",
+                name,
+                i % channels,
+                i / channels,
+                bits
+            ));
+        }
+    }
+    Ok(())
+}
+
+impl ErrorType for MockBus {
+    type Error = MockError;
+}
+
+impl SpiBus for MockBus {
+    fn read(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        words.iter_mut().for_each(|w| *w = 0);
+        Ok(())
+    }
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        if let Some(chip) = self.chip {
+            validate(chip, words).map_err(MockError)?;
+        }
+        let mut recording = self.recording.lock().unwrap();
+        if recording.frames.len() == self.capacity {
+            recording.frames.pop_front();
+        }
+        recording.frames.push_back(words.to_vec());
+        recording.count += 1;
+        Ok(())
+    }
+
+    fn transfer(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), Self::Error> {
+        self.read(read)?;
+        self.write(write)
+    }
+
+    fn transfer_in_place(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        self.write(words)?;
+        self.read(words)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use crate::driver::from_bus;
+
+    /// The bytes the driver for `chip` writes for `pixels`.
+    fn frame(chip: Chip, pixels: &[Color]) -> Vec<u8> {
+        let bus = MockBus::new();
+        from_bus(chip, bus.clone())
+            .unwrap()
+            .write_frame(pixels)
+            .unwrap();
+        bus.last_frame().unwrap()
+    }
+
+    /// Whether `frame` fails for `chip` once changed by `corrupt`.
+    fn rejects(chip: Chip, frame: &[u8], corrupt: impl Fn(&mut Vec<u8>)) -> bool {
+        let mut bytes = frame.to_vec();
+        corrupt(&mut bytes);
+        validate(chip, &bytes).is_err()
+    }
+
+    #[test]
+    fn accepts_good_frames_and_rejects_corrupt_ones() {
+        let pixels = [
+            Color::new(0xff, 0x80, 0x01),
+            Color::new(0, 0, 0),
+            Color::new(0x12, 0x34, 0x56),
+        ];
+
+        let p9813 = frame(Chip::P9813, &pixels);
+        assert_eq!(validate(Chip::P9813, &p9813), Ok(()));
+        assert!(rejects(Chip::P9813, &p9813, |b| b[4] ^= 0x01));
+        assert!(rejects(Chip::P9813, &p9813, |b| b.truncate(b.len() - 4)));
+        assert!(rejects(Chip::P9813, &p9813, |b| b[0] = 1));
+
+        let apa102 = frame(Chip::Apa102, &pixels);
+        assert_eq!(validate(Chip::Apa102, &apa102), Ok(()));
+        assert!(rejects(Chip::Apa102, &apa102, |b| b[8] = 0x1f));
+        assert!(rejects(Chip::Apa102, &apa102, |b| b.truncate(b.len() - 1)));
+        assert!(rejects(Chip::Apa102, &apa102, |b| b[1] = 1));
+
+        let lpd8806 = frame(Chip::Lpd8806, &pixels);
+        assert_eq!(validate(Chip::Lpd8806, &lpd8806), Ok(()));
+        assert!(rejects(Chip::Lpd8806, &lpd8806, |b| b[2] &= 0x7f));
+        assert!(rejects(Chip::Lpd8806, &lpd8806, |b| b.push(0)));
+        assert!(rejects(Chip::Lpd8806, &lpd8806, |b| b[0] = 0x80));
+
+        let ws2812 = frame(Chip::Ws2812, &pixels);
+        assert_eq!(validate(Chip::Ws2812, &ws2812), Ok(()));
+        // A symbol held high, one held low, a short latch and a stray byte.
+        assert!(rejects(Chip::Ws2812, &ws2812, |b| b[1] = 0xff));
+        assert!(rejects(Chip::Ws2812, &ws2812, |b| b[5] = 0x00));
+        assert!(rejects(Chip::Ws2812, &ws2812, |b| b.truncate(b.len() - 1)));
+        assert!(rejects(Chip::Ws2812, &ws2812, |b| b.insert(1, 0x92)));
+
+        let sk6812 = frame(Chip::Sk6812, &pixels[..2]);
+        assert_eq!(validate(Chip::Sk6812, &sk6812), Ok(()));
+        assert!(rejects(Chip::Sk6812, &sk6812, |b| b[0] = 0x92));
+        assert!(rejects(Chip::Sk6812, &sk6812, |b| b.truncate(40)));
+        // Four channels a pixel don't make whole WS2812 pixels.
+        assert!(validate(Chip::Ws2812, &sk6812).is_err());
+
+        assert!(validate(Chip::Pca9685, &[]).is_err());
+    }
+
+    #[test]
+    fn validating_bus_refuses_bad_frames() {
+        let mut bus = MockBus::validating(Chip::Ws2812).with_capacity(1);
+        assert!(bus.write(&[0xff; 100]).is_err());
+        assert_eq!(bus.frame_count(), 0);
+        let good = frame(Chip::Ws2812, &[Color::new(1, 2, 3)]);
+        bus.write(&good).unwrap();
+        bus.write(&good).unwrap();
+        assert_eq!((bus.frame_count(), bus.frames().len()), (2, 1));
+    }
+}
@@ -0,0 +1,241 @@
+//! Output backends for the different LED chips.
+
+use crate::color::{Color, ColorRgbw};
+use crate::error::{Error, Result};
+
+use embedded_hal::spi::SpiBus;
+use std::fmt;
+use std::str::FromStr;
+
+mod apa102;
+#[cfg(feature = "ftdi")]
+mod ftdi;
+mod lpd8806;
+pub mod mock;
+mod order;
+mod p9813;
+mod pca9685;
+#[cfg(feature = "sim")]
+mod sim;
+mod sk6812;
+pub mod spi;
+mod tee;
+mod terminal;
+mod ws2812;
+
+pub use apa102::Apa102;
+pub use lpd8806::Lpd8806;
+pub use mock::MockBus;
+pub use order::{ColorOrder, Reordered};
+pub use p9813::P9813;
+pub use pca9685::{Pca9685, PCA9685_ADDRESS};
+pub use sk6812::Sk6812;
+pub use spi::{create_spi, spidev_bufsiz, SpiOptions, SpidevBus, DEFAULT_SPI_HZ};
+pub use tee::Tee;
+pub use terminal::Terminal;
+pub use ws2812::{Ws2812, WS2812_SPI_HZ};
+
+/// A backend capable of pushing a frame of pixels out to a strip.
+pub trait LedDriver: Send {
+    /// Write one frame of pixels, first pixel nearest the controller.
+    fn write_frame(&mut self, pixels: &[Color]) -> Result<()>;
+
+    /// Whether the chip has a dedicated white LED per pixel.
+    fn has_white(&self) -> bool {
+        false
+    }
+
+    /// Write one frame of RGBW pixels. Chips without a white LED mix the
+    /// white component into the color channels.
+    fn write_frame_rgbw(&mut self, pixels: &[ColorRgbw]) -> Result<()> {
+        let pixels: Vec<Color> = pixels.iter().map(|p| p.to_rgb()).collect();
+        self.write_frame(&pixels)
+    }
+
+    /// Set the 5-bit current level, 0 to 31, that chips such as the APA102
+    /// send with every LED; chips without one ignore it.
+    fn set_global_brightness(&mut self, _brightness: u8) {}
+}
+
+impl<D: LedDriver + ?Sized> LedDriver for Box<D> {
+    fn write_frame(&mut self, pixels: &[Color]) -> Result<()> {
+        (**self).write_frame(pixels)
+    }
+
+    fn has_white(&self) -> bool {
+        (**self).has_white()
+    }
+
+    fn write_frame_rgbw(&mut self, pixels: &[ColorRgbw]) -> Result<()> {
+        (**self).write_frame_rgbw(pixels)
+    }
+
+    fn set_global_brightness(&mut self, brightness: u8) {
+        (**self).set_global_brightness(brightness)
+    }
+}
+
+/// The LED chips that can be selected at runtime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Chip {
+    P9813,
+    Apa102,
+    Ws2812,
+    Lpd8806,
+    Sk6812,
+    Pca9685,
+}
+
+impl Chip {
+    /// SPI clock the chip should be driven at.
+    pub fn spi_hz(self) -> u32 {
+        match self {
+            Chip::Ws2812 | Chip::Sk6812 => WS2812_SPI_HZ,
+            _ => DEFAULT_SPI_HZ,
+        }
+    }
+}
+
+impl FromStr for Chip {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "p9813" => Ok(Chip::P9813),
+            "apa102" | "sk9822" => Ok(Chip::Apa102),
+            "ws2812" | "ws2812b" | "neopixel" => Ok(Chip::Ws2812),
+            "lpd8806" => Ok(Chip::Lpd8806),
+            "sk6812" | "sk6812rgbw" => Ok(Chip::Sk6812),
+            "pca9685" => Ok(Chip::Pca9685),
+            _ => Err(format!("unknown chip: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for Chip {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Chip::P9813 => "p9813",
+            Chip::Apa102 => "apa102",
+            Chip::Ws2812 => "ws2812",
+            Chip::Lpd8806 => "lpd8806",
+            Chip::Sk6812 => "sk6812",
+            Chip::Pca9685 => "pca9685",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Where frames are sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    /// A Linux `/dev/spidevX.Y` device.
+    Spidev,
+    /// An FTDI USB adapter in MPSSE mode.
+    Ftdi,
+    /// Frames are checked and kept in memory; no hardware is needed.
+    Mock,
+    /// Frames are drawn in a desktop window.
+    Sim,
+    /// Frames are drawn in the terminal with ANSI colors.
+    Terminal,
+}
+
+impl FromStr for Backend {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "spidev" => Ok(Backend::Spidev),
+            "ftdi" => Ok(Backend::Ftdi),
+            "mock" => Ok(Backend::Mock),
+            "sim" => Ok(Backend::Sim),
+            "terminal" | "ansi" => Ok(Backend::Terminal),
+            _ => Err(format!("unknown driver: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Backend::Spidev => "spidev",
+            Backend::Ftdi => "ftdi",
+            Backend::Mock => "mock",
+            Backend::Sim => "sim",
+            Backend::Terminal => "terminal",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Open the SPI device at `path` with the driver for `chip`. For the
+/// PCA9685 `path` names the I2C bus instead and `options` are ignored.
+pub fn open(chip: Chip, path: &str, options: SpiOptions) -> Result<Box<dyn LedDriver>> {
+    if chip == Chip::Pca9685 {
+        return Ok(Box::new(Pca9685::open(path)?));
+    }
+    let speed_hz = options.speed_hz.unwrap_or_else(|| chip.spi_hz());
+    let mut bus = SpidevBus::open(path, speed_hz)?;
+    if let Some(chunk_size) = options.chunk_size {
+        bus.set_chunk_size(chunk_size);
+    }
+    from_bus(chip, bus)
+}
+
+/// Build the driver for `chip` on top of an already configured SPI bus.
+///
+/// The bus must be clocked appropriately for the chip, e.g. at
+/// `WS2812_SPI_HZ` for the WS2812 and SK6812.
+pub fn from_bus<B>(chip: Chip, bus: B) -> Result<Box<dyn LedDriver>>
+where
+    B: SpiBus + Send + 'static,
+{
+    Ok(match chip {
+        Chip::P9813 => Box::new(P9813::new(bus)),
+        Chip::Apa102 => Box::new(Apa102::new(bus)),
+        Chip::Ws2812 => Box::new(Ws2812::new(bus)),
+        Chip::Lpd8806 => Box::new(Lpd8806::new(bus)),
+        Chip::Sk6812 => Box::new(Sk6812::new(bus)),
+        Chip::Pca9685 => return Err(Error::Unsupported("the pca9685 is not an SPI chip")),
+    })
+}
+
+/// Drive `chip` on a `MockBus` that validates its framing.
+pub fn open_mock(chip: Chip) -> Result<Box<dyn LedDriver>> {
+    from_bus(chip, MockBus::validating(chip))
+}
+
+/// Open the FTDI adapter with the USB `description` and drive `chip` from
+/// it, clocked at `speed_hz` or the chip's usual rate when `None`.
+#[cfg(feature = "ftdi")]
+pub fn open_ftdi(
+    chip: Chip,
+    description: &str,
+    speed_hz: Option<u32>,
+) -> Result<Box<dyn LedDriver>> {
+    ftdi::open(chip, description, speed_hz.unwrap_or_else(|| chip.spi_hz()))
+}
+
+/// Open the FTDI adapter with the USB `description` and drive `chip` from
+/// it, clocked at `speed_hz` or the chip's usual rate when `None`.
+#[cfg(not(feature = "ftdi"))]
+pub fn open_ftdi(
+    _chip: Chip,
+    _description: &str,
+    _speed_hz: Option<u32>,
+) -> Result<Box<dyn LedDriver>> {
+    Err(Error::Unsupported("built without the ftdi feature"))
+}
+
+/// Open a window simulating the strip.
+#[cfg(feature = "sim")]
+pub fn open_sim() -> Result<Box<dyn LedDriver>> {
+    Ok(Box::new(sim::Sim::open()?))
+}
+
+/// Open a window simulating the strip.
+#[cfg(not(feature = "sim"))]
+pub fn open_sim() -> Result<Box<dyn LedDriver>> {
+    Err(Error::Unsupported("built without the sim feature"))
+}
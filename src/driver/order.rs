@@ -0,0 +1,177 @@
+use super::LedDriver;
+use crate::color::{Color, ColorRgbw};
+use crate::error::Result;
+
+use std::fmt;
+use std::str::FromStr;
+
+/// Which input channels feed the chip's red, green and blue outputs.
+///
+/// `Rgb` passes colors through unchanged; `Rbg`, for instance, suits a strip
+/// whose green and blue LEDs are swapped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorOrder {
+    #[default]
+    Rgb,
+    Rbg,
+    Grb,
+    Gbr,
+    Brg,
+    Bgr,
+}
+
+impl ColorOrder {
+    fn permute(self, red: u8, green: u8, blue: u8) -> (u8, u8, u8) {
+        match self {
+            ColorOrder::Rgb => (red, green, blue),
+            ColorOrder::Rbg => (red, blue, green),
+            ColorOrder::Grb => (green, red, blue),
+            ColorOrder::Gbr => (green, blue, red),
+            ColorOrder::Brg => (blue, red, green),
+            ColorOrder::Bgr => (blue, green, red),
+        }
+    }
+
+    pub fn apply(self, color: Color) -> Color {
+        let (red, green, blue) = self.permute(color.red, color.green, color.blue);
+        Color::new(red, green, blue)
+    }
+
+    pub fn apply_rgbw(self, color: ColorRgbw) -> ColorRgbw {
+        let (red, green, blue) = self.permute(color.red, color.green, color.blue);
+        ColorRgbw::new(red, green, blue, color.white)
+    }
+}
+
+impl FromStr for ColorOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "rgb" => Ok(ColorOrder::Rgb),
+            "rbg" => Ok(ColorOrder::Rbg),
+            "grb" => Ok(ColorOrder::Grb),
+            "gbr" => Ok(ColorOrder::Gbr),
+            "brg" => Ok(ColorOrder::Brg),
+            "bgr" => Ok(ColorOrder::Bgr),
+            _ => Err(format!("unknown color order: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for ColorOrder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            ColorOrder::Rgb => "rgb",
+            ColorOrder::Rbg => "rbg",
+            ColorOrder::Grb => "grb",
+            ColorOrder::Gbr => "gbr",
+            ColorOrder::Brg => "brg",
+            ColorOrder::Bgr => "bgr",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Wraps a driver, rearranging the channels of every pixel into `order`.
+pub struct Reordered<D> {
+    inner: D,
+    order: ColorOrder,
+    pixels: Vec<Color>,
+    rgbw: Vec<ColorRgbw>,
+}
+
+impl<D: LedDriver> Reordered<D> {
+    pub fn new(inner: D, order: ColorOrder) -> Self {
+        Reordered {
+            inner,
+            order,
+            pixels: Vec::new(),
+            rgbw: Vec::new(),
+        }
+    }
+}
+
+impl<D: LedDriver> LedDriver for Reordered<D> {
+    fn write_frame(&mut self, pixels: &[Color]) -> Result<()> {
+        let order = self.order;
+        self.pixels.clear();
+        self.pixels.extend(pixels.iter().map(|p| order.apply(*p)));
+        self.inner.write_frame(&self.pixels)
+    }
+
+    fn has_white(&self) -> bool {
+        self.inner.has_white()
+    }
+
+    fn write_frame_rgbw(&mut self, pixels: &[ColorRgbw]) -> Result<()> {
+        let order = self.order;
+        self.rgbw.clear();
+        self.rgbw
+            .extend(pixels.iter().map(|p| order.apply_rgbw(*p)));
+        self.inner.write_frame_rgbw(&self.rgbw)
+    }
+
+    fn set_global_brightness(&mut self, brightness: u8) {
+        self.inner.set_global_brightness(brightness)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::driver::ws2812::{encode_byte, LATCH_BYTES};
+    use crate::driver::{Chip, MockBus, Sk6812, P9813};
+
+    const ORDERS: [ColorOrder; 6] = [
+        ColorOrder::Rgb,
+        ColorOrder::Rbg,
+        ColorOrder::Grb,
+        ColorOrder::Gbr,
+        ColorOrder::Brg,
+        ColorOrder::Bgr,
+    ];
+
+    #[test]
+    fn names_round_trip() {
+        for order in &ORDERS {
+            let name = order.to_string();
+            assert_eq!(name.parse::<ColorOrder>(), Ok(*order));
+            // Each output channel takes the input named in its place.
+            let out = order.apply(Color::new(b'r', b'g', b'b'));
+            assert_eq!([out.red, out.green, out.blue], name.as_bytes());
+        }
+        assert_eq!("GRB".parse(), Ok(ColorOrder::Grb));
+        assert_eq!(
+            "rgbw".parse::<ColorOrder>(),
+            Err("unknown color order: rgbw".to_string())
+        );
+    }
+
+    #[test]
+    fn rbg_swaps_green_and_blue_on_the_wire() {
+        let bus = MockBus::validating(Chip::P9813);
+        let mut driver = Reordered::new(P9813::new(bus.clone()), ColorOrder::Rbg);
+        driver.write_frame(&[Color::new(10, 20, 30)]).unwrap();
+        // P9813 pixels are sent flag, blue, green, red.
+        let frame = bus.last_frame().unwrap();
+        assert_eq!(frame[5..8], [20, 30, 10]);
+    }
+
+    #[test]
+    fn white_stays_where_it_is() {
+        let bus = MockBus::validating(Chip::Sk6812);
+        let mut driver = Reordered::new(Sk6812::new(bus.clone()), ColorOrder::Rbg);
+        assert!(driver.has_white());
+        driver
+            .write_frame_rgbw(&[ColorRgbw::new(10, 20, 30, 40)])
+            .unwrap();
+        // SK6812 pixels are sent green, red, blue, white.
+        let mut wanted = vec![0x00];
+        for byte in &[30, 10, 20, 40] {
+            wanted.extend_from_slice(&encode_byte(*byte));
+        }
+        wanted.extend(std::iter::repeat_n(0x00, LATCH_BYTES));
+        assert_eq!(bus.last_frame().unwrap(), wanted);
+    }
+}
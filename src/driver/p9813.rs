@@ -0,0 +1,117 @@
+use super::spi::{bus_error, SpidevBus};
+use super::{LedDriver, DEFAULT_SPI_HZ};
+use crate::color::Color;
+use crate::error::Result;
+
+use embedded_hal::spi::SpiBus;
+
+/// Driver for the TCL P9813 chip.
+pub struct P9813<B = SpidevBus> {
+    spi: B,
+    bytes: Vec<u8>,
+}
+
+impl<B: SpiBus> P9813<B> {
+    pub fn new(spi: B) -> Self {
+        P9813 {
+            spi,
+            bytes: Vec::new(),
+        }
+    }
+}
+
+impl P9813 {
+    /// Open and configure the SPI device at `path`.
+    pub fn open(path: &str) -> Result<Self> {
+        Ok(P9813::new(SpidevBus::open(path, DEFAULT_SPI_HZ)?))
+    }
+}
+
+/// The flag byte sent before each pixel: two marker bits followed by the
+/// inverted top two bits of blue, green and red.
+fn flag(pixel: &Color) -> u8 {
+    !(((pixel.blue & 0xc0) >> 2) | ((pixel.green & 0xc0) >> 4) | ((pixel.red & 0xc0) >> 6))
+}
+
+/// Serialize `pixels` into the P9813 byte stream, replacing the contents
+/// of `bytes`.
+///
+/// The stream is one all-zero boundary frame, four bytes per pixel (flag,
+/// blue, green, red) and then two more boundary frames.
+fn serialize(pixels: &[Color], bytes: &mut Vec<u8>) {
+    bytes.clear();
+    bytes.reserve((pixels.len() + 3) * 4);
+    bytes.extend_from_slice(&[0x00; 4]);
+    for pixel in pixels {
+        bytes.extend_from_slice(&[flag(pixel), pixel.blue, pixel.green, pixel.red]);
+    }
+    bytes.extend_from_slice(&[0x00; 8]);
+}
+
+impl<B: SpiBus + Send> LedDriver for P9813<B> {
+    fn write_frame(&mut self, pixels: &[Color]) -> Result<()> {
+        serialize(pixels, &mut self.bytes);
+        trace!("pixels: {:02x?}", pixels);
+        trace!("bytes: {:02x?}", self.bytes);
+        self.spi.write(&self.bytes).map_err(bus_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::driver::{Chip, MockBus};
+
+    #[test]
+    fn empty_frame_is_only_boundaries() {
+        let mut bytes = Vec::new();
+        serialize(&[], &mut bytes);
+        assert_eq!(bytes, vec![0x00; 12]);
+    }
+
+    #[test]
+    fn pixel_wire_format() {
+        let mut bytes = Vec::new();
+        serialize(
+            &[
+                Color::new(0x00, 0x00, 0x00),
+                Color::new(0xff, 0x00, 0x00),
+                Color::new(0x00, 0xff, 0x00),
+                Color::new(0x00, 0x00, 0xff),
+                Color::new(0x12, 0x80, 0x45),
+            ],
+            &mut bytes,
+        );
+        #[rustfmt::skip]
+        let expected = vec![
+            0x00, 0x00, 0x00, 0x00,
+            0xff, 0x00, 0x00, 0x00,
+            0xfc, 0x00, 0x00, 0xff,
+            0xf3, 0x00, 0xff, 0x00,
+            0xcf, 0xff, 0x00, 0x00,
+            0xe7, 0x45, 0x80, 0x12,
+            0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00,
+        ];
+        assert_eq!(bytes, expected);
+    }
+
+    #[test]
+    fn buffer_is_reused() {
+        let mut bytes = Vec::new();
+        serialize(&[Color::new(1, 2, 3); 8], &mut bytes);
+        serialize(&[Color::new(1, 2, 3)], &mut bytes);
+        assert_eq!(bytes.len(), 16);
+    }
+
+    #[test]
+    fn driver_writes_valid_frames() {
+        let bus = MockBus::validating(Chip::P9813);
+        let mut driver = P9813::new(bus.clone());
+        driver
+            .write_frame(&[Color::new(0xff, 0x80, 0x01); 3])
+            .unwrap();
+        assert_eq!(bus.frame_count(), 1);
+        assert_eq!(bus.last_frame().unwrap().len(), 24);
+    }
+}
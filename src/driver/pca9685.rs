@@ -0,0 +1,207 @@
+use super::LedDriver;
+use crate::color::Color;
+use crate::error::{Error, Result};
+
+use i2cdev::core::I2CDevice;
+use i2cdev::linux::LinuxI2CDevice;
+
+/// Default I2C address of a PCA9685 with no address pins strapped.
+pub const PCA9685_ADDRESS: u16 = 0x40;
+
+const MODE1: u8 = 0x00;
+const MODE2: u8 = 0x01;
+const LED0_ON_L: u8 = 0x06;
+const PRE_SCALE: u8 = 0xFE;
+
+const MODE1_SLEEP: u8 = 0x10;
+const MODE1_AUTO_INCREMENT: u8 = 0x20;
+const MODE2_OUTDRV: u8 = 0x04;
+
+/// PWM frequency; fast enough to avoid visible flicker on camera.
+const PWM_HZ: f64 = 1000.0;
+
+/// Driver for a non-addressable analog RGB strip switched by the PWM
+/// outputs of a PCA9685.
+///
+/// The whole strip is a single logical pixel; when given several pixels
+/// the average color is shown.
+pub struct Pca9685<I = LinuxI2CDevice> {
+    i2c: I,
+    channels: [u8; 3],
+}
+
+fn i2c_error<E: std::error::Error>(e: E) -> Error {
+    Error::I2c(e.to_string())
+}
+
+impl<I: I2CDevice> Pca9685<I> {
+    /// Configure the PCA9685 with red, green and blue on the given PWM channels.
+    pub fn new(mut i2c: I, channels: [u8; 3]) -> Result<Self> {
+        let prescale = (25_000_000.0 / (4096.0 * PWM_HZ)).round() as u8 - 1;
+        // The prescaler can only be written while the oscillator is asleep.
+        i2c.smbus_write_byte_data(MODE1, MODE1_SLEEP)
+            .map_err(i2c_error)?;
+        i2c.smbus_write_byte_data(PRE_SCALE, prescale)
+            .map_err(i2c_error)?;
+        i2c.smbus_write_byte_data(MODE1, MODE1_AUTO_INCREMENT)
+            .map_err(i2c_error)?;
+        i2c.smbus_write_byte_data(MODE2, MODE2_OUTDRV)
+            .map_err(i2c_error)?;
+        Ok(Pca9685 { i2c, channels })
+    }
+
+    fn set_duty(&mut self, channel: u8, value: u8) -> Result<()> {
+        const FULL: u8 = 0x10;
+        let (on, off) = match value {
+            0 => (0u16, u16::from(FULL) << 8),
+            255 => (u16::from(FULL) << 8, 0u16),
+            // In u32, as 254 * 4095 is past a u16.
+            _ => (0, ((u32::from(value) * 4095 + 127) / 255) as u16),
+        };
+        let bytes = [
+            LED0_ON_L + 4 * channel,
+            on as u8,
+            (on >> 8) as u8,
+            off as u8,
+            (off >> 8) as u8,
+        ];
+        self.i2c.write(&bytes).map_err(i2c_error)
+    }
+}
+
+impl Pca9685 {
+    /// Open the I2C bus at `path` with the chip at its default address and
+    /// red, green and blue wired to channels 0, 1 and 2.
+    pub fn open(path: &str) -> Result<Self> {
+        let i2c = LinuxI2CDevice::new(path, PCA9685_ADDRESS).map_err(|e| Error::open(path, e))?;
+        Pca9685::new(i2c, [0, 1, 2])
+    }
+}
+
+impl<I: I2CDevice + Send> LedDriver for Pca9685<I> {
+    fn write_frame(&mut self, pixels: &[Color]) -> Result<()> {
+        let n = pixels.len().max(1) as u32;
+        let sum = pixels.iter().fold([0u32; 3], |acc, p| {
+            [
+                acc[0] + u32::from(p.red),
+                acc[1] + u32::from(p.green),
+                acc[2] + u32::from(p.blue),
+            ]
+        });
+        trace!(
+            "average: {:?}",
+            sum.iter().map(|c| c / n).collect::<Vec<_>>()
+        );
+        for (i, total) in sum.iter().enumerate() {
+            self.set_duty(self.channels[i], (total / n) as u8)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+
+    /// An I2C device that keeps every write it is sent.
+    #[derive(Default)]
+    struct Recorder(Vec<Vec<u8>>);
+
+    impl I2CDevice for Recorder {
+        type Error = io::Error;
+
+        fn read(&mut self, _: &mut [u8]) -> io::Result<()> {
+            unimplemented!()
+        }
+
+        fn write(&mut self, data: &[u8]) -> io::Result<()> {
+            self.0.push(data.to_vec());
+            Ok(())
+        }
+
+        fn smbus_write_quick(&mut self, _: bool) -> io::Result<()> {
+            unimplemented!()
+        }
+
+        fn smbus_read_block_data(&mut self, _: u8) -> io::Result<Vec<u8>> {
+            unimplemented!()
+        }
+
+        fn smbus_read_i2c_block_data(&mut self, _: u8, _: u8) -> io::Result<Vec<u8>> {
+            unimplemented!()
+        }
+
+        fn smbus_write_block_data(&mut self, _: u8, _: &[u8]) -> io::Result<()> {
+            unimplemented!()
+        }
+
+        fn smbus_write_i2c_block_data(&mut self, _: u8, _: &[u8]) -> io::Result<()> {
+            unimplemented!()
+        }
+
+        fn smbus_process_block(&mut self, _: u8, _: &[u8]) -> io::Result<Vec<u8>> {
+            unimplemented!()
+        }
+    }
+
+    /// The writes sent since the last call.
+    fn sent(driver: &mut Pca9685<Recorder>) -> Vec<Vec<u8>> {
+        std::mem::take(&mut driver.i2c.0)
+    }
+
+    #[test]
+    fn sets_up_the_chip() {
+        let mut driver = Pca9685::new(Recorder::default(), [0, 1, 2]).unwrap();
+        // Asleep while the prescaler is set for 1 kHz, then awake with
+        // auto increment and totem pole outputs.
+        assert_eq!(
+            sent(&mut driver),
+            [[0x00, 0x10], [0xFE, 5], [0x00, 0x20], [0x01, 0x04]]
+        );
+    }
+
+    #[test]
+    fn pwm_register_bytes() {
+        let mut driver = Pca9685::new(Recorder::default(), [0, 1, 2]).unwrap();
+        sent(&mut driver);
+        driver.write_frame(&[Color::new(0, 255, 128)]).unwrap();
+        assert_eq!(
+            sent(&mut driver),
+            [
+                // Full off, full on, then 2056 of 4096.
+                [0x06, 0x00, 0x00, 0x00, 0x10],
+                [0x0A, 0x00, 0x10, 0x00, 0x00],
+                [0x0E, 0x00, 0x00, 0x08, 0x08],
+            ]
+        );
+        // Levels that would overflow 16 bits along the way.
+        driver.write_frame(&[Color::new(1, 17, 254)]).unwrap();
+        let offs: Vec<u16> = sent(&mut driver)
+            .iter()
+            .map(|w| u16::from_le_bytes([w[3], w[4]]))
+            .collect();
+        assert_eq!(offs, [16, 273, 4079]);
+    }
+
+    #[test]
+    fn shows_the_average_of_the_pixels() {
+        let mut driver = Pca9685::new(Recorder::default(), [3, 4, 5]).unwrap();
+        sent(&mut driver);
+        driver
+            .write_frame(&[Color::new(255, 0, 100), Color::new(1, 0, 50)])
+            .unwrap();
+        assert_eq!(
+            sent(&mut driver),
+            [
+                // 128, 0 and 75 on channels 3, 4 and 5.
+                [0x12, 0x00, 0x00, 0x08, 0x08],
+                [0x16, 0x00, 0x00, 0x00, 0x10],
+                [0x1A, 0x00, 0x00, 0xB4, 0x04],
+            ]
+        );
+        // An empty frame turns the strip off.
+        driver.write_frame(&[]).unwrap();
+        assert!(sent(&mut driver).iter().all(|w| w[4] == 0x10));
+    }
+}
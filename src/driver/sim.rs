@@ -0,0 +1,90 @@
+//! Window simulator that shows the strip as a row of colored squares.
+
+use super::LedDriver;
+use crate::color::Color;
+use crate::error::{Error, Result};
+
+use minifb::{Window, WindowOptions};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender};
+use std::thread;
+use std::time::Duration;
+
+/// Side of the square drawn for each LED, in window pixels.
+const CELL: usize = 16;
+/// Blank space between neighbouring LEDs.
+const GAP: usize = 2;
+/// LEDs per row before wrapping, to keep long strips on screen.
+const ROW_LEDS: usize = 64;
+
+/// Driver that renders frames into a desktop window.
+///
+/// The window lives on its own thread and is sized from the first frame.
+pub struct Sim {
+    frames: SyncSender<Vec<Color>>,
+}
+
+impl Sim {
+    pub fn open() -> Result<Self> {
+        // Hold a single frame; newer frames are dropped while the window
+        // is still drawing rather than stalling the caller.
+        let (frames, rx) = mpsc::sync_channel(1);
+        thread::Builder::new()
+            .name("sim".to_string())
+            .spawn(move || run(rx))?;
+        Ok(Sim { frames })
+    }
+}
+
+fn run(frames: Receiver<Vec<Color>>) {
+    let first = match frames.recv() {
+        Ok(frame) => frame,
+        Err(_) => return,
+    };
+    let columns = first.len().clamp(1, ROW_LEDS);
+    let rows = first.len().max(1).div_ceil(columns);
+    let width = columns * (CELL + GAP) + GAP;
+    let height = rows * (CELL + GAP) + GAP;
+    let mut window = match Window::new("led-strip", width, height, WindowOptions::default()) {
+        Ok(window) => window,
+        Err(e) => {
+            error!("unable to open simulator window: {}", e);
+            return;
+        }
+    };
+    let mut buffer = vec![0u32; width * height];
+    let mut frame = first;
+    while window.is_open() {
+        for (i, pixel) in frame.iter().enumerate() {
+            let rgb = (u32::from(pixel.red) << 16)
+                | (u32::from(pixel.green) << 8)
+                | u32::from(pixel.blue);
+            let x0 = GAP + (i % columns) * (CELL + GAP);
+            let y0 = GAP + (i / columns) * (CELL + GAP);
+            for y in y0..y0 + CELL {
+                buffer[y * width + x0..y * width + x0 + CELL]
+                    .iter_mut()
+                    .for_each(|p| *p = rgb);
+            }
+        }
+        if let Err(e) = window.update_with_buffer(&buffer, width, height) {
+            error!("unable to draw simulator window: {}", e);
+            return;
+        }
+        match frames.recv_timeout(Duration::from_millis(16)) {
+            Ok(next) => frame = next,
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return,
+        }
+    }
+}
+
+impl LedDriver for Sim {
+    fn write_frame(&mut self, pixels: &[Color]) -> Result<()> {
+        match self.frames.try_send(pixels.to_vec()) {
+            Ok(()) | Err(mpsc::TrySendError::Full(_)) => Ok(()),
+            Err(mpsc::TrySendError::Disconnected(_)) => {
+                Err(Error::Device("simulator window closed".to_string()))
+            }
+        }
+    }
+}
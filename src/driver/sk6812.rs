@@ -0,0 +1,95 @@
+use super::spi::{bus_error, SpidevBus};
+use super::ws2812::{encode_byte, LATCH_BYTES};
+use super::{LedDriver, WS2812_SPI_HZ};
+use crate::color::{Color, ColorRgbw};
+use crate::error::Result;
+
+use embedded_hal::spi::SpiBus;
+
+/// Driver for SK6812 RGBW chips.
+///
+/// These share the WS2812 single-wire timing but take a fourth byte per
+/// pixel for the white LED, sent in green, red, blue, white order.
+pub struct Sk6812<B = SpidevBus> {
+    spi: B,
+    bytes: Vec<u8>,
+}
+
+impl<B: SpiBus> Sk6812<B> {
+    pub fn new(spi: B) -> Self {
+        Sk6812 {
+            spi,
+            bytes: Vec::new(),
+        }
+    }
+
+    fn send(&mut self, pixels: impl Iterator<Item = [u8; 4]>) -> Result<()> {
+        self.bytes.clear();
+        self.bytes.push(0x00);
+        for pixel in pixels {
+            for byte in &pixel {
+                self.bytes.extend_from_slice(&encode_byte(*byte));
+            }
+        }
+        self.bytes.extend(std::iter::repeat_n(0x00, LATCH_BYTES));
+        trace!("bytes: {:02x?}", self.bytes);
+        self.spi.write(&self.bytes).map_err(bus_error)
+    }
+}
+
+impl Sk6812 {
+    /// Open and configure the SPI device at `path`.
+    pub fn open(path: &str) -> Result<Self> {
+        Ok(Sk6812::new(SpidevBus::open(path, WS2812_SPI_HZ)?))
+    }
+}
+
+impl<B: SpiBus + Send> LedDriver for Sk6812<B> {
+    fn write_frame(&mut self, pixels: &[Color]) -> Result<()> {
+        self.send(pixels.iter().map(|p| [p.green, p.red, p.blue, 0]))
+    }
+
+    fn has_white(&self) -> bool {
+        true
+    }
+
+    fn write_frame_rgbw(&mut self, pixels: &[ColorRgbw]) -> Result<()> {
+        self.send(pixels.iter().map(|p| [p.green, p.red, p.blue, p.white]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::driver::{Chip, MockBus};
+
+    /// The single-wire symbols for `bytes`, framed as the driver sends them.
+    fn framed(bytes: &[u8]) -> Vec<u8> {
+        let mut frame = vec![0x00];
+        for byte in bytes {
+            frame.extend_from_slice(&encode_byte(*byte));
+        }
+        frame.extend_from_slice(&[0x00; LATCH_BYTES]);
+        frame
+    }
+
+    #[test]
+    fn white_follows_the_colors() {
+        let bus = MockBus::validating(Chip::Sk6812);
+        let mut driver = Sk6812::new(bus.clone());
+        assert!(driver.has_white());
+        driver
+            .write_frame_rgbw(&[
+                ColorRgbw::new(0x11, 0x22, 0x33, 0x44),
+                ColorRgbw::new(0, 0, 0, 0xff),
+            ])
+            .unwrap();
+        assert_eq!(
+            bus.last_frame().unwrap(),
+            framed(&[0x22, 0x11, 0x33, 0x44, 0x00, 0x00, 0x00, 0xff])
+        );
+        // Plain colors leave the white LED off.
+        driver.write_frame(&[Color::new(0x11, 0x22, 0x33)]).unwrap();
+        assert_eq!(bus.last_frame().unwrap(), framed(&[0x22, 0x11, 0x33, 0x00]));
+    }
+}
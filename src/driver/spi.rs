@@ -0,0 +1,189 @@
+//! Adapters between the drivers and the SPI bus they write to.
+//!
+//! Drivers accept any `embedded_hal::spi::SpiBus`; `SpidevBus` provides one
+//! on top of a Linux `/dev/spidevX.Y` device.
+
+use crate::error::{Error, Result};
+
+use embedded_hal::spi::{self, ErrorKind, ErrorType, SpiBus};
+use spidev::{SpiModeFlags, Spidev, SpidevOptions, SpidevTransfer};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::io::prelude::*;
+
+/// SPI clock used by the clocked chips.
+pub const DEFAULT_SPI_HZ: u32 = 15_000_000;
+
+/// Largest single transfer accepted by spidev unless the module was loaded
+/// with a different `bufsiz`.
+pub const DEFAULT_BUFSIZ: usize = 4096;
+
+const BUFSIZ_PATH: &str = "/sys/module/spidev/parameters/bufsiz";
+
+/// Settings for opening a spidev bus; `None` picks the default.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SpiOptions {
+    /// SPI clock, defaulting to the usual rate for the chip.
+    pub speed_hz: Option<u32>,
+    /// Largest transfer handed to the kernel, defaulting to `spidev_bufsiz`.
+    pub chunk_size: Option<usize>,
+}
+
+/// The spidev module's transfer size limit, or `DEFAULT_BUFSIZ` when it
+/// can't be read.
+pub fn spidev_bufsiz() -> usize {
+    fs::read_to_string(BUFSIZ_PATH)
+        .ok()
+        .and_then(|s| s.trim().parse().ok())
+        .filter(|n| *n > 0)
+        .unwrap_or(DEFAULT_BUFSIZ)
+}
+
+/// Split `words` into transfers of at most `chunk_size` bytes, the last
+/// one holding whatever is left.
+fn transfers(words: &[u8], chunk_size: usize) -> std::slice::Chunks<'_, u8> {
+    words.chunks(chunk_size.max(1))
+}
+
+pub fn create_spi(path: &str, speed_hz: u32) -> io::Result<Spidev> {
+    let mut spi = Spidev::open(path)?;
+    let options = SpidevOptions::new()
+        .bits_per_word(8)
+        .max_speed_hz(speed_hz)
+        .mode(SpiModeFlags::SPI_MODE_0)
+        .build();
+    spi.configure(&options)?;
+    Ok(spi)
+}
+
+/// Convert an error from any SPI bus into the error returned by drivers.
+pub(super) fn bus_error<E: spi::Error>(e: E) -> Error {
+    Error::Bus(format!("{:?}", e))
+}
+
+/// Error from a `SpidevBus`.
+#[derive(Debug)]
+pub struct SpidevError(pub io::Error);
+
+impl fmt::Display for SpidevError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl std::error::Error for SpidevError {}
+
+impl spi::Error for SpidevError {
+    fn kind(&self) -> ErrorKind {
+        ErrorKind::Other
+    }
+}
+
+/// An `SpiBus` backed by a Linux spidev device.
+///
+/// Writes longer than the kernel's buffer are split into several transfers.
+/// The clock pauses between them, which clocked chips don't notice but
+/// which may latch WS2812-style strips early at low SPI rates.
+pub struct SpidevBus {
+    spi: Spidev,
+    chunk_size: usize,
+}
+
+impl SpidevBus {
+    pub fn new(spi: Spidev) -> Self {
+        SpidevBus {
+            spi,
+            chunk_size: spidev_bufsiz(),
+        }
+    }
+
+    /// Open and configure the SPI device at `path`.
+    pub fn open(path: &str, speed_hz: u32) -> Result<Self> {
+        let spi = create_spi(path, speed_hz).map_err(|e| Error::open(path, e))?;
+        Ok(SpidevBus::new(spi))
+    }
+
+    /// Limit each transfer to `chunk_size` bytes.
+    pub fn set_chunk_size(&mut self, chunk_size: usize) {
+        self.chunk_size = chunk_size.max(1);
+    }
+}
+
+impl ErrorType for SpidevBus {
+    type Error = SpidevError;
+}
+
+impl SpiBus for SpidevBus {
+    fn read(&mut self, words: &mut [u8]) -> std::result::Result<(), Self::Error> {
+        self.spi.read_exact(words).map_err(SpidevError)
+    }
+
+    fn write(&mut self, words: &[u8]) -> std::result::Result<(), Self::Error> {
+        for chunk in transfers(words, self.chunk_size) {
+            let mut transfer = SpidevTransfer::write(chunk);
+            self.spi.transfer(&mut transfer).map_err(SpidevError)?;
+        }
+        Ok(())
+    }
+
+    fn transfer(&mut self, read: &mut [u8], write: &[u8]) -> std::result::Result<(), Self::Error> {
+        if read.len() == write.len() {
+            let mut transfer = SpidevTransfer::read_write(write, read);
+            return self.spi.transfer(&mut transfer).map_err(SpidevError);
+        }
+        // The ioctl needs equal lengths; pad the write and drop surplus reads.
+        let len = read.len().max(write.len());
+        let mut tx = write.to_vec();
+        tx.resize(len, 0);
+        let mut rx = vec![0; len];
+        let mut transfer = SpidevTransfer::read_write(&tx, &mut rx);
+        self.spi.transfer(&mut transfer).map_err(SpidevError)?;
+        read.copy_from_slice(&rx[..read.len()]);
+        Ok(())
+    }
+
+    fn transfer_in_place(&mut self, words: &mut [u8]) -> std::result::Result<(), Self::Error> {
+        let write = words.to_vec();
+        self.transfer(words, &write)
+    }
+
+    fn flush(&mut self) -> std::result::Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lengths(len: usize, chunk_size: usize) -> Vec<usize> {
+        let words = vec![0; len];
+        transfers(&words, chunk_size).map(<[u8]>::len).collect()
+    }
+
+    #[test]
+    fn splits_exact_multiples_evenly() {
+        assert_eq!(lengths(12, 4), [4, 4, 4]);
+        assert_eq!(lengths(4, 4), [4]);
+    }
+
+    #[test]
+    fn puts_the_remainder_last() {
+        assert_eq!(lengths(10, 4), [4, 4, 2]);
+        assert_eq!(lengths(3, 4), [3]);
+    }
+
+    #[test]
+    fn sends_one_byte_at_a_time() {
+        assert_eq!(lengths(3, 1), [1, 1, 1]);
+        assert_eq!(lengths(3, 0), [1, 1, 1]);
+    }
+
+    #[test]
+    fn keeps_the_bytes_in_order() {
+        let words: Vec<u8> = (0..10).collect();
+        let joined: Vec<u8> = transfers(&words, 3).flatten().copied().collect();
+        assert_eq!(joined, words);
+    }
+}
@@ -0,0 +1,39 @@
+use super::LedDriver;
+use crate::color::{Color, ColorRgbw};
+use crate::error::Result;
+
+/// Driver that writes every frame to two drivers, e.g. a strip and a
+/// preview of it.
+///
+/// White support follows `first`; both are written even if `first` fails.
+pub struct Tee<A, B> {
+    first: A,
+    second: B,
+}
+
+impl<A: LedDriver, B: LedDriver> Tee<A, B> {
+    pub fn new(first: A, second: B) -> Self {
+        Tee { first, second }
+    }
+}
+
+impl<A: LedDriver, B: LedDriver> LedDriver for Tee<A, B> {
+    fn write_frame(&mut self, pixels: &[Color]) -> Result<()> {
+        let first = self.first.write_frame(pixels);
+        self.second.write_frame(pixels).and(first)
+    }
+
+    fn has_white(&self) -> bool {
+        self.first.has_white()
+    }
+
+    fn write_frame_rgbw(&mut self, pixels: &[ColorRgbw]) -> Result<()> {
+        let first = self.first.write_frame_rgbw(pixels);
+        self.second.write_frame_rgbw(pixels).and(first)
+    }
+
+    fn set_global_brightness(&mut self, brightness: u8) {
+        self.first.set_global_brightness(brightness);
+        self.second.set_global_brightness(brightness);
+    }
+}
@@ -0,0 +1,58 @@
+//! Terminal preview that draws each LED as a cell with a 24-bit ANSI
+//! background color.
+
+use super::LedDriver;
+use crate::color::Color;
+use crate::error::Result;
+
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Terminal row handed to the next preview opened, so several strips
+/// each get a line of their own.
+static NEXT_ROW: AtomicUsize = AtomicUsize::new(1);
+
+/// Driver that mirrors frames to standard output.
+///
+/// Each strip is drawn on a fixed row at the top of the terminal, so it
+/// works over SSH; log output still scrolls underneath.
+pub struct Terminal {
+    row: usize,
+    line: String,
+}
+
+impl Terminal {
+    pub fn new() -> Self {
+        Terminal {
+            row: NEXT_ROW.fetch_add(1, Ordering::Relaxed),
+            line: String::new(),
+        }
+    }
+}
+
+impl Default for Terminal {
+    fn default() -> Self {
+        Terminal::new()
+    }
+}
+
+impl LedDriver for Terminal {
+    fn write_frame(&mut self, pixels: &[Color]) -> Result<()> {
+        // Save the cursor, draw the row and put the cursor back.
+        self.line.clear();
+        let _ = write!(self.line, "\x1b7\x1b[{};1H", self.row);
+        for pixel in pixels {
+            let _ = write!(
+                self.line,
+                "\x1b[48;2;{};{};{}m ",
+                pixel.red, pixel.green, pixel.blue
+            );
+        }
+        self.line.push_str("\x1b[0m\x1b[K\x1b8");
+        let mut stdout = io::stdout().lock();
+        stdout.write_all(self.line.as_bytes())?;
+        stdout.flush()?;
+        Ok(())
+    }
+}
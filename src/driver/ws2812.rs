@@ -0,0 +1,99 @@
+use super::spi::{bus_error, SpidevBus};
+use super::LedDriver;
+use crate::color::Color;
+use crate::error::Result;
+
+use embedded_hal::spi::SpiBus;
+
+/// SPI clock at which three SPI bits span one 1.25us WS2812 bit period.
+pub const WS2812_SPI_HZ: u32 = 2_400_000;
+
+/// Zero bytes held low after a frame so the strip latches. At 2.4MHz this
+/// is 300us which also satisfies the longer reset of newer WS2812B parts.
+pub(super) const LATCH_BYTES: usize = 90;
+
+/// Driver for WS2812/WS2812B ("NeoPixel") chips.
+///
+/// The single-wire protocol is emulated on SPI MOSI by encoding each data
+/// bit as three SPI bits: `100` for a zero and `110` for a one.
+pub struct Ws2812<B = SpidevBus> {
+    spi: B,
+    bytes: Vec<u8>,
+}
+
+impl<B: SpiBus> Ws2812<B> {
+    pub fn new(spi: B) -> Self {
+        Ws2812 {
+            spi,
+            bytes: Vec::new(),
+        }
+    }
+}
+
+impl Ws2812 {
+    /// Open and configure the SPI device at `path`.
+    pub fn open(path: &str) -> Result<Self> {
+        Ok(Ws2812::new(SpidevBus::open(path, WS2812_SPI_HZ)?))
+    }
+}
+
+/// Expand one data byte into the 24 SPI bits that encode it.
+pub(super) fn encode_byte(byte: u8) -> [u8; 3] {
+    let mut bits: u32 = 0;
+    for i in (0..8).rev() {
+        bits <<= 3;
+        bits |= if byte & (1 << i) != 0 { 0b110 } else { 0b100 };
+    }
+    [(bits >> 16) as u8, (bits >> 8) as u8, bits as u8]
+}
+
+impl<B: SpiBus + Send> LedDriver for Ws2812<B> {
+    fn write_frame(&mut self, pixels: &[Color]) -> Result<()> {
+        self.bytes.clear();
+        // Keep the line low before the first bit.
+        self.bytes.push(0x00);
+        for pixel in pixels {
+            for byte in &[pixel.green, pixel.red, pixel.blue] {
+                self.bytes.extend_from_slice(&encode_byte(*byte));
+            }
+        }
+        self.bytes.extend(std::iter::repeat_n(0x00, LATCH_BYTES));
+        trace!("bytes: {:02x?}", self.bytes);
+        self.spi.write(&self.bytes).map_err(bus_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::driver::{Chip, MockBus};
+
+    #[test]
+    fn bits_become_spi_symbols() {
+        assert_eq!(encode_byte(0x00), [0x92, 0x49, 0x24]);
+        assert_eq!(encode_byte(0xff), [0xdb, 0x6d, 0xb6]);
+        assert_eq!(encode_byte(0x80), [0xd2, 0x49, 0x24]);
+        assert_eq!(encode_byte(0x01), [0x92, 0x49, 0x26]);
+    }
+
+    #[test]
+    fn pixel_wire_format() {
+        let bus = MockBus::validating(Chip::Ws2812);
+        let mut driver = Ws2812::new(bus.clone());
+        driver.write_frame(&[Color::new(0xff, 0x00, 0x80)]).unwrap();
+        let mut expected = vec![0x00];
+        // Green, red then blue.
+        expected.extend_from_slice(&[0x92, 0x49, 0x24, 0xdb, 0x6d, 0xb6, 0xd2, 0x49, 0x24]);
+        expected.extend_from_slice(&[0x00; LATCH_BYTES]);
+        assert_eq!(bus.last_frame().unwrap(), expected);
+    }
+
+    #[test]
+    fn latch_outlasts_the_reset() {
+        // Three SPI bits to each 1.25us data bit, and at least the 280us
+        // reset of WS2812B parts held low after the frame.
+        let bit = 1.0 / f64::from(WS2812_SPI_HZ);
+        assert!((3.0 * bit - 1.25e-6).abs() < 1e-9);
+        assert!(LATCH_BYTES as f64 * 8.0 * bit >= 280e-6);
+    }
+}
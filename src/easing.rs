@@ -0,0 +1,131 @@
+//! Easing curves for ramps and transitions.
+//!
+//! Each curve maps progress from 0 to 1 onto 0 to 1, easing in and out so
+//! a ramp starts and finishes gently instead of with a visible kink.
+
+use std::f64::consts::PI;
+use std::fmt;
+use std::str::FromStr;
+
+/// Names of the curves, for help and error messages.
+pub const NAMES: &[&str] = &["linear", "quad", "cubic", "sine", "expo", "bounce"];
+
+/// The shape of a ramp.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Easing {
+    /// Constant speed from start to finish.
+    Linear,
+    Quad,
+    Cubic,
+    /// Half a cosine; the gentlest of the smooth curves.
+    #[default]
+    Sine,
+    /// Exponential, for ramps that should mostly happen in the middle.
+    Expo,
+    /// Bounces off the start and end.
+    Bounce,
+}
+
+impl Easing {
+    /// Eased progress for `t`, clamped to 0 to 1.
+    pub fn apply(self, t: f64) -> f64 {
+        let t = t.clamp(0.0, 1.0);
+        // The in-out curves run the ease-in shape on the first half and
+        // mirror it on the second.
+        let in_out = |ease_in: fn(f64) -> f64| {
+            if t < 0.5 {
+                ease_in(2.0 * t) / 2.0
+            } else {
+                1.0 - ease_in(2.0 - 2.0 * t) / 2.0
+            }
+        };
+        match self {
+            Easing::Linear => t,
+            Easing::Quad => in_out(|t| t * t),
+            Easing::Cubic => in_out(|t| t * t * t),
+            Easing::Sine => (1.0 - (PI * t).cos()) / 2.0,
+            Easing::Expo => in_out(|t| {
+                if t <= 0.0 {
+                    0.0
+                } else {
+                    (10.0 * (t - 1.0)).exp2()
+                }
+            }),
+            Easing::Bounce => in_out(|t| 1.0 - bounce_out(1.0 - t)),
+        }
+    }
+}
+
+/// Robert Penner's bounce, settling at 1.
+fn bounce_out(t: f64) -> f64 {
+    const N: f64 = 7.5625;
+    const D: f64 = 2.75;
+    if t < 1.0 / D {
+        N * t * t
+    } else if t < 2.0 / D {
+        let t = t - 1.5 / D;
+        N * t * t + 0.75
+    } else if t < 2.5 / D {
+        let t = t - 2.25 / D;
+        N * t * t + 0.9375
+    } else {
+        let t = t - 2.625 / D;
+        N * t * t + 0.984375
+    }
+}
+
+impl FromStr for Easing {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "linear" => Ok(Easing::Linear),
+            "quad" => Ok(Easing::Quad),
+            "cubic" => Ok(Easing::Cubic),
+            "sine" => Ok(Easing::Sine),
+            "expo" => Ok(Easing::Expo),
+            "bounce" => Ok(Easing::Bounce),
+            _ => Err(format!(
+                "unknown easing {}, expected one of {}",
+                s,
+                NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Easing {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Easing::Linear => "linear",
+            Easing::Quad => "quad",
+            Easing::Cubic => "cubic",
+            Easing::Sine => "sine",
+            Easing::Expo => "expo",
+            Easing::Bounce => "bounce",
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn curves_span_zero_to_one() {
+        for name in NAMES {
+            let easing: Easing = name.parse().unwrap();
+            assert_eq!(easing.to_string(), *name);
+            assert!(easing.apply(0.0).abs() < 1.0e-3, "{}", name);
+            assert!((easing.apply(1.0) - 1.0).abs() < 1.0e-3, "{}", name);
+            assert!((easing.apply(0.5) - 0.5).abs() < 1.0e-9, "{}", name);
+            assert!((easing.apply(0.25) + easing.apply(0.75) - 1.0).abs() < 1.0e-9);
+        }
+        // Smooth curves ease in: slower than linear at the start.
+        for easing in &[Easing::Quad, Easing::Cubic, Easing::Sine, Easing::Expo] {
+            assert!(easing.apply(0.1) < 0.1);
+            let steps: Vec<f64> = (0..=100).map(|i| easing.apply(i as f64 / 100.0)).collect();
+            assert!(steps.windows(2).all(|w| w[1] >= w[0]));
+        }
+    }
+}
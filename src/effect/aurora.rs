@@ -0,0 +1,127 @@
+//! The northern lights: slow curtains of green, teal and purple with a
+//! brighter wave now and then, meant for a dim evening strip.
+
+use super::{Effect, Param, Params};
+use crate::color::{Color, Color16};
+use crate::noise::{noise1, noise2};
+use crate::palette::Palette;
+
+use std::time::Duration;
+
+pub const PARAMS: &[Param] = &[
+    Param {
+        name: "scale",
+        description: "pixels across a curtain [default: 30]",
+    },
+    Param {
+        name: "rate",
+        description: "how fast the curtains shift [default: 1]",
+    },
+    Param {
+        name: "brightness",
+        description:
+            "brightness of the curtains, 0 to 1, leaving room for the waves [default: 0.5]",
+    },
+    Param {
+        name: "waves",
+        description: "how often brighter waves pass, 0 to 1 [default: 0.3]",
+    },
+];
+
+/// Colors when no palette is chosen: green through teal to purple.
+const COLORS: &[Color] = &[
+    Color::new(0x00, 0x40, 0x10),
+    Color::new(0x10, 0xff, 0x60),
+    Color::new(0x00, 0xc0, 0xa0),
+    Color::new(0x70, 0x20, 0xc0),
+];
+
+/// Pixels a wave travels each second.
+const WAVE_RATE: f64 = 6.0;
+
+/// Pixels either side of a wave's peak it brightens.
+const WAVE_WIDTH: f64 = 6.0;
+
+/// Curtains of light, their color and brightness drifting with noise.
+pub struct Aurora {
+    palette: Palette,
+    scale: f64,
+    rate: f64,
+    brightness: f64,
+    waves: f64,
+}
+
+impl Aurora {
+    pub fn new(palette: Option<Palette>, params: &Params) -> std::result::Result<Self, String> {
+        Ok(Aurora {
+            palette: palette.unwrap_or_else(|| Palette::even(COLORS)),
+            scale: params.number("scale", 30.0, 1.0, 100_000.0)?,
+            rate: params.number("rate", 1.0, 0.0, 100.0)?,
+            brightness: params.number("brightness", 0.5, 0.0, 1.0)?,
+            waves: params.number("waves", 0.3, 0.0, 1.0)?,
+        })
+    }
+}
+
+impl Effect for Aurora {
+    fn render(&mut self, frame: &mut [Color16], t: Duration) {
+        let t = t.as_secs_f64() * self.rate;
+        let len = frame.len() as f64;
+        // A wave rolls along the strip each pass, but only shows on passes
+        // where noise strays further from zero than `waves` allows; at a
+        // half or more every pass has one.
+        let pass = t * WAVE_RATE / (len + 2.0 * WAVE_WIDTH);
+        let peak = pass.fract() * (len + 2.0 * WAVE_WIDTH) - WAVE_WIDTH;
+        let threshold = (1.0 - 2.0 * self.waves).max(0.0);
+        let stray = noise1(pass.floor() * 0.61 + 0.5).abs() - threshold;
+        let surge = (stray / (1.0 - threshold).max(0.01)).clamp(0.0, 1.0);
+
+        for (i, pixel) in frame.iter_mut().enumerate() {
+            let x = i as f64 / self.scale;
+            let hue = noise2(x * 0.7, t * 0.03);
+            let curtain = noise2(x + 17.3, t * 0.05);
+            // Squared so the curtains have dark gaps between them.
+            let level = ((curtain + 1.0) / 2.0).powi(2) * self.brightness;
+            let offset = (i as f64 - peak) / WAVE_WIDTH;
+            let wave = surge * (-offset * offset).exp();
+            let color = self.palette.sample((hue + 1.0) / 2.0 + wave * 0.25);
+            *pixel = Color16::from(color).scale((level + wave * (1.0 - level)).min(1.0));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn curtains_stay_dim_between_waves() {
+        let mut params = Params::default();
+        params.set("waves", "0");
+        let mut aurora = Aurora::new(None, &params).unwrap();
+        let mut frame = vec![Color16::default(); 90];
+        let mut brightest = 0;
+        for s in 0..100 {
+            aurora.render(&mut frame, Duration::from_secs(s * 7));
+            let level = frame.iter().map(|c| c.green.max(c.blue)).max().unwrap();
+            brightest = brightest.max(level);
+        }
+        assert!(
+            brightest > 10000 && brightest <= 65535 / 2 + 1,
+            "{}",
+            brightest
+        );
+        // There's no red to speak of in the default colors.
+        assert!(frame.iter().all(|c| c.red <= c.blue.max(c.green)));
+
+        // Waves light it brighter than the curtains alone.
+        aurora.waves = 1.0;
+        let mut brightest = 0;
+        for ms in 0..4000 {
+            aurora.render(&mut frame, Duration::from_millis(ms * 500));
+            let level = frame.iter().map(|c| c.green.max(c.blue)).max().unwrap();
+            brightest = brightest.max(level);
+        }
+        assert!(brightest > 50000, "{}", brightest);
+    }
+}
@@ -0,0 +1,154 @@
+//! Balls bouncing under gravity, losing height with every bounce.
+
+use super::{elapsed, Colors, Direction, Effect, Param, Params, MAX_GAP};
+use crate::color::Color16;
+use crate::palette::Palette;
+
+use std::time::Duration;
+
+pub const PARAMS: &[Param] = &[
+    Param {
+        name: "count",
+        description: "balls bouncing [default: 3]",
+    },
+    Param {
+        name: "gravity",
+        description: "pixels per second the balls fall faster each second [default: 40]",
+    },
+    Param {
+        name: "damping",
+        description: "share of its speed a ball keeps each bounce, 0 to 1 [default: 0.9]",
+    },
+    Param {
+        name: "direction",
+        description: "forward bounces off the first pixel, reverse off the last [default: forward]",
+    },
+];
+
+/// Longest step the simulation takes.
+const MAX_STEP: f64 = 0.02;
+
+/// A ball is thrown up again once its bounces are this slow, against the
+/// speed it was thrown at.
+const RESTART_SPEED: f64 = 0.15;
+
+struct Ball {
+    at: f64,
+    speed: f64,
+    /// Share of its speed this ball keeps; each differs a little so the
+    /// balls drift apart.
+    damping: f64,
+    color: Color16,
+}
+
+/// Balls dropped from the top of the strip, each its own color.
+pub struct Balls {
+    balls: Vec<Ball>,
+    gravity: f64,
+    direction: Direction,
+    last: Option<Duration>,
+}
+
+impl Balls {
+    pub fn new(palette: Option<Palette>, params: &Params) -> std::result::Result<Self, String> {
+        let colors = Colors::from_palette(palette);
+        let count: usize = params.get("count", 3)?;
+        let damping = params.number("damping", 0.9, 0.0, 1.0)?;
+        let balls = (0..count)
+            .map(|n| Ball {
+                // Dropped from the top; placed once the length is known.
+                at: f64::NAN,
+                speed: 0.0,
+                damping: damping * (1.0 - 0.04 * n as f64),
+                color: colors.at(n as f64 / count as f64),
+            })
+            .collect();
+        Ok(Balls {
+            balls,
+            gravity: params.number("gravity", 40.0, 1.0, 10000.0)?,
+            direction: params.get("direction", Direction::default())?,
+            last: None,
+        })
+    }
+
+    fn update(&mut self, step: f64, top: f64) {
+        // The speed that carries a ball from the floor to the top.
+        let throw = (2.0 * self.gravity * top).sqrt();
+        for ball in self.balls.iter_mut() {
+            if ball.at.is_nan() {
+                ball.at = top;
+            }
+            ball.speed -= self.gravity * step;
+            ball.at += ball.speed * step;
+            if ball.at <= 0.0 {
+                ball.at = -ball.at;
+                ball.speed = -ball.speed * ball.damping;
+                if ball.speed < throw * RESTART_SPEED {
+                    ball.speed = throw;
+                }
+            }
+        }
+    }
+}
+
+impl Effect for Balls {
+    fn render(&mut self, frame: &mut [Color16], t: Duration) {
+        let mut step = elapsed(&mut self.last, t, MAX_GAP);
+        let len = frame.len();
+        let top = len.saturating_sub(1) as f64;
+        if self.balls.iter().any(|b| b.at.is_nan()) {
+            self.update(0.0, top);
+        }
+        while step > 0.0 {
+            self.update(step.min(MAX_STEP), top);
+            step -= MAX_STEP;
+        }
+
+        for pixel in frame.iter_mut() {
+            *pixel = Color16::default();
+        }
+        for ball in &self.balls {
+            let i = ball.at.round().clamp(0.0, top) as usize;
+            let i = self.direction.along(i, len);
+            if let Some(pixel) = frame.get_mut(i) {
+                *pixel = pixel.saturating_add(ball.color);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn balls_bounce_lower_each_time() {
+        let mut params = Params::default();
+        params.set("count", "1");
+        params.set("damping", "0.5");
+        let mut balls = Balls::new(None, &params).unwrap();
+        let mut frame = vec![Color16::default(); 41];
+        balls.render(&mut frame, Duration::from_secs(0));
+        assert_ne!(frame[40], Color16::default());
+
+        // Track the highest point between bounces.
+        let mut peaks = Vec::new();
+        let (mut rising, mut high) = (false, 0.0f64);
+        for ms in 1..3000 {
+            balls.render(&mut frame, Duration::from_millis(ms * 5));
+            let ball = &balls.balls[0];
+            if ball.speed > 0.0 {
+                rising = true;
+                high = high.max(ball.at);
+            } else if rising {
+                peaks.push(high);
+                rising = false;
+                high = 0.0;
+            }
+        }
+        // A quarter of the height each bounce, until it is thrown again.
+        assert!((peaks[0] - 10.0).abs() < 1.0, "{:?}", peaks);
+        assert!((peaks[1] - 2.5).abs() < 0.5, "{:?}", peaks);
+        assert!(peaks.iter().any(|&p| p > 39.0), "{:?}", peaks);
+    }
+}
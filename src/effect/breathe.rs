@@ -0,0 +1,146 @@
+//! The whole strip pulsing slowly, like breathing.
+
+use super::{Colors, Effect, Param, Params};
+use crate::color::Color16;
+use crate::palette::Palette;
+
+use std::f64::consts::PI;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+pub const PARAMS: &[Param] = &[
+    Param {
+        name: "color",
+        description: "color to pulse [default: drift along the palette or wheel]",
+    },
+    Param {
+        name: "period",
+        description: "seconds per breath [default: 5]",
+    },
+    Param {
+        name: "min",
+        description: "brightness between breaths, 0 to 1 [default: 0.05]",
+    },
+    Param {
+        name: "max",
+        description: "brightness at the top of a breath, 0 to 1 [default: 1]",
+    },
+    Param {
+        name: "envelope",
+        description: "sine, or gaussian for a quicker breath and longer rest [default: sine]",
+    },
+];
+
+/// Breaths to drift once around the palette when no color is given.
+const BREATHS_PER_TURN: f64 = 8.0;
+
+/// Width of the gaussian breath, as a fraction of the period.
+const GAUSSIAN_WIDTH: f64 = 0.14;
+
+/// The shape of each breath.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Envelope {
+    /// Evenly in and out.
+    #[default]
+    Sine,
+    /// A bell, resting longer between breaths.
+    Gaussian,
+}
+
+impl Envelope {
+    /// Level from 0 to 1, `phase` of the way through a breath.
+    pub fn level(self, phase: f64) -> f64 {
+        match self {
+            Envelope::Sine => 0.5 - 0.5 * (2.0 * PI * phase).cos(),
+            Envelope::Gaussian => {
+                let d = phase - 0.5;
+                (-(d * d) / (2.0 * GAUSSIAN_WIDTH * GAUSSIAN_WIDTH)).exp()
+            }
+        }
+    }
+}
+
+impl FromStr for Envelope {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "sine" => Ok(Envelope::Sine),
+            "gaussian" => Ok(Envelope::Gaussian),
+            _ => Err(format!("unknown envelope {}, expected sine or gaussian", s)),
+        }
+    }
+}
+
+impl fmt::Display for Envelope {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Envelope::Sine => "sine",
+            Envelope::Gaussian => "gaussian",
+        })
+    }
+}
+
+/// One color over the strip, its brightness rising and falling.
+pub struct Breathe {
+    colors: Colors,
+    period: f64,
+    min: f64,
+    max: f64,
+    envelope: Envelope,
+}
+
+impl Breathe {
+    pub fn new(palette: Option<Palette>, params: &Params) -> std::result::Result<Self, String> {
+        let min = params.number("min", 0.05, 0.0, 1.0)?;
+        let max = params.number("max", 1.0, 0.0, 1.0)?;
+        if min > max {
+            return Err(format!("min {} is above max {}", min, max));
+        }
+        Ok(Breathe {
+            colors: Colors::from_params(params, "color", palette)?,
+            period: params.number("period", 5.0, 0.1, 3600.0)?,
+            min,
+            max,
+            envelope: params.get("envelope", Envelope::default())?,
+        })
+    }
+}
+
+impl Effect for Breathe {
+    fn render(&mut self, frame: &mut [Color16], t: Duration) {
+        let breaths = t.as_secs_f64() / self.period;
+        let level = self.min + (self.max - self.min) * self.envelope.level(breaths.fract());
+        let color = self.colors.at(breaths / BREATHS_PER_TURN).scale(level);
+        for pixel in frame.iter_mut() {
+            *pixel = color;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::effect::parse_param;
+
+    #[test]
+    fn breathes_between_min_and_max() {
+        let params: Params = ["color=#ffffff", "period=2", "min=0.25", "envelope=gaussian"]
+            .iter()
+            .map(|p| parse_param(p).unwrap())
+            .collect();
+        let mut breathe = Breathe::new(None, &params).unwrap();
+        let mut frame = vec![Color16::default(); 2];
+        breathe.render(&mut frame, Duration::from_secs(1));
+        assert_eq!(frame[1], Color16::new(65535, 65535, 65535));
+        breathe.render(&mut frame, Duration::from_secs(4));
+        assert!(frame[0].red >= 16384 && frame[0].red < 16500);
+
+        assert_eq!(Envelope::Sine.level(0.5), 1.0);
+        let mut bad = Params::default();
+        bad.set("min", "0.8");
+        bad.set("max", "0.5");
+        assert!(Breathe::new(None, &bad).is_err());
+    }
+}
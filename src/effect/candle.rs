@@ -0,0 +1,115 @@
+//! Candlelight: warm white wavering gently, each pixel a flame of its own.
+
+use super::{Effect, Param, Params};
+use crate::color::{Color, Color16};
+use crate::palette::Palette;
+use crate::random::Rng;
+
+use std::time::Duration;
+
+pub const PARAMS: &[Param] = &[
+    Param {
+        name: "color",
+        description: "color of the flame at its brightest [default: #ff9329]",
+    },
+    Param {
+        name: "flicker",
+        description: "how far the flames dim, 0 to 1 [default: 0.35]",
+    },
+    Param {
+        name: "rate",
+        description: "how lively the flames are [default: 1]",
+    },
+];
+
+/// Steps each second at a rate of 1.
+const STEPS_PER_SECOND: f64 = 30.0;
+
+/// Most steps caught up on in one frame, after a pause.
+const MAX_STEPS: u32 = 10;
+
+/// Share of the walk kept each step, pulling it back towards steady.
+const DRIFT: f64 = 0.94;
+
+/// Largest random change to the walk each step.
+const KICK: f64 = 0.3;
+
+/// Share of the gap to the walk the shown level closes each step, which
+/// smooths out the random steps so the flame wavers rather than strobes.
+const SMOOTHING: f64 = 0.15;
+
+/// What a dimming flame shifts towards.
+const EMBER: Color = Color::new(0xff, 0x40, 0x00);
+
+/// Flames wavering independently, dimming and reddening at random.
+pub struct Candle {
+    color: Color16,
+    flicker: f64,
+    rate: f64,
+    rng: Rng,
+    /// Random walk and its smoothed value for each pixel.
+    flames: Vec<(f64, f64)>,
+    steps: u64,
+}
+
+impl Candle {
+    pub fn new(_palette: Option<Palette>, params: &Params) -> std::result::Result<Self, String> {
+        Ok(Candle {
+            color: params.get("color", Color::new(0xff, 0x93, 0x29))?.into(),
+            flicker: params.number("flicker", 0.35, 0.0, 1.0)?,
+            rate: params.number("rate", 1.0, 0.0, 10.0)?,
+            rng: Rng::from_time(),
+            flames: Vec::new(),
+            steps: 0,
+        })
+    }
+}
+
+impl Effect for Candle {
+    fn render(&mut self, frame: &mut [Color16], t: Duration) {
+        self.flames.resize(frame.len(), (0.0, 0.0));
+        let due = (t.as_secs_f64() * STEPS_PER_SECOND * self.rate) as u64;
+        let behind = due.saturating_sub(self.steps).min(u64::from(MAX_STEPS));
+        self.steps = due;
+        for _ in 0..behind {
+            for flame in self.flames.iter_mut() {
+                flame.0 = flame.0 * DRIFT + (self.rng.unit() - 0.5) * KICK;
+                flame.1 += (flame.0 - flame.1) * SMOOTHING;
+            }
+        }
+        let ember = Color16::from(EMBER);
+        for (pixel, flame) in frame.iter_mut().zip(&self.flames) {
+            // The walk rarely strays beyond a half either way.
+            let dim = (0.5 + flame.1).clamp(0.0, 1.0) * self.flicker;
+            *pixel = self.color.lerp(ember, dim).scale(1.0 - dim);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flames_waver_independently() {
+        let mut candle = Candle::new(None, &Params::default()).unwrap();
+        candle.rng = Rng::new(5);
+        let mut frame = vec![Color16::default(); 8];
+        let mut last = frame.clone();
+        let (mut low, mut high) = (u16::MAX, 0);
+        for i in 0..900 {
+            candle.render(&mut frame, Duration::from_millis(i * 33));
+            for (now, before) in frame.iter().zip(&last).filter(|_| i > 0) {
+                // Gently, frame to frame.
+                assert!((i32::from(now.red) - i32::from(before.red)).abs() < 3000);
+            }
+            last = frame.clone();
+            low = low.min(frame[0].red);
+            high = high.max(frame[0].red);
+        }
+        assert!(high - low > 5000, "{} {}", low, high);
+        assert!(frame.iter().any(|c| *c != frame[0]));
+        // Never dimmer than the flicker allows.
+        assert!(low >= 42000, "{}", low);
+    }
+}
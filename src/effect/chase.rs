@@ -0,0 +1,121 @@
+//! Theater marquee lights: every few pixels lit, stepping along.
+
+use super::{colors, Colors, Direction, Effect, Param, Params};
+use crate::color::Color16;
+use crate::palette::Palette;
+
+use std::time::Duration;
+
+pub const PARAMS: &[Param] = &[
+    Param {
+        name: "spacing",
+        description: "pixels from one lit pixel to the next [default: 3]",
+    },
+    Param {
+        name: "color",
+        description: "color of the lit pixels [default: the palette or wheel along the strip]",
+    },
+    Param {
+        name: "colors",
+        description: "colors the lit pixels take in turn, separated by commas, over color",
+    },
+    Param {
+        name: "direction",
+        description: "forward or reverse [default: forward]",
+    },
+    Param {
+        name: "steps",
+        description: "steps along each second [default: 8]",
+    },
+];
+
+/// Every `spacing`th pixel lit, moving on one pixel at a time.
+pub struct Chase {
+    colors: Colors,
+    /// Colors for the lit pixels in turn, when given.
+    turns: Vec<Color16>,
+    spacing: usize,
+    direction: Direction,
+    steps: f64,
+}
+
+impl Chase {
+    pub fn new(palette: Option<Palette>, params: &Params) -> std::result::Result<Self, String> {
+        let spacing = params.get("spacing", 3)?;
+        if spacing < 2 {
+            return Err(format!("spacing must be 2 or more, not {}", spacing));
+        }
+        Ok(Chase {
+            turns: match params.optional::<String>("colors")? {
+                Some(_) => colors::list(params, "colors", None, 0)?,
+                None => Vec::new(),
+            },
+            colors: Colors::from_params(params, "color", palette)?,
+            spacing,
+            direction: params.get("direction", Direction::default())?,
+            steps: params.number("steps", 8.0, 0.0, 1000.0)?,
+        })
+    }
+}
+
+impl Effect for Chase {
+    fn render(&mut self, frame: &mut [Color16], t: Duration) {
+        let len = frame.len();
+        let steps = (t.as_secs_f64() * self.steps) as i64;
+        let spacing = self.spacing as i64;
+        for (i, pixel) in frame.iter_mut().enumerate() {
+            // Pixels from where the first lit pixel started, so each keeps
+            // its color in turn as it moves.
+            let from = self.direction.along(i, len) as i64 - steps;
+            *pixel = if from.rem_euclid(spacing) != 0 {
+                Color16::default()
+            } else if self.turns.is_empty() {
+                self.colors.at(i as f64 / len as f64)
+            } else {
+                let turn = from.div_euclid(spacing).rem_euclid(self.turns.len() as i64);
+                self.turns[turn as usize]
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::effect::parse_param;
+
+    #[test]
+    fn steps_along_the_strip() {
+        let lit = |params: &[&str], secs: f64| {
+            let params: Params = params.iter().map(|p| parse_param(p).unwrap()).collect();
+            let mut chase = Chase::new(None, &params).unwrap();
+            let mut frame = vec![Color16::default(); 7];
+            chase.render(&mut frame, Duration::from_secs_f64(secs));
+            frame
+                .iter()
+                .enumerate()
+                .filter(|(_, c)| **c != Color16::default())
+                .map(|(i, _)| i)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(lit(&["steps=1"], 0.0), [0, 3, 6]);
+        assert_eq!(lit(&["steps=1"], 1.0), [1, 4]);
+        assert_eq!(lit(&["steps=1", "direction=reverse"], 1.0), [2, 5]);
+        assert_eq!(lit(&["spacing=4", "steps=1"], 3.5), [3]);
+
+        // Each lit pixel keeps its color as it steps along.
+        let mut params = Params::default();
+        params.set("colors", "red, lime");
+        params.set("steps", "1");
+        let mut chase = Chase::new(None, &params).unwrap();
+        let mut frame = vec![Color16::default(); 7];
+        chase.render(&mut frame, Duration::from_secs(0));
+        let (red, green) = (frame[0], frame[3]);
+        assert_ne!(red, green);
+        chase.render(&mut frame, Duration::from_secs(1));
+        assert_eq!((frame[1], frame[4]), (red, green));
+        let mut params = Params::default();
+        params.set("spacing", "1");
+        assert!(Chase::new(None, &params).is_err());
+    }
+}
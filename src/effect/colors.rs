@@ -0,0 +1,88 @@
+//! Where effects take their colors from.
+
+use super::Params;
+use crate::color::{Color, Color16};
+use crate::colorspace::hsv_to_rgb;
+use crate::palette::Palette;
+
+/// Split `s` at commas outside parentheses, so "rgb(1, 2, 3)" stays whole.
+fn split_list(s: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut depth, mut start) = (0, 0);
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(s[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(s[start..].trim());
+    parts
+}
+
+/// Parameter `key` as a list of colors separated by commas, or `steps`
+/// colors evenly around the palette or wheel without one.
+pub fn list(
+    params: &Params,
+    key: &str,
+    palette: Option<Palette>,
+    steps: usize,
+) -> std::result::Result<Vec<Color16>, String> {
+    match params.optional::<String>(key)? {
+        Some(list) => split_list(&list)
+            .into_iter()
+            .map(|c| c.parse::<Color>().map(Color16::from))
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| format!("bad {} {}: {}", key, list, e)),
+        None => {
+            let colors = Colors::from_palette(palette);
+            Ok((0..steps)
+                .map(|i| colors.at(i as f64 / steps as f64))
+                .collect())
+        }
+    }
+}
+
+/// A color given as a parameter, else the palette, else the color wheel.
+#[derive(Debug, Clone)]
+pub enum Colors {
+    One(Color16),
+    Palette(Palette),
+    Wheel,
+}
+
+impl Colors {
+    /// The palette, or the wheel without one.
+    pub fn from_palette(palette: Option<Palette>) -> Self {
+        palette.map_or(Colors::Wheel, Colors::Palette)
+    }
+
+    /// Parameter `key` as a color, or the palette without one.
+    pub fn from_params(
+        params: &Params,
+        key: &str,
+        palette: Option<Palette>,
+    ) -> std::result::Result<Self, String> {
+        Ok(match params.optional::<Color>(key)? {
+            Some(color) => Colors::One(color.into()),
+            None => Colors::from_palette(palette),
+        })
+    }
+
+    /// The color `t` of the way around, wrapping every 1.
+    pub fn at(&self, t: f64) -> Color16 {
+        match self {
+            Colors::One(color) => *color,
+            Colors::Palette(palette) => Color16::from(palette.sample_cyclic(t)),
+            Colors::Wheel => {
+                let (r, g, b) = hsv_to_rgb(t.rem_euclid(1.0) * 360.0, 1.0, 1.0);
+                let level = |v: f64| (v * 65535.0).round() as u16;
+                Color16::new(level(r), level(g), level(b))
+            }
+        }
+    }
+}
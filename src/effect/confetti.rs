@@ -0,0 +1,101 @@
+//! Confetti: pixels popping on in random colors and fading, after
+//! FastLED's demo reel.
+
+use super::{elapsed, Colors, Effect, Param, Params, MAX_GAP};
+use crate::color::Color16;
+use crate::palette::Palette;
+use crate::random::Rng;
+
+use std::time::Duration;
+
+pub const PARAMS: &[Param] = &[
+    Param {
+        name: "rate",
+        description: "pixels popping on each second [default: 20]",
+    },
+    Param {
+        name: "decay",
+        description: "seconds a pop takes to fade to a tenth [default: 1]",
+    },
+];
+
+/// How far along the palette or wheel pops stray from the drifting hue,
+/// and how fast that hue drifts, in turns a second.
+const SPREAD: f64 = 0.25;
+const DRIFT: f64 = 0.02;
+
+/// Colors popping on at random and fading over what came before.
+pub struct Confetti {
+    colors: Colors,
+    rate: f64,
+    decay: f64,
+    rng: Rng,
+    pixels: Vec<Color16>,
+    /// Pops due but not yet placed, as a fraction of one.
+    owed: f64,
+    last: Option<Duration>,
+}
+
+impl Confetti {
+    pub fn new(palette: Option<Palette>, params: &Params) -> std::result::Result<Self, String> {
+        Ok(Confetti {
+            colors: Colors::from_palette(palette),
+            rate: params.number("rate", 20.0, 0.0, 10000.0)?,
+            decay: params.number("decay", 1.0, 0.01, 600.0)?,
+            rng: Rng::from_time(),
+            pixels: Vec::new(),
+            owed: 0.0,
+            last: None,
+        })
+    }
+}
+
+impl Effect for Confetti {
+    fn render(&mut self, frame: &mut [Color16], t: Duration) {
+        let len = frame.len();
+        self.pixels.resize(len, Color16::default());
+        let step = elapsed(&mut self.last, t, MAX_GAP);
+
+        let fade = 0.1f64.powf(step / self.decay);
+        for pixel in self.pixels.iter_mut() {
+            *pixel = pixel.scale(fade);
+        }
+        if len > 0 {
+            self.owed += self.rate * step;
+            let hue = t.as_secs_f64() * DRIFT;
+            while self.owed >= 1.0 {
+                self.owed -= 1.0;
+                let color = self.colors.at(hue + self.rng.unit() * SPREAD);
+                let i = self.rng.below(len);
+                self.pixels[i] = self.pixels[i].saturating_add(color);
+            }
+        }
+        frame.copy_from_slice(&self.pixels);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pops_in_and_fades() {
+        let mut confetti = Confetti::new(None, &Params::default()).unwrap();
+        confetti.rng = Rng::new(3);
+        let mut frame = vec![Color16::default(); 40];
+        for ms in 0..=50 {
+            confetti.render(&mut frame, Duration::from_millis(ms * 20));
+        }
+        let lit = frame.iter().filter(|c| **c != Color16::default()).count();
+        assert!(lit > 5, "{} lit", lit);
+
+        // With no more pops, two seconds fades them to a hundredth.
+        confetti.rate = 0.0;
+        let brightest =
+            |frame: &[Color16]| frame.iter().map(|c| c.red.max(c.green).max(c.blue)).max();
+        let before = brightest(&frame).unwrap();
+        confetti.render(&mut frame, Duration::from_secs(2));
+        confetti.render(&mut frame, Duration::from_secs(3));
+        assert!(brightest(&frame).unwrap() <= before / 100 + 1);
+    }
+}
@@ -0,0 +1,182 @@
+//! Emergency lights: each half of the strip flashing its own color in
+//! bursts, taking turns.
+
+use super::strobe::MAX_FREQUENCY;
+use super::{colors, Effect, Param, Params};
+use crate::color::{Color, Color16};
+
+use std::time::{Duration, Instant};
+
+pub const PARAMS: &[Param] = &[
+    Param {
+        name: "colors",
+        description: "the two halves' colors, separated by a comma [default: red, blue]",
+    },
+    Param {
+        name: "rate",
+        description: "turns each half takes a second, kept to 3 flashes a second [default: 0.5]",
+    },
+    Param {
+        name: "pulses",
+        description: "flashes in each half's burst, 1 to 4 [default: 2]",
+    },
+];
+
+/// Share of a half's turn its burst lasts; the rest is a pause before the
+/// other half.
+const BURST: f64 = 0.75;
+
+/// Two colors flashing in turns across the halves of the strip.
+pub struct Emergency {
+    colors: [Color16; 2],
+    rate: f64,
+    pulses: u32,
+    /// The flash being shown, counted from the start of the effect.
+    flash: Option<u64>,
+    /// When the latest flash began.
+    started: Option<Instant>,
+}
+
+impl Emergency {
+    pub fn new(params: &Params) -> std::result::Result<Self, String> {
+        let colors = if params.optional::<String>("colors")?.is_some() {
+            colors::list(params, "colors", None, 2)?
+        } else {
+            vec![Color::new(0xff, 0, 0).into(), Color::new(0, 0, 0xff).into()]
+        };
+        let colors = match colors[..] {
+            [first, second] => [first, second],
+            _ => return Err(format!("colors needs two colors, not {}", colors.len())),
+        };
+        let pulses = params.get("pulses", 2)?;
+        if !(1..=4).contains(&pulses) {
+            return Err(format!("pulses must be from 1 to 4, not {}", pulses));
+        }
+        let rate = params.number("rate", 0.5, 0.01, MAX_FREQUENCY * BURST / 2.0)?;
+        // Each half's turn is 1 / (2 * rate) seconds with its flashes
+        // packed into the burst, so they come this often.
+        let frequency = 2.0 * rate * f64::from(pulses) / BURST;
+        if frequency > MAX_FREQUENCY {
+            return Err(format!(
+                "rate {} with {} pulses flashes {:.1} times a second, more than the {} allowed",
+                rate, pulses, frequency, MAX_FREQUENCY
+            ));
+        }
+        Ok(Emergency {
+            colors,
+            rate,
+            pulses,
+            flash: None,
+            started: None,
+        })
+    }
+
+    /// The frame at effect time `t` and real time `now`. As with the
+    /// strobe, a flash that would begin too soon after the last is skipped
+    /// so speeding the effect up can't pass `MAX_FREQUENCY`.
+    fn render_at(&mut self, frame: &mut [Color16], t: Duration, now: Instant) {
+        // Halves' turns since the start: the first half's, then the second's.
+        let turns = t.as_secs_f64() * self.rate * 2.0;
+        let side = (turns as u64 % 2) as usize;
+        // Into the burst, counted in pulses, each lit for its first half.
+        let pulse = turns.fract() / BURST * f64::from(self.pulses);
+        let lit = pulse < f64::from(self.pulses) && pulse.fract() < 0.5;
+        let flash = turns as u64 * u64::from(self.pulses) + pulse as u64;
+        if lit && self.flash != Some(flash) {
+            let gap = Duration::from_secs_f64(1.0 / MAX_FREQUENCY);
+            let rested = self
+                .started
+                .is_none_or(|started| now.saturating_duration_since(started) >= gap);
+            if rested {
+                self.flash = Some(flash);
+                self.started = Some(now);
+            }
+        }
+        let lit = lit && self.flash == Some(flash);
+
+        let half = frame.len() / 2;
+        for (i, pixel) in frame.iter_mut().enumerate() {
+            let ours = if i < half { 0 } else { 1 };
+            *pixel = if lit && ours == side {
+                self.colors[side]
+            } else {
+                Color16::default()
+            };
+        }
+    }
+}
+
+impl Effect for Emergency {
+    fn render(&mut self, frame: &mut [Color16], t: Duration) {
+        self.render_at(frame, t, Instant::now());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Flashes begun on each half over `seconds` of frames 1ms apart,
+    /// with the effect running `speed` times faster than real time.
+    fn flashes(lights: &mut Emergency, seconds: u64, speed: u32) -> [usize; 2] {
+        let start = Instant::now();
+        let mut frame = vec![Color16::default(); 10];
+        let (mut count, mut was_lit) = ([0, 0], [false, false]);
+        for ms in 0..seconds * 1000 {
+            let t = Duration::from_millis(ms);
+            lights.render_at(&mut frame, t * speed, start + t);
+            assert!(frame[0] == Color16::default() || frame[9] == Color16::default());
+            for (side, &i) in [0, 9].iter().enumerate() {
+                let lit = frame[i] != Color16::default();
+                if lit && !was_lit[side] {
+                    count[side] += 1;
+                }
+                was_lit[side] = lit;
+            }
+        }
+        count
+    }
+
+    #[test]
+    fn halves_take_turns_in_bursts() {
+        let mut params = Params::default();
+        params.set("rate", "0.25");
+        params.set("pulses", "3");
+        let mut lights = Emergency::new(&params).unwrap();
+        // Both halves' turns in each of two four second cycles, three
+        // flashes a turn.
+        assert_eq!(flashes(&mut lights, 8, 1), [6, 6]);
+
+        let mut lights = Emergency::new(&params).unwrap();
+        let start = Instant::now();
+        let mut frame = vec![Color16::default(); 10];
+        lights.render_at(&mut frame, Duration::from_secs(0), start);
+        assert_eq!(frame[4], Color::new(0xff, 0, 0).into());
+        lights.render_at(
+            &mut frame,
+            Duration::from_secs(2),
+            start + Duration::from_secs(2),
+        );
+        assert_eq!(frame[5], Color::new(0, 0, 0xff).into());
+    }
+
+    #[test]
+    fn flash_rate_is_capped() {
+        let mut params = Params::default();
+        assert!(Emergency::new(&params).is_ok());
+        // Four flashes in each 125ms burst.
+        params.set("rate", "3");
+        params.set("pulses", "4");
+        assert!(Emergency::new(&params).is_err());
+        params.set("rate", "1");
+        params.set("pulses", "2");
+        assert!(Emergency::new(&params).is_err());
+
+        // The fastest allowed, sped up, still flashes 3 times a second at most.
+        params.set("rate", "1.125");
+        params.set("pulses", "1");
+        let mut lights = Emergency::new(&params).unwrap();
+        let [first, second] = flashes(&mut lights, 2, 50);
+        assert!(first + second <= 6, "{} {}", first, second);
+    }
+}
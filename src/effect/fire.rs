@@ -0,0 +1,137 @@
+//! Fire, after Mark Kriegsman's Fire2012: heat rises from sparks at the
+//! base of the strip, cooling as it goes, and is shown through a palette.
+
+use super::{Direction, Effect, Param, Params};
+use crate::color::Color16;
+use crate::palette::Palette;
+use crate::random::Rng;
+
+use std::time::Duration;
+
+pub const PARAMS: &[Param] = &[
+    Param {
+        name: "cooling",
+        description: "how fast heat fades as it rises, 20 to 100 looks right [default: 55]",
+    },
+    Param {
+        name: "sparking",
+        description: "chance out of 255 of a new spark each step [default: 120]",
+    },
+    Param {
+        name: "direction",
+        description: "forward burns from the first pixel, reverse from the last [default: forward]",
+    },
+];
+
+/// Steps the simulation takes each second, the rate Fire2012 was tuned at.
+const STEPS_PER_SECOND: f64 = 60.0;
+
+/// Pixels at the base sparks start in.
+const SPARK_PIXELS: usize = 7;
+
+/// Most steps caught up on in one frame, after a pause.
+const MAX_STEPS: u32 = 10;
+
+/// Heat rising from random sparks, shown through the heat palette or the
+/// one chosen.
+pub struct Fire {
+    palette: Palette,
+    cooling: u32,
+    sparking: u32,
+    direction: Direction,
+    rng: Rng,
+    heat: Vec<u8>,
+    /// Simulation steps taken.
+    steps: u64,
+}
+
+impl Fire {
+    pub fn new(palette: Option<Palette>, params: &Params) -> std::result::Result<Self, String> {
+        let cooling = params.number("cooling", 55.0, 0.0, 255.0)? as u32;
+        let sparking = params.number("sparking", 120.0, 0.0, 255.0)? as u32;
+        Ok(Fire {
+            palette: palette
+                .or_else(|| Palette::builtin("heat"))
+                .ok_or("no heat palette")?,
+            cooling,
+            sparking,
+            direction: params.get("direction", Direction::default())?,
+            rng: Rng::from_time(),
+            heat: Vec::new(),
+            steps: 0,
+        })
+    }
+
+    /// A random number from `low` to just under `high`.
+    fn between(&mut self, low: u32, high: u32) -> u32 {
+        low + self.rng.below((high - low) as usize) as u32
+    }
+
+    fn step(&mut self) {
+        let len = self.heat.len();
+        // Every pixel cools a little.
+        let most = self.cooling * 10 / len as u32 + 2;
+        for i in 0..len {
+            let cooled = self.between(0, most);
+            self.heat[i] = (u32::from(self.heat[i]).saturating_sub(cooled)) as u8;
+        }
+        // Heat drifts up and spreads.
+        for k in (2..len).rev() {
+            let h = &self.heat;
+            let rising = (u32::from(h[k - 1]) + 2 * u32::from(h[k - 2])) / 3;
+            self.heat[k] = rising as u8;
+        }
+        // Now and then a spark near the base.
+        if self.between(0, 255) < self.sparking {
+            let y = self.rng.below(SPARK_PIXELS.min(len));
+            let spark = self.between(160, 255);
+            self.heat[y] = (u32::from(self.heat[y]) + spark).min(255) as u8;
+        }
+    }
+}
+
+impl Effect for Fire {
+    fn render(&mut self, frame: &mut [Color16], t: Duration) {
+        let len = frame.len();
+        if len == 0 {
+            return;
+        }
+        self.heat.resize(len, 0);
+        let due = (t.as_secs_f64() * STEPS_PER_SECOND) as u64;
+        let behind = due.saturating_sub(self.steps).min(u64::from(MAX_STEPS));
+        for _ in 0..behind {
+            self.step();
+        }
+        self.steps = due;
+        for (i, pixel) in frame.iter_mut().enumerate() {
+            let heat = self.heat[self.direction.along(i, len)];
+            *pixel = Color16::from(self.palette.sample(f64::from(heat) / 255.0));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn burns_hottest_at_the_base() {
+        let mut fire = Fire::new(None, &Params::default()).unwrap();
+        fire.rng = Rng::new(11);
+        let mut frame = vec![Color16::default(); 60];
+        let (mut base, mut top) = (0u64, 0u64);
+        for i in 0..600 {
+            fire.render(&mut frame, Duration::from_millis(i * 1000 / 60));
+            base += fire.heat[..10].iter().map(|&h| u64::from(h)).sum::<u64>();
+            top += fire.heat[50..].iter().map(|&h| u64::from(h)).sum::<u64>();
+        }
+        assert!(base > 3 * top, "{} {}", base, top);
+
+        // Without sparks it dies down to the palette's coldest color.
+        fire.sparking = 0;
+        for i in 600..900 {
+            fire.render(&mut frame, Duration::from_millis(i * 1000 / 60));
+        }
+        assert!(frame.iter().all(|c| *c == Color16::default()));
+    }
+}
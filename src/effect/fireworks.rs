@@ -0,0 +1,202 @@
+//! Fireworks: rockets rising from one end and bursting into sparks that
+//! fall back and fade.
+
+use super::{elapsed, Colors, Direction, Effect, Param, Params, MAX_GAP};
+use crate::color::{Color, Color16};
+use crate::palette::Palette;
+use crate::random::Rng;
+
+use std::time::Duration;
+
+pub const PARAMS: &[Param] = &[
+    Param {
+        name: "launches",
+        description: "rockets launched each second, on average [default: 0.5]",
+    },
+    Param {
+        name: "sparks",
+        description: "sparks in each burst [default: 14]",
+    },
+    Param {
+        name: "decay",
+        description: "seconds the sparks last [default: 1.5]",
+    },
+    Param {
+        name: "gravity",
+        description: "pixels per second things fall faster each second [default: 30]",
+    },
+    Param {
+        name: "direction",
+        description:
+            "forward launches from the first pixel, reverse from the last [default: forward]",
+    },
+];
+
+/// Longest step the simulation takes.
+const MAX_STEP: f64 = 0.1;
+
+/// How far up the strip rockets burst, as fractions of its length.
+const LOWEST_BURST: f64 = 0.4;
+const HIGHEST_BURST: f64 = 0.9;
+
+/// Fastest a spark leaves its burst, against the strip's length each
+/// second.
+const BURST_SPEED: f64 = 0.3;
+
+/// How far along the palette or wheel a burst's sparks may stray from its
+/// color.
+const COLOR_SPREAD: f64 = 0.08;
+
+const ROCKET: Color = Color::new(0xff, 0xb0, 0x60);
+
+struct Spark {
+    at: f64,
+    speed: f64,
+    color: Color16,
+    age: f64,
+    life: f64,
+}
+
+/// Rockets climbing against gravity and bursting at the top of their climb.
+pub struct Fireworks {
+    colors: Colors,
+    launches: f64,
+    sparks: usize,
+    decay: f64,
+    gravity: f64,
+    direction: Direction,
+    rng: Rng,
+    rockets: Vec<(f64, f64)>,
+    burning: Vec<Spark>,
+    last: Option<Duration>,
+}
+
+impl Fireworks {
+    pub fn new(palette: Option<Palette>, params: &Params) -> std::result::Result<Self, String> {
+        Ok(Fireworks {
+            colors: Colors::from_palette(palette),
+            launches: params.number("launches", 0.5, 0.0, 100.0)?,
+            sparks: params.get("sparks", 14)?,
+            decay: params.number("decay", 1.5, 0.1, 60.0)?,
+            gravity: params.number("gravity", 30.0, 1.0, 10000.0)?,
+            direction: params.get("direction", Direction::default())?,
+            rng: Rng::from_time(),
+            rockets: Vec::new(),
+            burning: Vec::new(),
+            last: None,
+        })
+    }
+
+    /// Sparks flying out from `at`, around one color.
+    fn burst(&mut self, at: f64, len: f64) {
+        let hue = self.rng.unit();
+        for _ in 0..self.sparks {
+            let spread = (self.rng.unit() - 0.5) * 2.0;
+            let color = self.colors.at(hue + (self.rng.unit() - 0.5) * COLOR_SPREAD);
+            let life = self.decay * (0.6 + 0.8 * self.rng.unit());
+            self.burning.push(Spark {
+                at,
+                speed: spread * BURST_SPEED * len,
+                color,
+                age: 0.0,
+                life,
+            });
+        }
+    }
+
+    fn update(&mut self, step: f64, len: f64) {
+        let gravity = self.gravity;
+        let mut bursts = Vec::new();
+        self.rockets.retain_mut(|(at, speed)| {
+            *speed -= gravity * step;
+            *at += *speed * step;
+            if *speed <= 0.0 {
+                bursts.push(*at);
+            }
+            *speed > 0.0
+        });
+        for at in bursts {
+            self.burst(at, len);
+        }
+        for spark in self.burning.iter_mut() {
+            spark.speed -= gravity * step;
+            spark.at += spark.speed * step;
+            spark.age += step;
+        }
+        self.burning.retain(|s| s.age < s.life && s.at > -1.0);
+
+        if self.rng.unit() < self.launches * step {
+            let height = len * (LOWEST_BURST + (HIGHEST_BURST - LOWEST_BURST) * self.rng.unit());
+            // Fast enough to climb to `height` before gravity stops it.
+            self.rockets.push((0.0, (2.0 * gravity * height).sqrt()));
+        }
+    }
+}
+
+impl Effect for Fireworks {
+    fn render(&mut self, frame: &mut [Color16], t: Duration) {
+        let mut step = elapsed(&mut self.last, t, MAX_GAP);
+        let len = frame.len();
+        // Small steps keep the climb true however far apart frames are.
+        while step > 0.0 {
+            self.update(step.min(MAX_STEP), len as f64);
+            step -= MAX_STEP;
+        }
+
+        for pixel in frame.iter_mut() {
+            *pixel = Color16::default();
+        }
+        let rocket = Color16::from(ROCKET);
+        let lights =
+            self.rockets
+                .iter()
+                .map(|&(at, _)| (at, rocket, 0.7))
+                .chain(self.burning.iter().map(|s| {
+                    let left = 1.0 - s.age / s.life;
+                    (s.at, s.color, left * left)
+                }));
+        for (at, color, level) in lights {
+            // Shared between the two pixels either side.
+            let first = at.floor();
+            for (pos, share) in [(first, 1.0 - (at - first)), (first + 1.0, at - first)] {
+                if pos >= 0.0 && (pos as usize) < len {
+                    let i = self.direction.along(pos as usize, len);
+                    frame[i] = frame[i].saturating_add(color.scale(share * level));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rockets_burst_at_the_top() {
+        let mut params = Params::default();
+        params.set("launches", "0");
+        params.set("sparks", "10");
+        let mut fireworks = Fireworks::new(None, &params).unwrap();
+        fireworks.rng = Rng::new(4);
+        // Climbing to 30 pixels.
+        fireworks.rockets.push((0.0, (2.0f64 * 30.0 * 30.0).sqrt()));
+        let mut frame = vec![Color16::default(); 60];
+        let mut t = Duration::from_secs(0);
+        while !fireworks.rockets.is_empty() {
+            fireworks.render(&mut frame, t);
+            t += Duration::from_millis(20);
+        }
+        assert_eq!(fireworks.burning.len(), 10);
+        let at = fireworks.burning[0].at;
+        assert!((at - 30.0).abs() < 1.0, "{}", at);
+
+        // Then the sparks fall and fade out.
+        for _ in 0..300 {
+            fireworks.render(&mut frame, t);
+            t += Duration::from_millis(20);
+        }
+        assert!(fireworks.burning.is_empty());
+        assert!(frame.iter().all(|c| *c == Color16::default()));
+    }
+}
@@ -0,0 +1,114 @@
+//! Pride flags, their stripes stretched along the strip.
+
+use super::{Effect, Param, Params};
+use crate::color::{Color, Color16};
+
+use std::f64::consts::TAU;
+use std::time::Duration;
+
+pub const PARAMS: &[Param] = &[
+    Param {
+        name: "flag",
+        description: "rainbow, trans, bi, pan, lesbian, nonbinary, ace, aro or genderfluid [default: rainbow]",
+    },
+    Param {
+        name: "wave",
+        description: "ripples a second running along the flag as it waves, 0 holds it still [default: 0]",
+    },
+];
+
+/// Each flag's stripes, top first. Stripes twice as wide are given twice.
+const FLAGS: &[(&str, &[u32])] = &[
+    (
+        "rainbow",
+        &[0xe40303, 0xff8c00, 0xffed00, 0x008026, 0x004dff, 0x750787],
+    ),
+    ("trans", &[0x5bcefa, 0xf5a9b8, 0xffffff, 0xf5a9b8, 0x5bcefa]),
+    ("bi", &[0xd60270, 0xd60270, 0x9b4f96, 0x0038a8, 0x0038a8]),
+    ("pan", &[0xff218c, 0xffd800, 0x21b1ff]),
+    (
+        "lesbian",
+        &[
+            0xd52d00, 0xef7627, 0xff9a56, 0xffffff, 0xd162a4, 0xb55690, 0xa30262,
+        ],
+    ),
+    ("nonbinary", &[0xfcf434, 0xffffff, 0x9c59d1, 0x2c2c2c]),
+    ("ace", &[0x000000, 0xa3a3a3, 0xffffff, 0x800080]),
+    ("aro", &[0x3da542, 0xa7d379, 0xffffff, 0xa9a9a9, 0x000000]),
+    (
+        "genderfluid",
+        &[0xff76a4, 0xffffff, 0xc011d7, 0x000000, 0x2f3cbe],
+    ),
+];
+
+/// Pixels from one ripple to the next, and how far the shade dips.
+const RIPPLE_LENGTH: f64 = 16.0;
+const RIPPLE_DEPTH: f64 = 0.4;
+
+/// A flag's stripes in order along the strip.
+pub struct Flag {
+    stripes: Vec<Color16>,
+    wave: f64,
+}
+
+impl Flag {
+    pub fn new(params: &Params) -> std::result::Result<Self, String> {
+        let name: String = params.get("flag", "rainbow".to_string())?;
+        let stripes = FLAGS
+            .iter()
+            .find(|(flag, _)| *flag == name)
+            .map(|(_, stripes)| stripes)
+            .ok_or_else(|| {
+                let known: Vec<&str> = FLAGS.iter().map(|(flag, _)| *flag).collect();
+                format!("no flag {}, expected {}", name, known.join(", "))
+            })?;
+        Ok(Flag {
+            stripes: stripes.iter().map(|&c| Color::from_rgb(c).into()).collect(),
+            wave: params.number("wave", 0.0, 0.0, 100.0)?,
+        })
+    }
+}
+
+impl Effect for Flag {
+    fn render(&mut self, frame: &mut [Color16], t: Duration) {
+        let len = frame.len();
+        let t = t.as_secs_f64();
+        for (i, pixel) in frame.iter_mut().enumerate() {
+            let stripe = self.stripes[i * self.stripes.len() / len];
+            *pixel = if self.wave > 0.0 {
+                let ripple = (TAU * (i as f64 / RIPPLE_LENGTH - self.wave * t)).sin();
+                stripe.scale(1.0 - RIPPLE_DEPTH * (ripple + 1.0) / 2.0)
+            } else {
+                stripe
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stripes_fill_the_strip() {
+        let mut params = Params::default();
+        params.set("flag", "bi");
+        let mut flag = Flag::new(&params).unwrap();
+        let mut frame = vec![Color16::default(); 10];
+        flag.render(&mut frame, Duration::from_secs(0));
+        let pink: Color16 = Color::new(0xd6, 0x02, 0x70).into();
+        let purple: Color16 = Color::new(0x9b, 0x4f, 0x96).into();
+        assert_eq!(&frame[..5], &[pink, pink, pink, pink, purple]);
+        let still = frame.clone();
+        flag.render(&mut frame, Duration::from_secs(3));
+        assert_eq!(frame, still);
+
+        params.set("wave", "0.5");
+        let mut flag = Flag::new(&params).unwrap();
+        flag.render(&mut frame, Duration::from_secs(3));
+        assert_ne!(frame, still);
+
+        params.set("flag", "plaid");
+        assert!(Flag::new(&params).is_err());
+    }
+}
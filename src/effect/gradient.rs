@@ -0,0 +1,90 @@
+//! A gradient through any colors, stretched along the strip and held or
+//! turned slowly.
+
+use super::{colors, Effect, Param, Params};
+use crate::color::Color16;
+use crate::palette::Palette;
+
+use std::time::Duration;
+
+pub const PARAMS: &[Param] = &[
+    Param {
+        name: "colors",
+        description: "colors from the first pixel to the last, separated by commas [default: the palette or wheel]",
+    },
+    Param {
+        name: "rotate",
+        description: "turns a minute the gradient moves along the strip, out and back, 0 holds it [default: 0]",
+    },
+];
+
+/// Colors from the wheel when there is neither a palette nor colors.
+const STEPS: usize = 6;
+
+/// A gradient across the strip.
+pub struct Gradient {
+    palette: Palette,
+    rotate: f64,
+}
+
+impl Gradient {
+    pub fn new(palette: Option<Palette>, params: &Params) -> std::result::Result<Self, String> {
+        let palette = match (palette, params.optional::<String>("colors")?) {
+            (Some(palette), None) => palette,
+            (_, list) => {
+                let colors: Vec<_> = colors::list(params, "colors", None, STEPS)?
+                    .into_iter()
+                    .map(Color16::to_color)
+                    .collect();
+                if list.is_some() && colors.len() < 2 {
+                    return Err("colors needs two or more colors".to_string());
+                }
+                Palette::even(&colors)
+            }
+        };
+        Ok(Gradient {
+            palette,
+            rotate: params.number("rotate", 0.0, 0.0, 600.0)?,
+        })
+    }
+}
+
+impl Effect for Gradient {
+    fn render(&mut self, frame: &mut [Color16], t: Duration) {
+        let last = frame.len().saturating_sub(1).max(1) as f64;
+        let turned = t.as_secs_f64() / 60.0 * self.rotate;
+        for (i, pixel) in frame.iter_mut().enumerate() {
+            // The strip covers the way out; turning brings in the way back,
+            // so the gradient moves without a seam.
+            let at = i as f64 / last / 2.0 + turned;
+            *pixel = self.palette.sample_cyclic(at).into();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+
+    #[test]
+    fn spans_the_strip_through_the_stops() {
+        let mut params = Params::default();
+        params.set("colors", "#ff0000, #0000ff");
+        let mut gradient = Gradient::new(None, &params).unwrap();
+        let mut frame = vec![Color16::default(); 3];
+        gradient.render(&mut frame, Duration::from_secs(60));
+        assert_eq!(frame[0], Color::new(0xff, 0, 0).into());
+        assert_eq!(frame[2], Color::new(0, 0, 0xff).into());
+        assert_eq!(frame[1], Color::new(0x80, 0, 0x80).into());
+
+        // Half a turn reverses it.
+        params.set("rotate", "0.5");
+        let mut gradient = Gradient::new(None, &params).unwrap();
+        gradient.render(&mut frame, Duration::from_secs(60));
+        assert_eq!(frame[0], Color::new(0, 0, 0xff).into());
+
+        params.set("colors", "red");
+        assert!(Gradient::new(None, &params).is_err());
+    }
+}
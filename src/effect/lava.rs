@@ -0,0 +1,146 @@
+//! A lava lamp: warm blobs drifting along the strip, run together where
+//! their fields overlap, like metaballs.
+
+use super::{elapsed, Effect, Param, Params, MAX_GAP};
+use crate::color::Color16;
+use crate::palette::Palette;
+use crate::random::Rng;
+
+use std::f64::consts::TAU;
+use std::time::Duration;
+
+pub const PARAMS: &[Param] = &[
+    Param {
+        name: "count",
+        description: "blobs drifting [default: 4]",
+    },
+    Param {
+        name: "size",
+        description: "pixels across a blob's core [default: 4]",
+    },
+    Param {
+        name: "rate",
+        description: "most pixels a second a blob drifts [default: 1]",
+    },
+];
+
+/// Seconds a blob takes to swell and shrink back, which pulls blobs apart
+/// again after they run together.
+const SWELL_PERIOD: f64 = 23.0;
+
+struct Blob {
+    at: f64,
+    speed: f64,
+    /// Where the blob is in its swelling, in turns.
+    phase: f64,
+}
+
+/// Blobs of the lava palette, or any other, drifting and merging.
+pub struct Lava {
+    palette: Palette,
+    count: usize,
+    size: f64,
+    rate: f64,
+    blobs: Vec<Blob>,
+    rng: Rng,
+    last: Option<Duration>,
+}
+
+impl Lava {
+    pub fn new(palette: Option<Palette>, params: &Params) -> std::result::Result<Self, String> {
+        Ok(Lava {
+            palette: palette
+                .or_else(|| Palette::builtin("lava"))
+                .ok_or("no lava palette")?,
+            count: params.get("count", 4)?,
+            size: params.number("size", 4.0, 0.5, 10000.0)?,
+            rate: params.number("rate", 1.0, 0.0, 1000.0)?,
+            blobs: Vec::new(),
+            rng: Rng::from_time(),
+            last: None,
+        })
+    }
+
+    fn update(&mut self, step: f64, len: f64) {
+        for blob in self.blobs.iter_mut() {
+            // Wander: nudge the speed at random, within the rate.
+            blob.speed += (self.rng.unit() - 0.5) * self.rate * step;
+            blob.speed = blob.speed.clamp(-self.rate, self.rate);
+            blob.at += blob.speed * step;
+            if blob.at < 0.0 || blob.at > len {
+                blob.at = blob.at.clamp(0.0, len);
+                blob.speed = -blob.speed;
+            }
+            blob.phase = (blob.phase + step / SWELL_PERIOD).fract();
+        }
+    }
+
+    /// The blobs' summed field at `x`: 2 at the middle of a lone blob, 1 at
+    /// the edge of its core, more where blobs overlap.
+    fn field(&self, x: f64) -> f64 {
+        self.blobs
+            .iter()
+            .map(|blob| {
+                let radius = self.size / 2.0 * (1.0 + 0.3 * (TAU * blob.phase).sin());
+                let r2 = radius * radius;
+                let d = x - blob.at;
+                2.0 * r2 / (d * d + r2)
+            })
+            .sum()
+    }
+}
+
+impl Effect for Lava {
+    fn render(&mut self, frame: &mut [Color16], t: Duration) {
+        let len = frame.len().saturating_sub(1) as f64;
+        if self.blobs.len() != self.count {
+            let rng = &mut self.rng;
+            self.blobs = (0..self.count)
+                .map(|_| Blob {
+                    at: rng.unit() * len,
+                    speed: 0.0,
+                    phase: rng.unit(),
+                })
+                .collect();
+        }
+        let step = elapsed(&mut self.last, t, MAX_GAP);
+        self.update(step, len);
+
+        for (i, pixel) in frame.iter_mut().enumerate() {
+            let field = self.field(i as f64);
+            // From the dark end of the palette towards the bright as the
+            // field grows without bound.
+            *pixel = self.palette.sample(field / (1.0 + field)).into();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blobs_glow_brightest_together() {
+        let mut params = Params::default();
+        params.set("count", "2");
+        params.set("size", "4");
+        let mut lava = Lava::new(None, &params).unwrap();
+        let mut frame = vec![Color16::default(); 60];
+        lava.render(&mut frame, Duration::from_secs(0));
+        for (blob, at) in lava.blobs.iter_mut().zip(vec![10.0, 40.0]) {
+            blob.at = at;
+            blob.phase = 0.0;
+        }
+        // Apart, each core is brighter than the dark between them.
+        let apart = lava.field(10.0);
+        assert!(apart > 1.0 && lava.field(25.0) < 0.2);
+        lava.blobs[1].at = 12.0;
+        assert!(lava.field(11.0) > apart);
+
+        // The blobs wander but stay on the strip.
+        for s in 1..600 {
+            lava.render(&mut frame, Duration::from_secs(s));
+        }
+        assert!(lava.blobs.iter().all(|b| (0.0..=59.0).contains(&b.at)));
+    }
+}
@@ -0,0 +1,261 @@
+//! Effects stacked as layers and blended together, bottom first.
+//!
+//! A stack is written as effect names joined by "+", each optionally
+//! followed by how it blends onto the layers below, its opacity and how
+//! fast it runs: "rainbow 0.5x + twinkle add 50%". Layers may also set
+//! their effect's parameters, over those given to the whole stack:
+//! "twinkle density=0.3 add".
+
+use super::{find, parse_param, Effect, Params, Registration, MAX_SPEED};
+use crate::color::Color16;
+use crate::palette::Palette;
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Names of the blend modes, for help and error messages.
+pub const BLEND_NAMES: &[&str] = &["alpha", "add", "multiply", "overlay"];
+
+/// How a layer combines with the layers below it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BlendMode {
+    /// Covers what is below, through its opacity.
+    #[default]
+    Alpha,
+    /// Brightens what is below; black leaves it alone.
+    Add,
+    /// Darkens what is below; white leaves it alone.
+    Multiply,
+    /// Multiplies dark colors below and screens light ones, for contrast.
+    Overlay,
+}
+
+impl BlendMode {
+    /// Blend channel `top` onto `base`, both from 0 to 1, at `opacity`.
+    pub fn blend(self, base: f64, top: f64, opacity: f64) -> f64 {
+        let blended = match self {
+            BlendMode::Alpha => top,
+            BlendMode::Add => (base + top).min(1.0),
+            BlendMode::Multiply => base * top,
+            BlendMode::Overlay => {
+                if base < 0.5 {
+                    2.0 * base * top
+                } else {
+                    1.0 - 2.0 * (1.0 - base) * (1.0 - top)
+                }
+            }
+        };
+        base + (blended - base) * opacity
+    }
+}
+
+impl FromStr for BlendMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "alpha" => Ok(BlendMode::Alpha),
+            "add" => Ok(BlendMode::Add),
+            "multiply" => Ok(BlendMode::Multiply),
+            "overlay" => Ok(BlendMode::Overlay),
+            _ => Err(format!(
+                "unknown blend mode {}, expected one of {}",
+                s,
+                BLEND_NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+impl fmt::Display for BlendMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            BlendMode::Alpha => "alpha",
+            BlendMode::Add => "add",
+            BlendMode::Multiply => "multiply",
+            BlendMode::Overlay => "overlay",
+        })
+    }
+}
+
+/// One layer of a stack: "twinkle", "twinkle add" or "twinkle add 50% 2x".
+#[derive(Clone)]
+pub struct Layer {
+    pub effect: &'static Registration,
+    pub blend: BlendMode,
+    /// From 0 to 1.
+    pub opacity: f64,
+    /// Multiplies the time the effect is drawn at.
+    pub speed: f64,
+    /// Set for this layer only.
+    pub params: Params,
+}
+
+impl Layer {
+    /// The layer's effect, set up by its own parameters over `params`.
+    pub fn create(
+        &self,
+        palette: Option<Palette>,
+        params: &Params,
+    ) -> std::result::Result<Box<dyn Effect>, String> {
+        (self.effect.create)(palette, &self.params.or(params))
+            .map_err(|e| format!("{}: {}", self.effect.name, e))
+    }
+}
+
+impl FromStr for Layer {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let mut words = s.split_whitespace();
+        let name = words.next().ok_or("empty layer")?;
+        let effect = find(name).ok_or_else(|| {
+            format!(
+                "unknown effect {}, expected one of {}",
+                name,
+                super::names().join(", ")
+            )
+        })?;
+        let mut layer = Layer {
+            effect,
+            blend: BlendMode::default(),
+            opacity: 1.0,
+            speed: 1.0,
+            params: Params::default(),
+        };
+        for word in words {
+            if word.contains('=') {
+                let (key, value) = parse_param(word)?;
+                if !effect.params.iter().any(|p| p.name == key) {
+                    return Err(format!("{} has no parameter {}", effect.name, key));
+                }
+                layer.params.set(&key, &value);
+                continue;
+            }
+            if let Ok(blend) = word.parse() {
+                layer.blend = blend;
+                continue;
+            }
+            if let Some(speed) = word.strip_suffix('x') {
+                layer.speed = match speed.parse::<f64>() {
+                    Ok(speed) if (0.0..=MAX_SPEED).contains(&speed) => speed,
+                    _ => {
+                        return Err(format!(
+                            "bad speed {} in {}, expected 0x to {}x",
+                            word,
+                            s.trim(),
+                            MAX_SPEED
+                        ))
+                    }
+                };
+                continue;
+            }
+            let opacity = match word.strip_suffix('%') {
+                Some(percent) => percent.parse::<f64>().map(|p| p / 100.0),
+                None => word.parse::<f64>(),
+            };
+            layer.opacity = match opacity {
+                Ok(opacity) if (0.0..=1.0).contains(&opacity) => opacity,
+                _ => {
+                    return Err(format!(
+                        "bad layer option {} in {}, expected a blend mode ({}), an \
+                         opacity from 0 to 1, a speed such as 2x or key=value",
+                        word,
+                        s.trim(),
+                        BLEND_NAMES.join(", ")
+                    ))
+                }
+            };
+        }
+        Ok(layer)
+    }
+}
+
+/// The layers in `name`, bottom first.
+pub fn parse_stack(name: &str) -> std::result::Result<Vec<Layer>, String> {
+    name.split('+').map(str::parse).collect()
+}
+
+/// Effects drawn into frames of their own and blended together.
+pub struct Layers {
+    layers: Vec<(Box<dyn Effect>, Layer, Vec<Color16>)>,
+}
+
+impl Layers {
+    /// Create each of `layers`, drawing from `palette` and set up by
+    /// `params`.
+    pub fn new(
+        layers: &[Layer],
+        palette: Option<Palette>,
+        params: &Params,
+    ) -> std::result::Result<Self, String> {
+        let layers = layers
+            .iter()
+            .map(|layer| {
+                Ok((
+                    layer.create(palette.clone(), params)?,
+                    layer.clone(),
+                    Vec::new(),
+                ))
+            })
+            .collect::<std::result::Result<_, String>>()?;
+        Ok(Layers { layers })
+    }
+}
+
+impl Effect for Layers {
+    fn render(&mut self, frame: &mut [Color16], t: Duration) {
+        let mut levels = vec![[0.0; 3]; frame.len()];
+        for (effect, layer, own) in self.layers.iter_mut() {
+            own.resize(frame.len(), Color16::default());
+            effect.render(own, t.mul_f64(layer.speed));
+            for (level, color) in levels.iter_mut().zip(own.iter()) {
+                let top = [color.red, color.green, color.blue];
+                for c in 0..3 {
+                    let top = f64::from(top[c]) / 65535.0;
+                    level[c] = layer.blend.blend(level[c], top, layer.opacity);
+                }
+            }
+        }
+        for (pixel, level) in frame.iter_mut().zip(&levels) {
+            let widen = |v: f64| (v.clamp(0.0, 1.0) * 65535.0).round() as u16;
+            *pixel = Color16::new(widen(level[0]), widen(level[1]), widen(level[2]));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_blends_layers() {
+        let layers = parse_stack("rainbow + rainbow multiply 50% 2x").unwrap();
+        assert_eq!(layers.len(), 2);
+        assert_eq!(layers[0].blend, BlendMode::Alpha);
+        assert_eq!(
+            (layers[1].blend, layers[1].opacity),
+            (BlendMode::Multiply, 0.5)
+        );
+        assert_eq!((layers[0].speed, layers[1].speed), (1.0, 2.0));
+        assert!(parse_stack("rainbow -1x").is_err());
+        assert!(parse_stack("rainbow density=0.3").is_err());
+        assert!(parse_stack("rainbow + hyperspace").is_err());
+        assert!(parse_stack("rainbow add 2").is_err());
+        assert!(parse_stack("rainbow +").is_err());
+
+        assert_eq!(BlendMode::Add.blend(0.5, 0.75, 1.0), 1.0);
+        assert_eq!(BlendMode::Multiply.blend(0.5, 0.5, 1.0), 0.25);
+        assert_eq!(BlendMode::Overlay.blend(0.25, 0.5, 1.0), 0.25);
+        assert_eq!(BlendMode::Overlay.blend(0.75, 0.5, 1.0), 0.75);
+        assert_eq!(BlendMode::Alpha.blend(0.25, 1.0, 0.5), 0.625);
+
+        // A rainbow multiplied by itself keeps its pure colors.
+        let mut frame = vec![Color16::default(); 3];
+        Layers::new(&layers, None, &Params::default())
+            .unwrap()
+            .render(&mut frame, Duration::from_secs(0));
+        assert_eq!(frame[0], Color16::new(65535, 0, 0));
+    }
+}
@@ -0,0 +1,147 @@
+//! An elementary cellular automaton, Wolfram's rules 0 to 255, one
+//! generation after another along the strip, cells colored by age.
+
+use super::{Colors, Effect, Param, Params};
+use crate::color::Color16;
+use crate::palette::Palette;
+use crate::random::Rng;
+
+use std::time::Duration;
+
+pub const PARAMS: &[Param] = &[
+    Param {
+        name: "rule",
+        description:
+            "the automaton's rule from 0 to 255; 30, 90, 110 and 150 look lively [default: 30]",
+    },
+    Param {
+        name: "rate",
+        description: "generations a second [default: 8]",
+    },
+    Param {
+        name: "density",
+        description: "share of cells alive when seeded, 0 to 1 [default: 0.5]",
+    },
+];
+
+/// Generations a cell takes to age once along the palette or around the
+/// wheel.
+const AGE_TURN: f64 = 32.0;
+
+/// Most generations caught up on in one frame, after a pause.
+const MAX_STEPS: u64 = 10;
+
+/// Cells living and dying by their neighbours, the ends wrapping round.
+pub struct Life {
+    colors: Colors,
+    rule: u8,
+    rate: f64,
+    density: f64,
+    rng: Rng,
+    /// Generations each cell has been alive, 0 for dead.
+    ages: Vec<u32>,
+    /// The generation before this one, to tell when it has settled.
+    previous: Vec<bool>,
+    steps: u64,
+}
+
+impl Life {
+    pub fn new(palette: Option<Palette>, params: &Params) -> std::result::Result<Self, String> {
+        Ok(Life {
+            colors: Colors::from_palette(palette),
+            rule: params.get("rule", 30)?,
+            rate: params.number("rate", 8.0, 0.0, 1000.0)?,
+            density: params.number("density", 0.5, 0.0, 1.0)?,
+            rng: Rng::from_time(),
+            ages: Vec::new(),
+            previous: Vec::new(),
+            steps: 0,
+        })
+    }
+
+    fn alive(&self) -> Vec<bool> {
+        self.ages.iter().map(|&age| age > 0).collect()
+    }
+
+    /// Cells alive at random; those alive already keep their age.
+    fn seed(&mut self) {
+        for age in self.ages.iter_mut() {
+            *age = if self.rng.unit() < self.density {
+                *age + 1
+            } else {
+                0
+            };
+        }
+    }
+
+    fn step(&mut self) {
+        let cells = self.alive();
+        let len = cells.len();
+        for (i, age) in self.ages.iter_mut().enumerate() {
+            let bit = |j: usize| u8::from(cells[j % len]);
+            let pattern = bit(i + len - 1) << 2 | bit(i) << 1 | bit(i + 1);
+            *age = if self.rule >> pattern & 1 == 1 {
+                *age + 1
+            } else {
+                0
+            };
+        }
+        // Dead, still or flipping between two states: start again, as
+        // there is nothing more to watch.
+        let next = self.alive();
+        if next.iter().all(|&cell| !cell) || next == cells || next == self.previous {
+            self.seed();
+        }
+        self.previous = cells;
+    }
+}
+
+impl Effect for Life {
+    fn render(&mut self, frame: &mut [Color16], t: Duration) {
+        if self.ages.len() != frame.len() {
+            self.ages = vec![0; frame.len()];
+            self.seed();
+        }
+        if frame.is_empty() {
+            return;
+        }
+        let due = (t.as_secs_f64() * self.rate) as u64;
+        for _ in 0..due.saturating_sub(self.steps).min(MAX_STEPS) {
+            self.step();
+        }
+        self.steps = due;
+        for (pixel, &age) in frame.iter_mut().zip(&self.ages) {
+            *pixel = if age > 0 {
+                self.colors.at(f64::from(age - 1) / AGE_TURN)
+            } else {
+                Color16::default()
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn follows_the_rule() {
+        let mut params = Params::default();
+        params.set("rule", "90");
+        let mut life = Life::new(None, &params).unwrap();
+        // Rule 90: alive when exactly one neighbour is.
+        life.ages = vec![0, 0, 3, 0, 0, 0, 1];
+        life.step();
+        assert_eq!(life.ages, [1, 1, 0, 1, 0, 1, 0]);
+        life.step();
+        assert_eq!(life.ages, [2, 2, 0, 0, 0, 0, 0]);
+
+        // Rule 204 copies each generation, so it is seeded again.
+        params.set("rule", "204");
+        params.set("density", "0");
+        let mut life = Life::new(None, &params).unwrap();
+        life.ages = vec![2, 0, 1];
+        life.step();
+        assert_eq!(life.ages, [0, 0, 0]);
+    }
+}
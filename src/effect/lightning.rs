@@ -0,0 +1,173 @@
+//! A lightning storm: strikes of a few quick flashes, each lighting a
+//! stretch of the strip, with quiet dark blue between them.
+
+use super::{Effect, Param, Params};
+use crate::color::{Color, Color16};
+use crate::random::Rng;
+
+use std::time::Duration;
+
+pub const PARAMS: &[Param] = &[
+    Param {
+        name: "intensity",
+        description: "strikes a minute, on average [default: 6]",
+    },
+    Param {
+        name: "color",
+        description: "color of the flashes [default: white]",
+    },
+    Param {
+        name: "background",
+        description: "color of the sky between flashes [default: #000410]",
+    },
+];
+
+/// Flashes in a strike, the leader and its return strokes.
+const FLASHES: (usize, usize) = (2, 6);
+
+/// Seconds each flash lasts, and the darkness between flashes of a strike.
+const FLASH: (f64, f64) = (0.02, 0.08);
+const GAP: (f64, f64) = (0.05, 0.25);
+
+/// Seconds behind the storm can fall, after a pause, before it starts
+/// over from now.
+const MAX_GAP: f64 = 5.0;
+
+/// A flash: when it starts and ends, how bright it is and the pixels it
+/// lights, as shares of the strip.
+#[derive(Debug, Clone, Copy, Default)]
+struct Flash {
+    start: f64,
+    end: f64,
+    level: f64,
+    from: f64,
+    to: f64,
+}
+
+/// Lightning striking at random over a dark sky.
+pub struct Lightning {
+    color: Color16,
+    background: Color16,
+    intensity: f64,
+    rng: Rng,
+    flash: Flash,
+    /// When the next flash starts, and the flashes left in this strike.
+    next: Option<f64>,
+    left: usize,
+}
+
+impl Lightning {
+    pub fn new(params: &Params) -> std::result::Result<Self, String> {
+        Ok(Lightning {
+            color: params.get("color", Color::WHITE)?.into(),
+            background: params.get("background", Color::new(0, 4, 16))?.into(),
+            intensity: params.number("intensity", 6.0, 0.01, 600.0)?,
+            rng: Rng::from_time(),
+            flash: Flash::default(),
+            next: None,
+            left: 0,
+        })
+    }
+
+    /// A random number from `low` to `high`.
+    fn between(&mut self, (low, high): (f64, f64)) -> f64 {
+        low + self.rng.unit() * (high - low)
+    }
+
+    /// Seconds from one strike to the next: random like rain, so a storm
+    /// has lulls and bursts.
+    fn lull(&mut self) -> f64 {
+        -(1.0 - self.rng.unit()).ln() * 60.0 / self.intensity
+    }
+
+    /// Start the flash due at `start`, and schedule the next.
+    fn strike(&mut self, start: f64) {
+        if self.left == 0 {
+            // A new strike, lighting its own stretch of the strip.
+            self.left = FLASHES.0 + self.rng.below(FLASHES.1 - FLASHES.0 + 1);
+            let middle = self.rng.unit();
+            let width = self.between((0.1, 0.5));
+            self.flash.from = middle - width / 2.0;
+            self.flash.to = middle + width / 2.0;
+            // The leader is fainter than the strokes that follow.
+            self.flash.level = self.between((0.2, 0.5));
+        } else {
+            self.flash.level = self.between((0.6, 1.0));
+        }
+        self.left -= 1;
+        self.flash.start = start;
+        self.flash.end = start + self.between(FLASH);
+        let wait = if self.left > 0 {
+            self.between(GAP)
+        } else {
+            self.lull()
+        };
+        self.next = Some(self.flash.end + wait);
+    }
+}
+
+impl Effect for Lightning {
+    fn render(&mut self, frame: &mut [Color16], t: Duration) {
+        let t = t.as_secs_f64();
+        let next = match self.next {
+            Some(next) if t >= self.flash.start && t - next < MAX_GAP => next,
+            // The first frame, or time has jumped back or far ahead: wait
+            // for a strike from now.
+            _ => {
+                let next = t + self.lull();
+                self.left = 0;
+                self.flash = Flash::default();
+                next
+            }
+        };
+        self.next = Some(next);
+        while let Some(next) = self.next.filter(|&next| next <= t) {
+            self.strike(next);
+        }
+
+        let flash = self.flash;
+        let lit = t >= flash.start && t < flash.end;
+        let len = frame.len() as f64;
+        for (i, pixel) in frame.iter_mut().enumerate() {
+            let at = (i as f64 + 0.5) / len;
+            *pixel = if lit && at >= flash.from && at <= flash.to {
+                self.background
+                    .saturating_add(self.color.scale(flash.level))
+            } else {
+                self.background
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strikes_flash_in_bursts() {
+        let mut params = Params::default();
+        params.set("intensity", "30");
+        let mut lightning = Lightning::new(&params).unwrap();
+        lightning.rng = Rng::new(7);
+        let mut frame = vec![Color16::default(); 50];
+        let dark = Color16::from(Color::new(0, 4, 16));
+        // Flash starts, by the frame, over two minutes.
+        let mut starts = Vec::new();
+        let mut was_lit = false;
+        for ms in 0..120_000 {
+            lightning.render(&mut frame, Duration::from_millis(ms));
+            let lit = frame.iter().any(|c| *c != dark);
+            if lit && !was_lit {
+                starts.push(ms);
+            }
+            was_lit = lit;
+        }
+        let gaps: Vec<u64> = starts.windows(2).map(|w| w[1] - w[0]).collect();
+        // Flashes of a strike close together, strikes well apart.
+        assert!(gaps.iter().all(|&gap| gap >= 70), "{:?}", gaps);
+        assert!(gaps.iter().filter(|&&gap| gap < 400).count() > 30);
+        let strikes = gaps.iter().filter(|&&gap| gap >= 400).count() + 1;
+        assert!((30..=100).contains(&strikes), "{} strikes", strikes);
+    }
+}
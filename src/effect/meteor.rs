@@ -0,0 +1,133 @@
+//! Meteors falling along the strip, their trails breaking up behind them.
+
+use super::{elapsed, Colors, Direction, Effect, Param, Params, MAX_GAP};
+use crate::color::Color16;
+use crate::palette::Palette;
+use crate::random::Rng;
+
+use std::time::Duration;
+
+pub const PARAMS: &[Param] = &[
+    Param {
+        name: "size",
+        description: "pixels in each meteor's head [default: 3]",
+    },
+    Param {
+        name: "rate",
+        description: "pixels travelled each second [default: 40]",
+    },
+    Param {
+        name: "decay",
+        description:
+            "how fast the trail fades, the fraction lost each second, on average [default: 0.9]",
+    },
+    Param {
+        name: "count",
+        description: "meteors on the strip at once, evenly spaced [default: 1]",
+    },
+    Param {
+        name: "color",
+        description: "color of the meteors [default: one each along the palette, or white]",
+    },
+    Param {
+        name: "direction",
+        description: "forward or reverse [default: forward]",
+    },
+];
+
+/// Chance each pixel of the trail fades on a frame, so the trail breaks
+/// up instead of fading evenly.
+const FADE_CHANCE: f64 = 0.5;
+
+/// Bright heads travelling along the strip, each leaving a trail that
+/// fades at random.
+pub struct Meteor {
+    colors: Colors,
+    size: usize,
+    rate: f64,
+    /// Natural log of the fraction of trail brightness left each second.
+    fade: f64,
+    count: usize,
+    direction: Direction,
+    rng: Rng,
+    last: Option<Duration>,
+}
+
+impl Meteor {
+    pub fn new(palette: Option<Palette>, params: &Params) -> std::result::Result<Self, String> {
+        let colors = match Colors::from_params(params, "color", palette)? {
+            Colors::Wheel => Colors::One(Color16::new(65535, 65535, 65535)),
+            colors => colors,
+        };
+        let size: usize = params.get("size", 3)?;
+        let count: usize = params.get("count", 1)?;
+        if size == 0 || count == 0 {
+            return Err("size and count must be 1 or more".to_string());
+        }
+        let decay = params.number("decay", 0.9, 0.0, 0.999)?;
+        Ok(Meteor {
+            colors,
+            size,
+            rate: params.number("rate", 40.0, 0.0, 10000.0)?,
+            fade: (1.0 - decay).ln(),
+            count,
+            direction: params.get("direction", Direction::default())?,
+            rng: Rng::from_time(),
+            last: None,
+        })
+    }
+}
+
+impl Effect for Meteor {
+    fn render(&mut self, frame: &mut [Color16], t: Duration) {
+        let step = elapsed(&mut self.last, t, MAX_GAP);
+
+        // Fading only some pixels each frame, by more, keeps the average.
+        let factor = (self.fade * step / FADE_CHANCE).exp();
+        for pixel in frame.iter_mut() {
+            if self.rng.unit() < FADE_CHANCE {
+                *pixel = pixel.scale(factor);
+            }
+        }
+
+        // Each meteor runs past the end by the strip's length again, so
+        // its trail fades before it comes round.
+        let len = frame.len();
+        let lap = (2 * len).max(1) as f64;
+        let travelled = t.as_secs_f64() * self.rate;
+        for m in 0..self.count {
+            let head = (travelled + lap * m as f64 / self.count as f64) % lap;
+            let color = self.colors.at(m as f64 / self.count as f64);
+            for (i, pixel) in frame.iter_mut().enumerate() {
+                let at = self.direction.along(i, len) as f64;
+                if at <= head && at > head - self.size as f64 {
+                    *pixel = color;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn head_leaves_a_fading_trail() {
+        let mut params = Params::default();
+        params.set("rate", "10");
+        params.set("size", "2");
+        let mut meteor = Meteor::new(None, &params).unwrap();
+        meteor.rng = Rng::new(3);
+        let white = Color16::new(65535, 65535, 65535);
+        let mut frame = vec![Color16::default(); 20];
+        for i in 0..=10 {
+            meteor.render(&mut frame, Duration::from_millis(i * 100));
+        }
+        // The head is on 9 and 10, with the trail fading behind it.
+        assert_eq!(frame[9..11], [white, white]);
+        assert_eq!(frame[11], Color16::default());
+        assert!(frame[8].red > 0 && frame[8].red < 65535);
+        assert!(frame[0].red < frame[8].red);
+    }
+}
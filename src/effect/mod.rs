@@ -0,0 +1,525 @@
+//! Animations and the engine that runs them.
+//!
+//! An effect draws frames at full brightness from the time since it
+//! started; the engine keeps a frame buffer for each, renders them on every
+//! tick and works out the brightness they're shown at. The engine's clock
+//! runs at an adjustable speed, so every effect can be slowed down or sped
+//! up without jumping.
+
+use crate::color::Color16;
+use crate::palette::Palette;
+use crate::schedule::Smoothed;
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+mod aurora;
+mod balls;
+mod breathe;
+mod candle;
+mod chase;
+mod colors;
+mod confetti;
+mod emergency;
+mod fire;
+mod fireworks;
+mod flag;
+mod gradient;
+mod lava;
+mod layers;
+mod life;
+mod lightning;
+mod meteor;
+mod pacifica;
+mod params;
+mod plasma;
+mod rain;
+mod rainbow;
+mod running;
+mod scanner;
+mod sinelon;
+mod snow;
+mod solid;
+mod strobe;
+mod twinkle;
+mod wipe;
+mod zones;
+
+pub use aurora::Aurora;
+pub use balls::Balls;
+pub use breathe::{Breathe, Envelope};
+pub use candle::Candle;
+pub use chase::Chase;
+pub use colors::Colors;
+pub use confetti::Confetti;
+pub use emergency::Emergency;
+pub use fire::Fire;
+pub use fireworks::Fireworks;
+pub use flag::Flag;
+pub use gradient::Gradient;
+pub use lava::Lava;
+pub use layers::{parse_stack, BlendMode, Layer, Layers, BLEND_NAMES};
+pub use life::Life;
+pub use lightning::Lightning;
+pub use meteor::Meteor;
+pub use pacifica::Pacifica;
+pub use params::{parse_param, Param, Params};
+pub use plasma::Plasma;
+pub use rain::Rain;
+pub use rainbow::Rainbow;
+pub use running::Running;
+pub use scanner::Scanner;
+pub use sinelon::Sinelon;
+pub use snow::Snow;
+pub use solid::Solid;
+pub use strobe::Strobe;
+pub use twinkle::Twinkle;
+pub use wipe::Wipe;
+pub use zones::{Zone, Zones};
+
+/// Fastest an effect may be run, as a multiple of its normal speed.
+pub const MAX_SPEED: f64 = 100.0;
+
+/// An animation drawn one frame at a time.
+pub trait Effect: Send {
+    /// Draw the frame `t` after the effect started. `frame` still holds the
+    /// last frame drawn, for effects that build on it.
+    fn render(&mut self, frame: &mut [Color16], t: Duration);
+}
+
+/// Longest gap between frames that effects moving on from their last frame
+/// catch up on; after a longer one they carry on as if only this much time
+/// had passed.
+const MAX_GAP: f64 = 1.0;
+
+/// Seconds from the `last` frame to `t`, at most `max`, noting `t` as the
+/// last. The first frame, and one from before the last, are 0 seconds on.
+fn elapsed(last: &mut Option<Duration>, t: Duration, max: f64) -> f64 {
+    let step = match *last {
+        Some(last) if t >= last => (t - last).as_secs_f64().min(max),
+        _ => 0.0,
+    };
+    *last = Some(t);
+    step
+}
+
+/// Which way an effect moves along the strip.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Direction {
+    /// Away from the first pixel.
+    #[default]
+    Forward,
+    /// Towards the first pixel.
+    Reverse,
+}
+
+impl Direction {
+    /// How far pixel `i` of `len` is along the way the effect moves,
+    /// counting down from the far end in reverse.
+    pub fn along(self, i: usize, len: usize) -> usize {
+        match self {
+            Direction::Forward => i,
+            Direction::Reverse => len.saturating_sub(i + 1),
+        }
+    }
+}
+
+impl FromStr for Direction {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "forward" => Ok(Direction::Forward),
+            "reverse" => Ok(Direction::Reverse),
+            _ => Err(format!(
+                "unknown direction {}, expected forward or reverse",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Direction::Forward => "forward",
+            Direction::Reverse => "reverse",
+        })
+    }
+}
+
+/// Makes an effect drawing from a palette, for those that take one, set up
+/// by its parameters; bad values are described for the user.
+pub type Constructor = fn(Option<Palette>, &Params) -> std::result::Result<Box<dyn Effect>, String>;
+
+/// An effect that can be chosen by name.
+pub struct Registration {
+    pub name: &'static str,
+    /// One line for the effects listing.
+    pub description: &'static str,
+    /// Parameters the effect reads; others given to it are ignored.
+    pub params: &'static [Param],
+    pub create: Constructor,
+}
+
+/// Every effect, by name.
+pub const REGISTRY: &[Registration] = &[
+    Registration {
+        name: "rainbow",
+        description: "the color wheel, or the palette, turning along the strip",
+        params: rainbow::PARAMS,
+        create: |palette, params| Ok(Box::new(Rainbow::with_params(palette, params)?)),
+    },
+    Registration {
+        name: "breathe",
+        description: "the whole strip pulsing one color, slowly",
+        params: breathe::PARAMS,
+        create: |palette, params| Ok(Box::new(Breathe::new(palette, params)?)),
+    },
+    Registration {
+        name: "chase",
+        description: "theater marquee lights stepping along the strip",
+        params: chase::PARAMS,
+        create: |palette, params| Ok(Box::new(Chase::new(palette, params)?)),
+    },
+    Registration {
+        name: "scanner",
+        description: "an eye sweeping back and forth with a fading tail",
+        params: scanner::PARAMS,
+        create: |palette, params| Ok(Box::new(Scanner::new(palette, params)?)),
+    },
+    Registration {
+        name: "twinkle",
+        description: "pixels lighting up at random and fading, like stars",
+        params: twinkle::PARAMS,
+        create: |palette, params| Ok(Box::new(Twinkle::new(palette, params)?)),
+    },
+    Registration {
+        name: "meteor",
+        description: "meteors falling along the strip, trails breaking up",
+        params: meteor::PARAMS,
+        create: |palette, params| Ok(Box::new(Meteor::new(palette, params)?)),
+    },
+    Registration {
+        name: "fire",
+        description: "flames rising from the base, through the heat palette",
+        params: fire::PARAMS,
+        create: |palette, params| Ok(Box::new(Fire::new(palette, params)?)),
+    },
+    Registration {
+        name: "candle",
+        description: "warm candlelight, each pixel wavering on its own",
+        params: candle::PARAMS,
+        create: |palette, params| Ok(Box::new(Candle::new(palette, params)?)),
+    },
+    Registration {
+        name: "wipe",
+        description: "colors filling the strip one pixel at a time, in turn",
+        params: wipe::PARAMS,
+        create: |palette, params| Ok(Box::new(Wipe::new(palette, params)?)),
+    },
+    Registration {
+        name: "plasma",
+        description: "sine waves flowing through each other, through the palette",
+        params: plasma::PARAMS,
+        create: |palette, params| Ok(Box::new(Plasma::new(palette, params)?)),
+    },
+    Registration {
+        name: "aurora",
+        description: "slow curtains of northern lights, for dim evenings",
+        params: aurora::PARAMS,
+        create: |palette, params| Ok(Box::new(Aurora::new(palette, params)?)),
+    },
+    Registration {
+        name: "rain",
+        description: "raindrops running down the strip, from drizzle to downpour",
+        params: rain::PARAMS,
+        create: |palette, params| Ok(Box::new(Rain::new(palette, params)?)),
+    },
+    Registration {
+        name: "snow",
+        description: "snow drifting down and piling up at the end",
+        params: snow::PARAMS,
+        create: |palette, params| Ok(Box::new(Snow::new(palette, params)?)),
+    },
+    Registration {
+        name: "fireworks",
+        description: "rockets bursting into sparks of the palette's colors",
+        params: fireworks::PARAMS,
+        create: |palette, params| Ok(Box::new(Fireworks::new(palette, params)?)),
+    },
+    Registration {
+        name: "balls",
+        description: "balls bouncing under gravity, each its own color",
+        params: balls::PARAMS,
+        create: |palette, params| Ok(Box::new(Balls::new(palette, params)?)),
+    },
+    Registration {
+        name: "lava",
+        description: "warm blobs drifting, merging and splitting like a lava lamp",
+        params: lava::PARAMS,
+        create: |palette, params| Ok(Box::new(Lava::new(palette, params)?)),
+    },
+    Registration {
+        name: "running",
+        description: "waves of two or three colors running through each other both ways",
+        params: running::PARAMS,
+        create: |palette, params| Ok(Box::new(Running::new(palette, params)?)),
+    },
+    Registration {
+        name: "strobe",
+        description: "flashes of one color, at no more than 3 a second",
+        params: strobe::PARAMS,
+        create: |_, params| Ok(Box::new(Strobe::new(params)?)),
+    },
+    Registration {
+        name: "emergency",
+        description: "the halves of the strip flashing red and blue in turns, like police lights",
+        params: emergency::PARAMS,
+        create: |_, params| Ok(Box::new(Emergency::new(params)?)),
+    },
+    Registration {
+        name: "flag",
+        description: "a pride flag's stripes stretched along the strip, still or waving",
+        params: flag::PARAMS,
+        create: |_, params| Ok(Box::new(Flag::new(params)?)),
+    },
+    Registration {
+        name: "gradient",
+        description: "a still gradient through any colors along the strip, or turned slowly",
+        params: gradient::PARAMS,
+        create: |palette, params| Ok(Box::new(Gradient::new(palette, params)?)),
+    },
+    Registration {
+        name: "life",
+        description: "an elementary cellular automaton such as rule 30, cells colored by age",
+        params: life::PARAMS,
+        create: |palette, params| Ok(Box::new(Life::new(palette, params)?)),
+    },
+    Registration {
+        name: "pacifica",
+        description: "layered blue-green ocean waves with white caps, calm at low brightness",
+        params: pacifica::PARAMS,
+        create: |palette, params| Ok(Box::new(Pacifica::new(palette, params)?)),
+    },
+    Registration {
+        name: "lightning",
+        description: "a storm of bursts of white flashes over a dark blue sky",
+        params: lightning::PARAMS,
+        create: |_, params| Ok(Box::new(Lightning::new(params)?)),
+    },
+    Registration {
+        name: "confetti",
+        description: "pixels popping on in random colors and fading",
+        params: confetti::PARAMS,
+        create: |palette, params| Ok(Box::new(Confetti::new(palette, params)?)),
+    },
+    Registration {
+        name: "sinelon",
+        description: "a dot swinging end to end with a fading trail, its hue drifting",
+        params: sinelon::PARAMS,
+        create: |palette, params| Ok(Box::new(Sinelon::new(palette, params)?)),
+    },
+];
+
+/// Names of the effects, for help and error messages.
+pub fn names() -> Vec<&'static str> {
+    REGISTRY.iter().map(|r| r.name).collect()
+}
+
+/// The registration for effect `name`.
+pub fn find(name: &str) -> Option<&'static Registration> {
+    REGISTRY.iter().find(|r| r.name == name)
+}
+
+/// The effect called `name`, drawing its colors from `palette` when it
+/// takes one and set up by `params`. Names may stack effects as layers, as
+/// `parse_stack` reads them.
+pub fn create(
+    name: &str,
+    palette: Option<Palette>,
+    params: &Params,
+) -> std::result::Result<Box<dyn Effect>, String> {
+    let layers = parse_stack(name)?;
+    match layers.as_slice() {
+        [layer]
+            if layer.blend == BlendMode::Alpha && layer.opacity == 1.0 && layer.speed == 1.0 =>
+        {
+            layer.create(palette, params)
+        }
+        _ => Ok(Box::new(Layers::new(&layers, palette, params)?)),
+    }
+}
+
+/// Keys in `params` that no layer of any of the effects `names` reads.
+pub fn unused_params(names: &[&str], params: &Params) -> Vec<String> {
+    let layers: Vec<Layer> = names
+        .iter()
+        .filter_map(|name| parse_stack(name).ok())
+        .flatten()
+        .collect();
+    params
+        .keys()
+        .filter(|key| {
+            !layers
+                .iter()
+                .any(|layer| layer.effect.params.iter().any(|p| p.name == *key))
+        })
+        .map(str::to_string)
+        .collect()
+}
+
+/// Check that `name` is an effect, or a stack of them, that can be run.
+pub fn check(name: &str) -> std::result::Result<(), String> {
+    parse_stack(name).map(|_| ())
+}
+
+/// Runs effects into their frame buffers and smooths the brightness they
+/// are shown at.
+///
+/// The engine doesn't own the strips' drivers: each strip is written from
+/// an output thread of its own that is sent the engine's frames, so a
+/// slow strip or a stalled SPI write doesn't hold up the others or the
+/// effects' timing.
+pub struct Engine {
+    effects: Vec<Box<dyn Effect>>,
+    frames: Vec<Vec<Color16>>,
+    /// Time the effects have run, at their speed, up to `clock`.
+    time: Duration,
+    clock: Instant,
+    speed: f64,
+    last_tick: Option<Instant>,
+    brightness: Smoothed,
+    level: f64,
+}
+
+impl Engine {
+    /// An engine running each effect into a frame of its number of pixels,
+    /// starting at `now`.
+    pub fn new(effects: Vec<(Box<dyn Effect>, usize)>, now: Instant) -> Self {
+        let mut engine = Engine {
+            effects: Vec::new(),
+            frames: Vec::new(),
+            time: Duration::ZERO,
+            clock: now,
+            speed: 1.0,
+            last_tick: None,
+            brightness: Smoothed::default(),
+            level: 0.0,
+        };
+        engine.set_effects(effects, now);
+        engine
+    }
+
+    /// Replace the effects, starting them again from `now` on black
+    /// frames; the brightness carries on smoothly.
+    pub fn set_effects(&mut self, effects: Vec<(Box<dyn Effect>, usize)>, now: Instant) {
+        let (effects, frames) = effects
+            .into_iter()
+            .map(|(effect, len)| (effect, vec![Color16::default(); len]))
+            .unzip();
+        self.effects = effects;
+        self.frames = frames;
+        self.time = Duration::ZERO;
+        self.clock = now;
+    }
+
+    /// Replace the effects, carrying on from the time the old ones had
+    /// reached, for changes such as of palette that shouldn't start the
+    /// animation over.
+    pub fn swap_effects(&mut self, effects: Vec<(Box<dyn Effect>, usize)>) {
+        let time = self.time;
+        self.set_effects(effects, self.clock);
+        self.time = time;
+    }
+
+    /// Run the effects `speed` times as fast from now on, carrying on from
+    /// where they are; speeds are held to 0 to `MAX_SPEED`.
+    pub fn set_speed(&mut self, speed: f64) {
+        self.speed = if speed.is_nan() {
+            0.0
+        } else {
+            speed.clamp(0.0, MAX_SPEED)
+        };
+    }
+
+    /// Render every frame for `now`, easing the brightness towards
+    /// `target`, from 0 to 255, about two thirds of the way every
+    /// `smoothing` seconds.
+    pub fn tick(&mut self, now: Instant, target: f64, smoothing: f64) {
+        let elapsed = self.last_tick.map_or(0.0, |last| {
+            now.saturating_duration_since(last).as_secs_f64()
+        });
+        self.last_tick = Some(now);
+        self.level = self.brightness.update(target, elapsed, smoothing) / 255.0;
+        self.time += now
+            .saturating_duration_since(self.clock)
+            .mul_f64(self.speed);
+        self.clock = self.clock.max(now);
+        for (effect, frame) in self.effects.iter_mut().zip(self.frames.iter_mut()) {
+            effect.render(frame, self.time);
+        }
+    }
+
+    /// The frames drawn by the last tick, one per effect.
+    pub fn frames(&self) -> &[Vec<Color16>] {
+        &self.frames
+    }
+
+    /// Brightness to show the frames at, from 0 to 1.
+    pub fn brightness(&self) -> f64 {
+        self.level
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn elapsed_is_capped() {
+        let mut last = None;
+        let ms = Duration::from_millis;
+        assert_eq!(elapsed(&mut last, ms(500), MAX_GAP), 0.0);
+        assert_eq!(elapsed(&mut last, ms(750), MAX_GAP), 0.25);
+        assert_eq!(elapsed(&mut last, ms(9000), MAX_GAP), MAX_GAP);
+        // Going back in time moves nothing on, but starts over from there.
+        assert_eq!(elapsed(&mut last, ms(100), MAX_GAP), 0.0);
+        assert_eq!(elapsed(&mut last, ms(600), 0.25), 0.25);
+        assert_eq!(last, Some(ms(600)));
+    }
+
+    #[test]
+    fn engine_renders_each_effect() {
+        let now = Instant::now();
+        let effects = vec![
+            (create("rainbow", None, &Params::default()).unwrap(), 3),
+            (create("rainbow", None, &Params::default()).unwrap(), 5),
+        ];
+        assert!(create("sparkle", None, &Params::default()).is_err());
+        let mut engine = Engine::new(effects, now);
+        engine.tick(now, 255.0, 2.0);
+        assert_eq!(engine.frames()[0].len(), 3);
+        assert_eq!(engine.frames()[1][0], Color16::new(65535, 0, 0));
+        assert_eq!(engine.brightness(), 1.0);
+
+        // The rainbow moves on with time, and brightness eases.
+        let first = engine.frames()[1].clone();
+        engine.tick(now + Duration::from_secs(2), 0.0, 2.0);
+        assert_ne!(engine.frames()[1], first);
+        assert!(engine.brightness() > 0.3 && engine.brightness() < 0.4);
+
+        // Stopped, the effects hold still.
+        engine.set_speed(0.0);
+        let held = engine.frames()[1].clone();
+        engine.tick(now + Duration::from_secs(5), 0.0, 2.0);
+        assert_eq!(engine.frames()[1], held);
+
+        // Swapped in, effects carry on from the same time.
+        let rainbow = create("rainbow", None, &Params::default()).unwrap();
+        engine.swap_effects(vec![(rainbow, 5)]);
+        engine.tick(now + Duration::from_secs(6), 0.0, 2.0);
+        assert_eq!(engine.frames()[0], held);
+    }
+}
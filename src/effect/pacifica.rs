@@ -0,0 +1,127 @@
+//! Ocean waves, after Mark Kriegsman's Pacifica for FastLED: layers of
+//! blue-green waves at different sizes and speeds, with white caps where
+//! they pile up.
+
+use super::{Effect, Param, Params};
+use crate::color::{Color, Color16};
+use crate::palette::Palette;
+
+use std::f64::consts::TAU;
+use std::time::Duration;
+
+pub const PARAMS: &[Param] = &[
+    Param {
+        name: "scale",
+        description: "pixels across the largest waves [default: 30]",
+    },
+    Param {
+        name: "rate",
+        description: "how fast the waves roll [default: 1]",
+    },
+];
+
+/// Pacifica's three palettes: deep blue rising to blue-green, the last to
+/// blue-white.
+const PALETTES: [&[u32]; 3] = [
+    &[
+        0x000507, 0x000409, 0x00030b, 0x00030d, 0x000210, 0x000212, 0x000114, 0x000117, 0x000019,
+        0x00001c, 0x000026, 0x000031, 0x00003b, 0x000046, 0x14554b, 0x28aa50,
+    ],
+    &[
+        0x000507, 0x000409, 0x00030b, 0x00030d, 0x000210, 0x000212, 0x000114, 0x000117, 0x000019,
+        0x00001c, 0x000026, 0x000031, 0x00003b, 0x000046, 0x0c5f52, 0x19be5f,
+    ],
+    &[
+        0x000208, 0x00030e, 0x000514, 0x00061a, 0x000820, 0x000927, 0x000b2d, 0x000c33, 0x000e39,
+        0x001040, 0x001450, 0x001860, 0x001c70, 0x002080, 0x1040bf, 0x2060ff,
+    ],
+];
+
+/// Each layer's palette, size against the largest, speed in turns a second
+/// and brightness, after Pacifica's.
+const LAYERS: &[(usize, f64, f64, f64)] = &[
+    (0, 1.0, 0.031, 0.45),
+    (1, 0.73, -0.047, 0.39),
+    (2, 0.41, 0.059, 0.28),
+    (2, 0.29, -0.071, 0.22),
+];
+
+/// The water between the waves.
+const DEEP: Color = Color::new(2, 6, 10);
+
+/// Level, 0 to 1, above which waves show white caps.
+const CAPS: f64 = 0.35;
+
+/// Rolling layers of ocean colors.
+pub struct Pacifica {
+    palettes: Vec<Palette>,
+    scale: f64,
+    rate: f64,
+}
+
+impl Pacifica {
+    pub fn new(palette: Option<Palette>, params: &Params) -> std::result::Result<Self, String> {
+        let palettes = match palette {
+            Some(palette) => vec![palette; PALETTES.len()],
+            None => PALETTES
+                .iter()
+                .map(|colors| {
+                    let colors: Vec<Color> = colors.iter().copied().map(Color::from_rgb).collect();
+                    Palette::even(&colors)
+                })
+                .collect(),
+        };
+        Ok(Pacifica {
+            palettes,
+            scale: params.number("scale", 30.0, 1.0, 100_000.0)?,
+            rate: params.number("rate", 1.0, 0.0, 100.0)?,
+        })
+    }
+}
+
+impl Effect for Pacifica {
+    fn render(&mut self, frame: &mut [Color16], t: Duration) {
+        let t = t.as_secs_f64() * self.rate;
+        let deep = Color16::from(DEEP);
+        for (i, pixel) in frame.iter_mut().enumerate() {
+            let x = i as f64 / self.scale;
+            let mut color = deep;
+            let mut level = 0.0;
+            for (n, &(palette, size, speed, brightness)) in LAYERS.iter().enumerate() {
+                // Where the wave is along its palette, and how bright it
+                // swells, drifting at different rates.
+                let phase = TAU * (x / size + t * speed);
+                let at = (phase.sin() + 1.0) / 2.0;
+                let swell = (TAU * (x / size / 1.7 - t * speed * 0.6) + n as f64).sin();
+                let bright = brightness * (0.75 + 0.25 * swell);
+                color =
+                    color.saturating_add(self.palettes[palette].sample(at).scale(bright).into());
+                level += at * bright;
+            }
+            if level > CAPS {
+                let cap = ((level - CAPS) / (1.0 - CAPS)).min(1.0);
+                color = color.saturating_add(Color16::from(Color::WHITE).scale(cap * cap * 0.5));
+            }
+            // Deepen the blues and greens, as Pacifica does.
+            let green = u32::from(color.green) * 4 / 5;
+            *pixel = Color16::new(color.red / 2, green as u16, color.blue);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn waves_roll_blue_green() {
+        let mut pacifica = Pacifica::new(None, &Params::default()).unwrap();
+        let mut frame = vec![Color16::default(); 60];
+        pacifica.render(&mut frame, Duration::from_secs(5));
+        assert!(frame.iter().all(|c| c.red <= c.blue.max(c.green)));
+        assert!(frame.iter().any(|c| c.blue > 10_000));
+        let first = frame.clone();
+        pacifica.render(&mut frame, Duration::from_secs(6));
+        assert_ne!(frame, first);
+    }
+}
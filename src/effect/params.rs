@@ -0,0 +1,141 @@
+//! Knobs effects expose by name, given as key=value pairs on the command
+//! line, in the config file or on a layer of a stack.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::iter::FromIterator;
+use std::str::FromStr;
+
+/// A knob an effect takes, for the effects listing.
+pub struct Param {
+    pub name: &'static str,
+    /// One line for the effects listing, with the default.
+    pub description: &'static str,
+}
+
+/// Parameter values by name, kept as written until an effect reads them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Params {
+    values: BTreeMap<String, String>,
+}
+
+impl Params {
+    pub fn set(&mut self, key: &str, value: &str) {
+        self.values.insert(key.to_string(), value.to_string());
+    }
+
+    /// Remove `key`, returning its value, for parameters handled before
+    /// they reach an effect.
+    pub fn take(&mut self, key: &str) -> Option<String> {
+        self.values.remove(key)
+    }
+
+    /// These values with any missing filled in from `base`.
+    pub fn or(&self, base: &Params) -> Params {
+        let mut values = base.values.clone();
+        values.extend(self.values.clone());
+        Params { values }
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.values.keys().map(String::as_str)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Parameter `key` parsed, if it is given.
+    pub fn optional<T: FromStr>(&self, key: &str) -> std::result::Result<Option<T>, String>
+    where
+        T::Err: fmt::Display,
+    {
+        self.values
+            .get(key)
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|e| format!("bad {} {}: {}", key, value, e))
+            })
+            .transpose()
+    }
+
+    /// Parameter `key` parsed, or `default` when it isn't given.
+    pub fn get<T: FromStr>(&self, key: &str, default: T) -> std::result::Result<T, String>
+    where
+        T::Err: fmt::Display,
+    {
+        Ok(self.optional(key)?.unwrap_or(default))
+    }
+
+    /// Parameter `key` as a number from `min` to `max`, or `default`.
+    pub fn number(
+        &self,
+        key: &str,
+        default: f64,
+        min: f64,
+        max: f64,
+    ) -> std::result::Result<f64, String> {
+        let value = self.get(key, default)?;
+        if (min..=max).contains(&value) {
+            Ok(value)
+        } else {
+            Err(format!(
+                "{} must be from {} to {}, not {}",
+                key, min, max, value
+            ))
+        }
+    }
+}
+
+impl FromIterator<(String, String)> for Params {
+    fn from_iter<I: IntoIterator<Item = (String, String)>>(iter: I) -> Self {
+        Params {
+            values: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl fmt::Display for Params {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let pairs: Vec<String> = self
+            .values
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect();
+        f.write_str(&pairs.join(", "))
+    }
+}
+
+/// Read one "key=value" pair.
+pub fn parse_param(s: &str) -> std::result::Result<(String, String), String> {
+    let (key, value) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected key=value, got {}", s))?;
+    let key = key.trim();
+    if key.is_empty() {
+        return Err(format!("no parameter name in {}", s));
+    }
+    Ok((key.to_string(), value.trim().to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_values_over_defaults() {
+        let base: Params = vec![parse_param("density = 0.3").unwrap()]
+            .into_iter()
+            .collect();
+        let mut params = Params::default();
+        params.set("count", "x");
+        let params = params.or(&base);
+        assert_eq!(params.number("density", 0.5, 0.0, 1.0), Ok(0.3));
+        assert_eq!(params.number("cooling", 0.5, 0.0, 1.0), Ok(0.5));
+        assert!(params.number("density", 0.5, 0.5, 1.0).is_err());
+        assert!(params.get("count", 3u32).is_err());
+        assert_eq!(params.to_string(), "count=x, density=0.3");
+        assert!(parse_param("=3").is_err());
+    }
+}
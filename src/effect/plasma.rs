@@ -0,0 +1,80 @@
+//! The demoscene plasma: sine waves of different sizes and speeds summed
+//! along the strip and shown through a palette.
+
+use super::{Colors, Effect, Param, Params};
+use crate::color::Color16;
+use crate::palette::Palette;
+
+use std::f64::consts::TAU;
+use std::time::Duration;
+
+pub const PARAMS: &[Param] = &[
+    Param {
+        name: "scale",
+        description: "pixels across the largest waves [default: 24]",
+    },
+    Param {
+        name: "rate",
+        description: "how fast the waves move [default: 1]",
+    },
+];
+
+/// Each wave's size, against the largest, and speed in turns a second.
+/// Speeds with no simple ratio keep the pattern from repeating.
+const WAVES: &[(f64, f64)] = &[(1.0, 0.11), (0.53, -0.17), (0.31, 0.07), (0.19, -0.05)];
+
+/// Summed sine waves flowing past each other.
+pub struct Plasma {
+    colors: Colors,
+    scale: f64,
+    rate: f64,
+}
+
+impl Plasma {
+    pub fn new(palette: Option<Palette>, params: &Params) -> std::result::Result<Self, String> {
+        Ok(Plasma {
+            colors: Colors::from_palette(palette),
+            scale: params.number("scale", 24.0, 1.0, 100_000.0)?,
+            rate: params.number("rate", 1.0, 0.0, 100.0)?,
+        })
+    }
+}
+
+impl Effect for Plasma {
+    fn render(&mut self, frame: &mut [Color16], t: Duration) {
+        let t = t.as_secs_f64() * self.rate;
+        for (i, pixel) in frame.iter_mut().enumerate() {
+            let x = i as f64 / self.scale;
+            let sum: f64 = WAVES
+                .iter()
+                .enumerate()
+                .map(|(n, (size, speed))| (TAU * (x / size + t * speed) + n as f64).sin())
+                .sum();
+            // From -1 to 1 over the waves, then 0 to 1.
+            let level = (sum / WAVES.len() as f64 + 1.0) / 2.0;
+            *pixel = self.colors.at(level);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flows_smoothly() {
+        let mut params = Params::default();
+        params.set("scale", "200");
+        let mut plasma = Plasma::new(None, &params).unwrap();
+        let mut frame = vec![Color16::default(); 60];
+        plasma.render(&mut frame, Duration::from_secs(3));
+        let first = frame.clone();
+        // Neighbours are close on the wheel; the pattern moves on.
+        for pair in frame.windows(2) {
+            let step = |a: u16, b: u16| (i32::from(a) - i32::from(b)).abs();
+            assert!(step(pair[0].red, pair[1].red) < 20000);
+        }
+        plasma.render(&mut frame, Duration::from_secs(4));
+        assert_ne!(frame, first);
+    }
+}
@@ -0,0 +1,184 @@
+//! Raindrops running down the strip, speeding up as they go.
+
+use super::{elapsed, Colors, Direction, Effect, Param, Params, MAX_GAP};
+use crate::color::{Color, Color16};
+use crate::palette::Palette;
+use crate::random::Rng;
+
+use std::time::Duration;
+
+pub const PARAMS: &[Param] = &[
+    Param {
+        name: "intensity",
+        description: "from drizzle at 0 to downpour at 1 [default: 0.3]",
+    },
+    Param {
+        name: "gravity",
+        description: "pixels per second the drops gain each second [default: 40]",
+    },
+    Param {
+        name: "fade",
+        description: "seconds a drop lasts [default: 1.2]",
+    },
+    Param {
+        name: "color",
+        description: "color of the drops [default: random from the palette, or blue to white]",
+    },
+    Param {
+        name: "direction",
+        description: "forward runs from the first pixel, reverse from the last [default: forward]",
+    },
+];
+
+/// Drops a second on every 60 pixels in a downpour.
+const MAX_DROPS_PER_SECOND: f64 = 20.0;
+
+/// Seconds of movement a drop's streak covers.
+const STREAK: f64 = 0.06;
+
+/// The colors drops are picked between without a palette.
+const BLUE: Color = Color::new(0x30, 0x60, 0xff);
+const WHITE: Color = Color::new(0xc0, 0xd8, 0xff);
+
+struct Drop {
+    at: f64,
+    speed: f64,
+    age: f64,
+    color: Color16,
+}
+
+/// Drops appearing at random and running along the strip as they fade.
+pub struct Rain {
+    colors: Option<Colors>,
+    intensity: f64,
+    gravity: f64,
+    fade: f64,
+    direction: Direction,
+    rng: Rng,
+    drops: Vec<Drop>,
+    last: Option<Duration>,
+}
+
+impl Rain {
+    pub fn new(palette: Option<Palette>, params: &Params) -> std::result::Result<Self, String> {
+        let colors = match Colors::from_params(params, "color", palette)? {
+            Colors::Wheel => None,
+            colors => Some(colors),
+        };
+        Ok(Rain {
+            colors,
+            intensity: params.number("intensity", 0.3, 0.0, 1.0)?,
+            gravity: params.number("gravity", 40.0, 0.0, 10000.0)?,
+            fade: params.number("fade", 1.2, 0.05, 60.0)?,
+            direction: params.get("direction", Direction::default())?,
+            rng: Rng::from_time(),
+            drops: Vec::new(),
+            last: None,
+        })
+    }
+
+    fn color(&mut self) -> Color16 {
+        let pick = self.rng.unit();
+        match &self.colors {
+            Some(colors) => colors.at(pick),
+            None => Color16::from(BLUE.lerp(WHITE, pick)),
+        }
+    }
+}
+
+impl Effect for Rain {
+    fn render(&mut self, frame: &mut [Color16], t: Duration) {
+        let step = elapsed(&mut self.last, t, MAX_GAP);
+        let len = frame.len();
+
+        for drop in self.drops.iter_mut() {
+            drop.speed += self.gravity * step;
+            drop.at += drop.speed * step;
+            drop.age += step;
+        }
+        let fade = self.fade;
+        self.drops
+            .retain(|d| d.age < fade && d.at - d.speed * STREAK < len as f64);
+
+        // Intensity squared, so the low end has room for a light drizzle.
+        let rate = MAX_DROPS_PER_SECOND * self.intensity.powi(2) * len as f64 / 60.0;
+        let mut due = rate * step;
+        while due > 0.0 {
+            if self.rng.unit() < due.min(1.0) {
+                let at = self.rng.unit() * len as f64;
+                let color = self.color();
+                self.drops.push(Drop {
+                    at,
+                    speed: 0.0,
+                    age: 0.0,
+                    color,
+                });
+            }
+            due -= 1.0;
+        }
+
+        for pixel in frame.iter_mut() {
+            *pixel = Color16::default();
+        }
+        for drop in &self.drops {
+            let level = 1.0 - drop.age / self.fade;
+            // A streak back from the drop, longer the faster it falls.
+            let tail = (drop.speed * STREAK).max(1.0);
+            for (i, pixel) in frame.iter_mut().enumerate() {
+                let at = self.direction.along(i, len) as f64 + 0.5;
+                let behind = drop.at - at;
+                let lit = if behind < -0.5 || behind > tail {
+                    0.0
+                } else if behind < 0.5 {
+                    // The drop itself, shared between the pixels it's on.
+                    1.0 - behind.abs()
+                } else {
+                    1.0 - behind / tail
+                };
+                if lit > 0.0 {
+                    *pixel = pixel.saturating_add(drop.color.scale(lit.min(1.0) * level));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drops_fall_faster_and_fade() {
+        let mut params = Params::default();
+        params.set("intensity", "0");
+        let mut rain = Rain::new(None, &params).unwrap();
+        rain.drops.push(Drop {
+            at: 2.5,
+            speed: 0.0,
+            age: 0.0,
+            color: Color16::new(0, 0, 65535),
+        });
+        let mut frame = vec![Color16::default(); 40];
+        rain.render(&mut frame, Duration::from_secs(0));
+        assert_eq!(frame[2].blue, 65535);
+        let mut moved = Vec::new();
+        for tenth in 1..=5 {
+            rain.render(&mut frame, Duration::from_millis(tenth * 100));
+            moved.push(rain.drops[0].at);
+        }
+        // Each tenth of a second covers more ground than the last.
+        assert!(moved.windows(3).all(|m| m[2] - m[1] > m[1] - m[0]));
+        let brightest = frame.iter().map(|c| c.blue).max().unwrap();
+        assert!(brightest < 65535 && brightest > 0);
+
+        // Then it has gone, and with no intensity no more come.
+        for tenth in 6..20 {
+            rain.render(&mut frame, Duration::from_millis(tenth * 100));
+        }
+        assert!(rain.drops.is_empty());
+
+        rain.intensity = 1.0;
+        rain.render(&mut frame, Duration::from_millis(2100));
+        assert!(!rain.drops.is_empty());
+    }
+}
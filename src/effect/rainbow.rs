@@ -0,0 +1,118 @@
+//! The color wheel, or a palette, spread along the strip and turning.
+
+use super::{Colors, Direction, Effect, Param, Params};
+use crate::color::Color16;
+use crate::palette::Palette;
+
+use std::time::Duration;
+
+pub const PARAMS: &[Param] = &[
+    Param {
+        name: "span",
+        description: "turns of the wheel across the strip; below 1 spreads out part of it [default: 1]",
+    },
+    Param {
+        name: "rate",
+        description: "degrees the wheel turns each second, negative turning it back [default: 12.5]",
+    },
+    Param {
+        name: "saturation",
+        description: "from 0, white, to 1, full color [default: 1]",
+    },
+    Param {
+        name: "direction",
+        description: "forward lays the wheel out from the first pixel, reverse from the last [default: forward]",
+    },
+];
+
+/// Degrees the wheel turns each second.
+const DEGREES_PER_SECOND: f64 = 12.5;
+
+/// The color wheel across the strip, drifting along it.
+pub struct Rainbow {
+    colors: Colors,
+    span: f64,
+    rate: f64,
+    saturation: f64,
+    direction: Direction,
+}
+
+impl Rainbow {
+    /// Draws from `palette` instead of the full wheel when there is one.
+    pub fn new(palette: Option<Palette>) -> Self {
+        Rainbow {
+            colors: Colors::from_palette(palette),
+            span: 1.0,
+            rate: DEGREES_PER_SECOND,
+            saturation: 1.0,
+            direction: Direction::default(),
+        }
+    }
+
+    /// Spread and turned as the parameters give.
+    pub fn with_params(
+        palette: Option<Palette>,
+        params: &Params,
+    ) -> std::result::Result<Self, String> {
+        Ok(Rainbow {
+            span: params.number("span", 1.0, 0.0, 100.0)?,
+            rate: params.number("rate", DEGREES_PER_SECOND, -3600.0, 3600.0)?,
+            saturation: params.number("saturation", 1.0, 0.0, 1.0)?,
+            direction: params.get("direction", Direction::default())?,
+            ..Rainbow::new(palette)
+        })
+    }
+}
+
+impl Effect for Rainbow {
+    fn render(&mut self, frame: &mut [Color16], t: Duration) {
+        let len = frame.len();
+        let turned = t.as_secs_f64() * self.rate;
+        for (i, pixel) in frame.iter_mut().enumerate() {
+            let at = self.direction.along(i, len) as f64;
+            let hue = (at * 360.0 * self.span / len as f64 + turned).rem_euclid(360.0);
+            let color = self.colors.at(hue / 360.0);
+            // Towards white at the color's brightest, as lowering
+            // saturation does on the wheel.
+            let top = color.red.max(color.green).max(color.blue);
+            let fade = |c: u16| top - (f64::from(top - c) * self.saturation).round() as u16;
+            *pixel = Color16::new(fade(color.red), fade(color.green), fade(color.blue));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spreads_and_turns_as_asked() {
+        let render = |params: &[(&str, &str)], secs: u64| {
+            let mut set = Params::default();
+            for (key, value) in params {
+                set.set(key, value);
+            }
+            let mut rainbow = Rainbow::with_params(None, &set).unwrap();
+            let mut frame = vec![Color16::default(); 6];
+            rainbow.render(&mut frame, Duration::from_secs(secs));
+            frame
+        };
+        let red = Color16::new(65535, 0, 0);
+        let cyan = Color16::new(0, 65535, 65535);
+        assert_eq!(render(&[], 0)[0], red);
+        assert_eq!(render(&[], 0)[3], cyan);
+        // Half the wheel across the strip, reversed.
+        let half = render(&[("span", "0.5"), ("direction", "reverse")], 0);
+        assert_eq!((half[5], half[0].green), (red, 65535));
+        // Turned a quarter, and washed out.
+        assert_eq!(
+            render(&[("rate", "-45")], 2)[3],
+            Color16::new(32768, 65535, 0)
+        );
+        let pale = render(&[("saturation", "0.5")], 0)[0];
+        assert_eq!(pale, Color16::new(65535, 32767, 32767));
+        let mut params = Params::default();
+        params.set("span", "-1");
+        assert!(Rainbow::with_params(None, &params).is_err());
+    }
+}
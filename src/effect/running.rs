@@ -0,0 +1,118 @@
+//! Running lights: sine waves of two or three colors moving through each
+//! other, alternate waves running the opposite way.
+
+use super::{colors, Effect, Param, Params};
+use crate::color::Color16;
+use crate::palette::Palette;
+
+use std::f64::consts::TAU;
+use std::time::Duration;
+
+pub const PARAMS: &[Param] = &[
+    Param {
+        name: "colors",
+        description: "two or three colors separated by commas, one a wave [default: two from the palette or wheel]",
+    },
+    Param {
+        name: "wavelength",
+        description: "pixels from one crest of a wave to the next [default: 16]",
+    },
+    Param {
+        name: "speeds",
+        description: "pixels a second each wave moves, separated by commas; the last is used for any more waves [default: 8,5,3]",
+    },
+];
+
+const DEFAULT_SPEEDS: &[f64] = &[8.0, 5.0, 3.0];
+
+/// A wave of one color and how fast it runs, backwards for odd waves.
+struct Wave {
+    color: Color16,
+    speed: f64,
+}
+
+/// Colored waves running both ways along the strip.
+pub struct Running {
+    waves: Vec<Wave>,
+    wavelength: f64,
+}
+
+impl Running {
+    pub fn new(palette: Option<Palette>, params: &Params) -> std::result::Result<Self, String> {
+        let colors = colors::list(params, "colors", palette, 2)?;
+        if !(2..=3).contains(&colors.len()) {
+            return Err(format!(
+                "colors needs two or three colors, not {}",
+                colors.len()
+            ));
+        }
+        let speeds = match params.optional::<String>("speeds")? {
+            Some(list) => list
+                .split(',')
+                .map(|s| match s.trim().parse::<f64>() {
+                    Ok(speed) if (0.0..=1000.0).contains(&speed) => Ok(speed),
+                    _ => Err(format!(
+                        "bad speeds {}: expected numbers from 0 to 1000",
+                        list
+                    )),
+                })
+                .collect::<std::result::Result<Vec<_>, _>>()?,
+            None => DEFAULT_SPEEDS.to_vec(),
+        };
+        let waves = colors
+            .into_iter()
+            .enumerate()
+            .map(|(n, color)| Wave {
+                color,
+                speed: speeds[n.min(speeds.len() - 1)],
+            })
+            .collect();
+        Ok(Running {
+            waves,
+            wavelength: params.number("wavelength", 16.0, 1.0, 10000.0)?,
+        })
+    }
+}
+
+impl Effect for Running {
+    fn render(&mut self, frame: &mut [Color16], t: Duration) {
+        let t = t.as_secs_f64();
+        for (i, pixel) in frame.iter_mut().enumerate() {
+            *pixel = self
+                .waves
+                .iter()
+                .enumerate()
+                .fold(Color16::default(), |sum, (n, wave)| {
+                    let moved = if n % 2 == 0 { wave.speed } else { -wave.speed } * t;
+                    let phase = TAU * (i as f64 - moved) / self.wavelength;
+                    sum.saturating_add(wave.color.scale((phase.sin() + 1.0) / 2.0))
+                });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn waves_run_both_ways() {
+        let mut params = Params::default();
+        params.set("colors", "red, blue");
+        params.set("speeds", "10");
+        params.set("wavelength", "40");
+        let mut running = Running::new(None, &params).unwrap();
+        let mut frame = vec![Color16::default(); 40];
+        let crest = |frame: &[Color16], level: fn(&Color16) -> u16| {
+            (0..frame.len()).max_by_key(|&i| level(&frame[i])).unwrap()
+        };
+        running.render(&mut frame, Duration::from_secs(0));
+        let (red, blue) = (crest(&frame, |c| c.red), crest(&frame, |c| c.blue));
+        running.render(&mut frame, Duration::from_millis(500));
+        assert_eq!(crest(&frame, |c| c.red), red + 5);
+        assert_eq!(crest(&frame, |c| c.blue), blue - 5);
+
+        params.set("colors", "red");
+        assert!(Running::new(None, &params).is_err());
+    }
+}
@@ -0,0 +1,106 @@
+//! The Larson scanner: an eye sweeping back and forth with a fading tail,
+//! as on KITT and Cylon visors.
+
+use super::{Colors, Effect, Param, Params};
+use crate::color::Color16;
+use crate::palette::Palette;
+
+use std::time::Duration;
+
+pub const PARAMS: &[Param] = &[
+    Param {
+        name: "color",
+        description: "color of the eye [default: the palette along the strip, or red]",
+    },
+    Param {
+        name: "width",
+        description: "pixels lit fully by the eye [default: 2]",
+    },
+    Param {
+        name: "period",
+        description: "seconds to sweep from one end to the other [default: 1.5]",
+    },
+    Param {
+        name: "tail",
+        description: "pixels the tail takes to fade to 1% [default: 8]",
+    },
+];
+
+/// Fraction of full the tail fades to over its length.
+const TAIL_END: f64 = 0.01;
+
+/// A bright eye bouncing between the ends of the strip.
+pub struct Scanner {
+    colors: Colors,
+    width: f64,
+    period: f64,
+    tail: f64,
+}
+
+impl Scanner {
+    pub fn new(palette: Option<Palette>, params: &Params) -> std::result::Result<Self, String> {
+        let colors = match Colors::from_params(params, "color", palette)? {
+            Colors::Wheel => Colors::One(Color16::new(65535, 0, 0)),
+            colors => colors,
+        };
+        Ok(Scanner {
+            colors,
+            width: params.number("width", 2.0, 1.0, 1000.0)?,
+            period: params.number("period", 1.5, 0.05, 3600.0)?,
+            tail: params.number("tail", 8.0, 0.0, 1000.0)?,
+        })
+    }
+}
+
+impl Effect for Scanner {
+    fn render(&mut self, frame: &mut [Color16], t: Duration) {
+        let len = frame.len();
+        let span = len.saturating_sub(1) as f64;
+        // Out over one period and back over the next.
+        let phase = (t.as_secs_f64() / self.period) % 2.0;
+        let (centre, forward) = if phase < 1.0 {
+            (phase * span, true)
+        } else {
+            ((2.0 - phase) * span, false)
+        };
+        let half = (self.width - 1.0) / 2.0;
+        for (i, pixel) in frame.iter_mut().enumerate() {
+            let offset = i as f64 - centre;
+            // How far behind the eye's edge the pixel is, the way it came.
+            let behind = if forward { -offset } else { offset } - half;
+            let level = if offset.abs() <= half {
+                1.0
+            } else if behind > 0.0 && self.tail > 0.0 {
+                TAIL_END.powf(behind / self.tail)
+            } else {
+                // A pixel just ahead of the eye lights as it arrives.
+                (1.0 - (offset.abs() - half)).max(0.0)
+            };
+            let color = self.colors.at(i as f64 / len as f64);
+            *pixel = color.scale(level);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn eye_bounces_with_a_tail() {
+        let mut scanner = Scanner::new(None, &Params::default()).unwrap();
+        let mut frame = vec![Color16::default(); 11];
+        // On pixel 3 heading towards the far end.
+        scanner.render(&mut frame, Duration::from_secs_f64(0.45));
+        let red: Vec<u16> = frame.iter().map(|c| c.red).collect();
+        assert_eq!(red[3..5], [65535, 32768]);
+        assert!(red[2] > red[1] && red[1] > red[0] && red[0] > 0);
+        assert_eq!(red[6], 0);
+        assert_eq!(frame[3].green, 0);
+
+        // Coming back, the tail is on the far side.
+        scanner.render(&mut frame, Duration::from_secs_f64(2.5));
+        assert_eq!(frame[3].red, 65535);
+        assert!(frame[5].red > frame[1].red);
+    }
+}
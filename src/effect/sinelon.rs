@@ -0,0 +1,105 @@
+//! Sinelon, from FastLED's demo reel: a dot swinging from end to end
+//! leaving a fading trail, its hue slowly drifting.
+
+use super::{elapsed, Colors, Effect, Param, Params, MAX_GAP};
+use crate::color::Color16;
+use crate::palette::Palette;
+
+use std::f64::consts::TAU;
+use std::time::Duration;
+
+pub const PARAMS: &[Param] = &[
+    Param {
+        name: "rate",
+        description: "swings out and back each minute [default: 13]",
+    },
+    Param {
+        name: "trail",
+        description: "seconds the trail takes to fade to a tenth [default: 0.5]",
+    },
+    Param {
+        name: "color",
+        description: "color of the dot [default: drift along the palette or wheel]",
+    },
+];
+
+/// Turns a second the dot drifts along the palette or wheel.
+const DRIFT: f64 = 0.02;
+
+/// A swinging dot and its trail.
+pub struct Sinelon {
+    colors: Colors,
+    rate: f64,
+    trail: f64,
+    pixels: Vec<Color16>,
+    /// The dot's pixel at the last frame.
+    dot: Option<usize>,
+    last: Option<Duration>,
+}
+
+impl Sinelon {
+    pub fn new(palette: Option<Palette>, params: &Params) -> std::result::Result<Self, String> {
+        Ok(Sinelon {
+            colors: Colors::from_params(params, "color", palette)?,
+            rate: params.number("rate", 13.0, 0.0, 6000.0)?,
+            trail: params.number("trail", 0.5, 0.01, 600.0)?,
+            pixels: Vec::new(),
+            dot: None,
+            last: None,
+        })
+    }
+}
+
+impl Effect for Sinelon {
+    fn render(&mut self, frame: &mut [Color16], t: Duration) {
+        let len = frame.len();
+        if len == 0 {
+            return;
+        }
+        self.pixels.resize(len, Color16::default());
+        let secs = t.as_secs_f64();
+        // From the first pixel out to the last and back.
+        let swing = (TAU * secs * self.rate / 60.0).sin();
+        let dot = ((swing + 1.0) / 2.0 * (len - 1) as f64).round() as usize;
+
+        let from = self.dot.replace(dot).map_or(dot, |from| from.min(len - 1));
+        let step = elapsed(&mut self.last, t, MAX_GAP);
+        let fade = 0.1f64.powf(step / self.trail);
+        for pixel in self.pixels.iter_mut() {
+            *pixel = pixel.scale(fade);
+        }
+        // Light every pixel passed since the last frame, so a fast swing
+        // leaves no gaps.
+        let color = self.colors.at(secs * DRIFT);
+        for pixel in &mut self.pixels[from.min(dot)..=from.max(dot)] {
+            *pixel = color;
+        }
+        frame.copy_from_slice(&self.pixels);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn swings_with_a_trail() {
+        let mut params = Params::default();
+        params.set("rate", "60");
+        let mut sinelon = Sinelon::new(None, &params).unwrap();
+        let mut frame = vec![Color16::default(); 21];
+        let level = |c: &Color16| c.red.max(c.green).max(c.blue);
+        let mut reached = [false, false];
+        for ms in 0..=250 {
+            sinelon.render(&mut frame, Duration::from_millis(ms * 4));
+            reached[0] |= level(&frame[0]) == u16::MAX;
+            reached[1] |= level(&frame[20]) == u16::MAX;
+        }
+        assert_eq!(reached, [true, true]);
+        // A second in, the dot is back in the middle heading out, with the
+        // trail dimmer the further back it was lit.
+        assert_eq!(level(&frame[10]), u16::MAX);
+        assert!(level(&frame[9]) > level(&frame[5]));
+        assert!(level(&frame[5]) > level(&frame[1]));
+    }
+}
@@ -0,0 +1,192 @@
+//! Snow drifting down the strip and piling up at the end, melting away
+//! once the pile is deep enough.
+
+use super::{elapsed, Direction, Effect, Param, Params, MAX_GAP};
+use crate::color::{Color, Color16};
+use crate::palette::Palette;
+use crate::random::Rng;
+
+use std::f64::consts::TAU;
+use std::time::Duration;
+
+pub const PARAMS: &[Param] = &[
+    Param {
+        name: "rate",
+        description: "flakes a second on every 60 pixels [default: 1.5]",
+    },
+    Param {
+        name: "fall",
+        description: "pixels a flake falls each second [default: 4]",
+    },
+    Param {
+        name: "depth",
+        description: "fraction of the strip the pile fills before it melts [default: 0.25]",
+    },
+    Param {
+        name: "color",
+        description: "color of the snow [default: white]",
+    },
+    Param {
+        name: "background",
+        description: "color of the sky [default: #000018]",
+    },
+    Param {
+        name: "direction",
+        description:
+            "forward falls towards the last pixel, reverse towards the first [default: forward]",
+    },
+];
+
+/// Pixels of pile each flake makes.
+const FLAKE_DEPTH: f64 = 0.5;
+
+/// Seconds a full pile takes to melt.
+const MELT_SECONDS: f64 = 4.0;
+
+/// Brightness of settled snow, against falling flakes.
+const PILE_LEVEL: f64 = 0.6;
+
+/// Pixels a flake sways either way as it falls, and how many times a
+/// second.
+const SWAY: f64 = 0.6;
+const SWAYS_PER_SECOND: f64 = 0.3;
+
+struct Flake {
+    at: f64,
+    /// Offset into its sway, so flakes don't sway together.
+    phase: f64,
+    speed: f64,
+}
+
+/// Flakes falling on a dim sky and settling.
+pub struct Snow {
+    color: Color16,
+    background: Color16,
+    rate: f64,
+    fall: f64,
+    depth: f64,
+    direction: Direction,
+    rng: Rng,
+    flakes: Vec<Flake>,
+    /// Pixels of settled snow.
+    pile: f64,
+    /// How much of the pile is left while it melts, from 1 down to 0.
+    melting: Option<f64>,
+    last: Option<Duration>,
+}
+
+impl Snow {
+    pub fn new(_palette: Option<Palette>, params: &Params) -> std::result::Result<Self, String> {
+        Ok(Snow {
+            color: params.get("color", Color::WHITE)?.into(),
+            background: params.get("background", Color::new(0, 0, 0x18))?.into(),
+            rate: params.number("rate", 1.5, 0.0, 1000.0)?,
+            fall: params.number("fall", 4.0, 0.1, 1000.0)?,
+            depth: params.number("depth", 0.25, 0.0, 1.0)?,
+            direction: params.get("direction", Direction::default())?,
+            rng: Rng::from_time(),
+            flakes: Vec::new(),
+            pile: 0.0,
+            melting: None,
+            last: None,
+        })
+    }
+}
+
+impl Effect for Snow {
+    fn render(&mut self, frame: &mut [Color16], t: Duration) {
+        let step = elapsed(&mut self.last, t, MAX_GAP);
+        let len = frame.len() as f64;
+
+        // Flakes landing on the pile add to it, unless it is melting.
+        let top = len - self.pile;
+        let mut landed = 0;
+        for flake in self.flakes.iter_mut() {
+            flake.at += flake.speed * step;
+        }
+        self.flakes.retain(|f| {
+            let down = f.at >= top;
+            landed += down as usize;
+            !down
+        });
+        match self.melting.as_mut() {
+            Some(left) => {
+                *left -= step / MELT_SECONDS;
+                if *left <= 0.0 {
+                    self.melting = None;
+                    self.pile = 0.0;
+                }
+            }
+            None => {
+                self.pile += landed as f64 * FLAKE_DEPTH;
+                if self.pile >= (self.depth * len).max(1.0) {
+                    self.melting = Some(1.0);
+                }
+            }
+        }
+
+        let mut due = self.rate * len / 60.0 * step;
+        while due > 0.0 {
+            if self.rng.unit() < due.min(1.0) {
+                let speed = self.fall * (0.7 + 0.6 * self.rng.unit());
+                self.flakes.push(Flake {
+                    at: 0.0,
+                    phase: self.rng.unit(),
+                    speed,
+                });
+            }
+            due -= 1.0;
+        }
+
+        let pile = self.pile * self.melting.unwrap_or(1.0);
+        let sway = t.as_secs_f64() * SWAYS_PER_SECOND;
+        let n = frame.len();
+        for (i, pixel) in frame.iter_mut().enumerate() {
+            let at = self.direction.along(i, n) as f64 + 0.5;
+            // Part of a pixel may be covered by the top of the pile.
+            let settled = (at + 0.5 - (len - pile)).clamp(0.0, 1.0) * PILE_LEVEL;
+            let falling: f64 = self
+                .flakes
+                .iter()
+                .map(|f| {
+                    let x = f.at + SWAY * (TAU * (sway + f.phase)).sin();
+                    (1.0 - (x - at).abs()).max(0.0)
+                })
+                .sum();
+            let level = (settled + falling).min(1.0);
+            *pixel = self.background.lerp(self.color, level);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snow_piles_up_and_melts() {
+        let mut params = Params::default();
+        params.set("rate", "30");
+        params.set("fall", "20");
+        params.set("depth", "0.5");
+        let mut snow = Snow::new(None, &params).unwrap();
+        snow.rng = Rng::new(2);
+        let mut frame = vec![Color16::default(); 20];
+        let mut deepest: f64 = 0.0;
+        let mut melted = false;
+        for tenth in 0..600 {
+            snow.render(&mut frame, Duration::from_millis(tenth * 100));
+            deepest = deepest.max(snow.pile);
+            melted |= snow.melting.is_some();
+            if tenth == 30 {
+                // The pile shows at the end, with sky above it.
+                assert!(snow.pile >= 2.0);
+                assert_eq!(
+                    frame[19],
+                    Color16::from(Color::new(0, 0, 0x18)).lerp(snow.color, 0.6)
+                );
+            }
+        }
+        assert!(melted && (10.0..12.0).contains(&deepest), "{}", deepest);
+    }
+}
@@ -0,0 +1,25 @@
+//! One color on every pixel.
+
+use super::Effect;
+use crate::color::Color16;
+
+use std::time::Duration;
+
+/// A steady color, for zones given a color rather than an effect.
+pub struct Solid {
+    color: Color16,
+}
+
+impl Solid {
+    pub fn new(color: Color16) -> Self {
+        Solid { color }
+    }
+}
+
+impl Effect for Solid {
+    fn render(&mut self, frame: &mut [Color16], _t: Duration) {
+        for pixel in frame.iter_mut() {
+            *pixel = self.color;
+        }
+    }
+}
@@ -0,0 +1,121 @@
+//! A strobe, held to a safe flash rate however fast it is asked to go.
+
+use super::{Effect, Param, Params};
+use crate::color::{Color, Color16};
+
+use std::time::{Duration, Instant};
+
+pub const PARAMS: &[Param] = &[
+    Param {
+        name: "frequency",
+        description: "flashes a second, at most 3 [default: 2]",
+    },
+    Param {
+        name: "duty",
+        description: "share of each flash the strip is lit, 0 to 1 [default: 0.1]",
+    },
+    Param {
+        name: "color",
+        description: "color of the flashes [default: white]",
+    },
+];
+
+/// The most flashes a second the strobe shows, the limit WCAG 2.3.1 sets.
+/// Flashing from about 3 to 30 times a second is a seizure risk for people
+/// with photosensitive epilepsy, so the limit also holds against the effect
+/// being sped up: it is kept in real time, not the effect's.
+pub const MAX_FREQUENCY: f64 = 3.0;
+
+/// Flashes of one color at a steady rate.
+pub struct Strobe {
+    color: Color16,
+    frequency: f64,
+    duty: f64,
+    /// The flash being shown, counted from the start of the effect.
+    flash: Option<u64>,
+    /// When the latest flash began.
+    started: Option<Instant>,
+}
+
+impl Strobe {
+    pub fn new(params: &Params) -> std::result::Result<Self, String> {
+        Ok(Strobe {
+            color: params.get("color", Color::WHITE)?.into(),
+            frequency: params.number("frequency", 2.0, 0.01, MAX_FREQUENCY)?,
+            duty: params.number("duty", 0.1, 0.0, 1.0)?,
+            flash: None,
+            started: None,
+        })
+    }
+
+    /// The frame at effect time `t` and real time `now`. A flash that would
+    /// begin too soon after the last is skipped.
+    fn render_at(&mut self, frame: &mut [Color16], t: Duration, now: Instant) {
+        let cycles = t.as_secs_f64() * self.frequency;
+        let flash = cycles as u64;
+        let lit = cycles.fract() < self.duty;
+        if lit && self.flash != Some(flash) {
+            let gap = Duration::from_secs_f64(1.0 / MAX_FREQUENCY);
+            let rested = self
+                .started
+                .is_none_or(|started| now.saturating_duration_since(started) >= gap);
+            if rested {
+                self.flash = Some(flash);
+                self.started = Some(now);
+            }
+        }
+        let color = if lit && self.flash == Some(flash) {
+            self.color
+        } else {
+            Color16::default()
+        };
+        for pixel in frame.iter_mut() {
+            *pixel = color;
+        }
+    }
+}
+
+impl Effect for Strobe {
+    fn render(&mut self, frame: &mut [Color16], t: Duration) {
+        self.render_at(frame, t, Instant::now());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Flashes begun over a second of frames 10ms apart, with the effect
+    /// running `speed` times faster than real time.
+    fn flashes(strobe: &mut Strobe, speed: u32) -> usize {
+        let start = Instant::now();
+        let mut frame = vec![Color16::default(); 3];
+        let (mut count, mut was_lit) = (0, false);
+        for ms in 0..1000 {
+            let now = start + Duration::from_millis(ms);
+            strobe.render_at(&mut frame, Duration::from_millis(ms) * speed, now);
+            let lit = frame[0] != Color16::default();
+            if lit && !was_lit {
+                count += 1;
+            }
+            was_lit = lit;
+        }
+        count
+    }
+
+    #[test]
+    fn flash_rate_is_capped() {
+        let mut params = Params::default();
+        params.set("frequency", "2");
+        params.set("duty", "0.2");
+        let mut strobe = Strobe::new(&params).unwrap();
+        assert_eq!(flashes(&mut strobe, 1), 2);
+
+        params.set("frequency", "3");
+        let mut strobe = Strobe::new(&params).unwrap();
+        assert!(flashes(&mut strobe, 20) <= 3);
+
+        params.set("frequency", "4");
+        assert!(Strobe::new(&params).is_err());
+    }
+}
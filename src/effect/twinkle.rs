@@ -0,0 +1,103 @@
+//! Pixels lighting up at random and fading away, like stars.
+
+use super::{elapsed, Colors, Effect, Param, Params, MAX_GAP};
+use crate::color::{Color, Color16};
+use crate::palette::Palette;
+use crate::random::Rng;
+
+use std::time::Duration;
+
+pub const PARAMS: &[Param] = &[
+    Param {
+        name: "density",
+        description: "fraction of pixels lit at once, on average, 0 to 1 [default: 0.1]",
+    },
+    Param {
+        name: "decay",
+        description: "seconds a twinkle takes to fade out [default: 1.5]",
+    },
+    Param {
+        name: "color",
+        description: "color of the twinkles [default: random from the palette or wheel]",
+    },
+    Param {
+        name: "background",
+        description: "color between twinkles [default: black]",
+    },
+];
+
+/// Twinkles lit at random over a background.
+pub struct Twinkle {
+    colors: Colors,
+    background: Color16,
+    density: f64,
+    decay: f64,
+    rng: Rng,
+    /// Color and level, 0 to 1, of the twinkle on each pixel.
+    sparks: Vec<(Color16, f64)>,
+    last: Option<Duration>,
+}
+
+impl Twinkle {
+    pub fn new(palette: Option<Palette>, params: &Params) -> std::result::Result<Self, String> {
+        Ok(Twinkle {
+            colors: Colors::from_params(params, "color", palette)?,
+            background: params.get("background", Color::BLACK)?.into(),
+            density: params.number("density", 0.1, 0.0, 1.0)?,
+            decay: params.number("decay", 1.5, 0.05, 3600.0)?,
+            rng: Rng::from_time(),
+            sparks: Vec::new(),
+            last: None,
+        })
+    }
+}
+
+impl Effect for Twinkle {
+    fn render(&mut self, frame: &mut [Color16], t: Duration) {
+        let step = elapsed(&mut self.last, t, MAX_GAP);
+        self.sparks.resize(frame.len(), (Color16::default(), 0.0));
+
+        // Lit for `decay` seconds each, sparks starting at this rate keep
+        // `density` of the pixels lit.
+        let chance = 1.0 - (-self.density / self.decay * step).exp();
+        for (pixel, spark) in frame.iter_mut().zip(self.sparks.iter_mut()) {
+            spark.1 = (spark.1 - step / self.decay).max(0.0);
+            if spark.1 == 0.0 && self.rng.unit() < chance {
+                *spark = (self.colors.at(self.rng.unit()), 1.0);
+            }
+            // Squared so the fade looks even rather than lingering bright.
+            *pixel = self.background.lerp(spark.0, spark.1 * spark.1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn twinkles_light_and_fade() {
+        let mut params = Params::default();
+        params.set("density", "0.5");
+        params.set("decay", "1");
+        params.set("background", "#010101");
+        let mut twinkle = Twinkle::new(None, &params).unwrap();
+        twinkle.rng = Rng::new(7);
+        let mut frame = vec![Color16::default(); 400];
+        let background = Color16::from(Color::new(1, 1, 1));
+        let mut lit = 0;
+        for i in 0..200 {
+            twinkle.render(&mut frame, Duration::from_millis(i * 20));
+            lit = frame.iter().filter(|c| **c != background).count();
+        }
+        // About half are lit once it has settled.
+        assert!(lit > 120 && lit < 280, "{}", lit);
+
+        // With none starting, they all fade back to the background.
+        twinkle.density = 0.0;
+        for i in 200..260 {
+            twinkle.render(&mut frame, Duration::from_millis(i * 20));
+        }
+        assert!(frame.iter().all(|c| *c == background));
+    }
+}
@@ -0,0 +1,90 @@
+//! Colors filling the strip one pixel at a time, each wiping over the last.
+
+use super::{colors, Direction, Effect, Param, Params};
+use crate::color::Color16;
+use crate::palette::Palette;
+
+use std::time::Duration;
+
+pub const PARAMS: &[Param] = &[
+    Param {
+        name: "colors",
+        description: "colors to wipe in turn, separated by commas [default: steps along the palette or wheel]",
+    },
+    Param {
+        name: "rate",
+        description: "pixels filled each second [default: 30]",
+    },
+    Param {
+        name: "direction",
+        description: "forward or reverse [default: forward]",
+    },
+];
+
+/// Colors taken from the palette or wheel, evenly around it, when none are
+/// given.
+const STEPS: usize = 6;
+
+/// The strip filled with one color after another.
+pub struct Wipe {
+    colors: Vec<Color16>,
+    rate: f64,
+    direction: Direction,
+}
+
+impl Wipe {
+    pub fn new(palette: Option<Palette>, params: &Params) -> std::result::Result<Self, String> {
+        Ok(Wipe {
+            colors: colors::list(params, "colors", palette, STEPS)?,
+            rate: params.number("rate", 30.0, 0.0, 10000.0)?,
+            direction: params.get("direction", Direction::default())?,
+        })
+    }
+}
+
+impl Effect for Wipe {
+    fn render(&mut self, frame: &mut [Color16], t: Duration) {
+        let len = frame.len();
+        if len == 0 {
+            return;
+        }
+        let filled = (t.as_secs_f64() * self.rate) as usize;
+        let (wipe, edge) = (filled / len, filled % len);
+        let color = |n: usize| self.colors[n % self.colors.len()];
+        // The first color wipes over black.
+        let under = wipe.checked_sub(1).map_or(Color16::default(), color);
+        let over = color(wipe);
+        for (i, pixel) in frame.iter_mut().enumerate() {
+            *pixel = if self.direction.along(i, len) < edge {
+                over
+            } else {
+                under
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wipes_each_color_over_the_last() {
+        let mut params = Params::default();
+        params.set("colors", "red, rgb(0, 0, 255)");
+        params.set("rate", "1");
+        let mut wipe = Wipe::new(None, &params).unwrap();
+        let (red, blue) = (Color16::new(65535, 0, 0), Color16::new(0, 0, 65535));
+        let mut frame = vec![Color16::default(); 3];
+        wipe.render(&mut frame, Duration::from_secs(2));
+        assert_eq!(frame, [red, red, Color16::default()]);
+        wipe.render(&mut frame, Duration::from_secs(4));
+        assert_eq!(frame, [blue, red, red]);
+        // Back to red after blue.
+        wipe.render(&mut frame, Duration::from_secs(7));
+        assert_eq!(frame, [red, blue, blue]);
+
+        params.set("colors", "red, rgb(0, 0");
+        assert!(Wipe::new(None, &params).is_err());
+    }
+}
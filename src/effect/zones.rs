@@ -0,0 +1,82 @@
+//! Named stretches of the strip, each running an effect of its own over
+//! the effect drawn on the rest.
+
+use super::Effect;
+use crate::color::Color16;
+use crate::strip::PixelRange;
+
+use std::time::Duration;
+
+/// A stretch of pixels and what to show on it.
+pub struct Zone {
+    /// Pixels covered, counted along the animation.
+    pub leds: PixelRange,
+    pub effect: Box<dyn Effect>,
+    /// From 0 to 1.
+    pub brightness: f64,
+}
+
+/// An effect over the whole frame with zones drawn on top, each sized to
+/// its own pixels. Where zones overlap the later one shows.
+pub struct Zones {
+    base: Box<dyn Effect>,
+    own: Vec<Color16>,
+    zones: Vec<(Zone, Vec<Color16>)>,
+}
+
+impl Zones {
+    pub fn new(base: Box<dyn Effect>, zones: Vec<Zone>) -> Self {
+        Zones {
+            base,
+            own: Vec::new(),
+            zones: zones.into_iter().map(|zone| (zone, Vec::new())).collect(),
+        }
+    }
+}
+
+impl Effect for Zones {
+    fn render(&mut self, frame: &mut [Color16], t: Duration) {
+        // The base keeps a frame of its own so the zones drawn over it
+        // don't feed back into its next frame.
+        self.own.resize(frame.len(), Color16::default());
+        self.base.render(&mut self.own, t);
+        frame.copy_from_slice(&self.own);
+        for (zone, own) in self.zones.iter_mut() {
+            let end = (zone.leds.last + 1).min(frame.len());
+            if zone.leds.first >= end {
+                continue;
+            }
+            own.resize(end - zone.leds.first, Color16::default());
+            zone.effect.render(own, t);
+            for (pixel, color) in frame[zone.leds.first..end].iter_mut().zip(own.iter()) {
+                *pixel = color.scale(zone.brightness);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::effect::{Rainbow, Solid};
+
+    #[test]
+    fn zones_cover_their_pixels() {
+        let white = Color16::new(65535, 65535, 65535);
+        let zone = |first, last, brightness| Zone {
+            leds: PixelRange { first, last },
+            effect: Box::new(Solid::new(white)),
+            brightness,
+        };
+        let mut zones = Zones::new(
+            Box::new(Rainbow::new(None)),
+            vec![zone(1, 2, 0.5), zone(2, 9, 1.0)],
+        );
+        let mut frame = vec![Color16::default(); 4];
+        zones.render(&mut frame, Duration::from_secs(0));
+        assert_eq!(frame[0], Color16::new(65535, 0, 0));
+        assert_eq!(frame[1], Color16::new(32768, 32768, 32768));
+        // The later zone wins, and stops at the end of the frame.
+        assert_eq!(frame[2..], [white, white]);
+    }
+}
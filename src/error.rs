@@ -0,0 +1,44 @@
+use std::io;
+use thiserror::Error;
+
+/// Errors from setting up or writing to a strip.
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("unable to open {path}: {source}")]
+    Open {
+        path: String,
+        #[source]
+        source: io::Error,
+    },
+    #[error("invalid config {path}: {message}")]
+    Config { path: String, message: String },
+    #[error("invalid state file {path}: {message}")]
+    State { path: String, message: String },
+    #[error("{0} is required, on the command line or in the config file")]
+    Missing(&'static str),
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+    #[error("SPI bus error: {0}")]
+    Bus(String),
+    #[error("I2C error: {0}")]
+    I2c(String),
+    #[error("device error: {0}")]
+    Device(String),
+    #[error("invalid input: {0}")]
+    Input(String),
+    #[error("unable to set signal handler: {0}")]
+    Signal(#[source] io::Error),
+    #[error("{0}")]
+    Unsupported(&'static str),
+}
+
+impl Error {
+    pub(crate) fn open(path: &str, source: impl Into<io::Error>) -> Self {
+        Error::Open {
+            path: path.to_string(),
+            source: source.into(),
+        }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
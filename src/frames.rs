@@ -0,0 +1,112 @@
+//! Recording of rendered frames for other programs to read.
+//!
+//! Each record is a header of the capture time as little endian `u64`
+//! microseconds since the Unix epoch, the strip index as `u16` and the LED
+//! count as `u16`, followed by three bytes of red, green and blue per LED.
+
+use crate::error::{Error, Result};
+
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Writes frame records to a file or standard output.
+pub struct FrameWriter {
+    out: BufWriter<Box<dyn Write + Send>>,
+}
+
+impl FrameWriter {
+    pub fn new<W: Write + Send + 'static>(out: W) -> Self {
+        FrameWriter {
+            out: BufWriter::new(Box::new(out)),
+        }
+    }
+
+    /// Create the file at `path`, or write to standard output for "-".
+    pub fn create(path: &str) -> Result<Self> {
+        if path == "-" {
+            return Ok(FrameWriter::new(io::stdout()));
+        }
+        let file = File::create(path).map_err(|e| Error::open(path, e))?;
+        Ok(FrameWriter::new(file))
+    }
+
+    /// Append the frame shown on `strip` at `time`.
+    pub fn write(
+        &mut self,
+        time: SystemTime,
+        strip: u16,
+        pixels: &[(u8, u8, u8)],
+    ) -> io::Result<()> {
+        let micros = time
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_micros() as u64)
+            .unwrap_or(0);
+        let count = u16::try_from(pixels.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "frame too long"))?;
+        self.out.write_all(&micros.to_le_bytes())?;
+        self.out.write_all(&strip.to_le_bytes())?;
+        self.out.write_all(&count.to_le_bytes())?;
+        for (r, g, b) in pixels {
+            self.out.write_all(&[*r, *g, *b])?;
+        }
+        Ok(())
+    }
+
+    /// Push buffered records out, e.g. once every strip's frame is written.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    /// A buffer the test can read while the writer owns it.
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn record_layout() {
+        let out = Shared::default();
+        let mut writer = FrameWriter::new(out.clone());
+        let time = UNIX_EPOCH + Duration::from_micros(0x0102_0304_0506_0708);
+        writer
+            .write(time, 3, &[(1, 2, 3), (0xfd, 0xfe, 0xff)])
+            .unwrap();
+        writer.write(time, 0x0100, &[]).unwrap();
+        writer.flush().unwrap();
+        let bytes = out.0.lock().unwrap().clone();
+        let micros = [0x08, 0x07, 0x06, 0x05, 0x04, 0x03, 0x02, 0x01];
+        let mut wanted = micros.to_vec();
+        // Strip 3 with 2 LEDs, then their colors.
+        wanted.extend_from_slice(&[3, 0, 2, 0]);
+        wanted.extend_from_slice(&[1, 2, 3, 0xfd, 0xfe, 0xff]);
+        // An empty frame is only its header.
+        wanted.extend_from_slice(&micros);
+        wanted.extend_from_slice(&[0, 1, 0, 0]);
+        assert_eq!(bytes, wanted);
+
+        // A count that won't fit in the header is refused, writing nothing.
+        let e = writer.write(time, 0, &vec![(0, 0, 0); 65536]).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+        writer.write(time, 0, &vec![(0, 0, 0); 65535]).unwrap();
+        writer.flush().unwrap();
+        assert_eq!(out.0.lock().unwrap().len(), bytes.len() + 12 + 65535 * 3);
+    }
+}
@@ -0,0 +1,168 @@
+use crate::color::{Color, ColorRgbw};
+
+use std::fmt;
+use std::str::FromStr;
+
+/// How a channel's values are mapped to LED duty cycles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Curve {
+    /// A power law with the channel's gamma exponent.
+    #[default]
+    Gamma,
+    /// The CIE 1931 lightness (L*) curve, which is linear at the bottom and
+    /// so dims more smoothly there; the gamma exponent is ignored.
+    Cie,
+}
+
+impl FromStr for Curve {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "gamma" => Ok(Curve::Gamma),
+            "cie" => Ok(Curve::Cie),
+            _ => Err(format!("unknown curve {}, expected gamma or cie", s)),
+        }
+    }
+}
+
+impl fmt::Display for Curve {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Curve::Gamma => "gamma",
+            Curve::Cie => "cie",
+        })
+    }
+}
+
+pub struct GammaTable {
+    red_table: [u8; 256],
+    green_table: [u8; 256],
+    blue_table: [u8; 256],
+    white_table: [u8; 256],
+    /// Curve and exponent of red, green and blue, for 16-bit levels.
+    curves: [(Curve, f64); 3],
+}
+
+/// Corrected level, 0 to 1, for `level` from 0 to 1.
+fn apply_curve(curve: Curve, gamma: f64, level: f64) -> f64 {
+    match curve {
+        Curve::Gamma => level.powf(gamma),
+        // Luminance for CIE lightness L* of `level`.
+        Curve::Cie => {
+            let lightness = level * 100.0;
+            if lightness <= 8.0 {
+                lightness / 903.3
+            } else {
+                ((lightness + 16.0) / 116.0).powi(3)
+            }
+        }
+    }
+}
+
+fn build_curve(curve: Curve, gamma: f64) -> [u8; 256] {
+    let mut table = [0u8; 256];
+    for (i, v) in table.iter_mut().enumerate() {
+        *v = (apply_curve(curve, gamma, i as f64 / 255_f64) * 255.0 + 0.5) as u8;
+    }
+    table
+}
+
+fn build_table(gamma: f64) -> [u8; 256] {
+    build_curve(Curve::Gamma, gamma)
+}
+
+impl GammaTable {
+    pub fn new(red: f64, green: f64, blue: f64) -> Self {
+        GammaTable::with_curves(
+            (Curve::Gamma, red),
+            (Curve::Gamma, green),
+            (Curve::Gamma, blue),
+        )
+    }
+
+    /// Build a table choosing each channel's curve; white uses CIE only
+    /// when every color channel does.
+    pub fn with_curves(red: (Curve, f64), green: (Curve, f64), blue: (Curve, f64)) -> Self {
+        let white = if red.0 == Curve::Cie && green.0 == Curve::Cie && blue.0 == Curve::Cie {
+            build_curve(Curve::Cie, 1.0)
+        } else {
+            build_table((red.1 + green.1 + blue.1) / 3.0)
+        };
+        GammaTable {
+            red_table: build_curve(red.0, red.1),
+            green_table: build_curve(green.0, green.1),
+            blue_table: build_curve(blue.0, blue.1),
+            white_table: white,
+            curves: [red, green, blue],
+        }
+    }
+
+    /// Build a table for RGBW strips with its own exponent for the white LED.
+    ///
+    /// `new` uses the mean of the three color exponents for white.
+    pub fn new_rgbw(red: f64, green: f64, blue: f64, white: f64) -> Self {
+        GammaTable {
+            white_table: build_table(white),
+            ..GammaTable::new(red, green, blue)
+        }
+    }
+
+    pub fn correct_color(&self, red: u8, green: u8, blue: u8) -> Color {
+        Color::new(
+            self.red_table[red as usize],
+            self.green_table[green as usize],
+            self.blue_table[blue as usize],
+        )
+    }
+
+    /// Correct 16-bit red, green and blue, giving levels from 0 to 255
+    /// before rounding.
+    pub fn correct_exact(&self, red: u16, green: u16, blue: u16) -> [f32; 3] {
+        let correct = |(curve, gamma): (Curve, f64), level: u16| {
+            (apply_curve(curve, gamma, f64::from(level) / 65535.0) * 255.0) as f32
+        };
+        [
+            correct(self.curves[0], red),
+            correct(self.curves[1], green),
+            correct(self.curves[2], blue),
+        ]
+    }
+
+    pub fn correct_white(&self, white: u8) -> u8 {
+        self.white_table[white as usize]
+    }
+
+    pub fn correct_color_rgbw(&self, red: u8, green: u8, blue: u8, white: u8) -> ColorRgbw {
+        ColorRgbw::new(
+            self.red_table[red as usize],
+            self.green_table[green as usize],
+            self.blue_table[blue as usize],
+            self.white_table[white as usize],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn channels_use_their_own_exponent() {
+        let table = GammaTable::new(1.0, 2.0, 3.0);
+        let color = table.correct_color(128, 128, 128);
+        assert_eq!(color.red, 128);
+        assert_eq!(color.green, build_table(2.0)[128]);
+        assert_eq!(color.blue, build_table(3.0)[128]);
+        assert!(color.green > color.blue);
+    }
+
+    #[test]
+    fn cie_curve_is_gentler_at_the_bottom() {
+        let cie = build_curve(Curve::Cie, 1.0);
+        assert_eq!(cie[0], 0);
+        assert_eq!(cie[255], 255);
+        assert!(cie.windows(2).all(|w| w[0] <= w[1]));
+        assert!(cie[20] > build_table(2.2)[20]);
+    }
+}
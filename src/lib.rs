@@ -0,0 +1,35 @@
+//! Control for strips of addressable LEDs attached to an SPI bus.
+
+#[macro_use]
+extern crate log;
+
+pub mod calibration;
+pub mod camera;
+mod color;
+mod color_names;
+pub mod colorspace;
+pub mod config;
+pub mod crossfade;
+pub mod driver;
+pub mod easing;
+pub mod effect;
+mod error;
+pub mod frames;
+mod gamma;
+pub mod noise;
+pub mod palette;
+pub mod playlist;
+pub mod random;
+pub mod schedule;
+pub mod state;
+mod strip;
+pub mod tonemap;
+
+pub use calibration::Calibration;
+pub use color::{Color, Color16, ColorRgbw, WhitePoint};
+pub use driver::LedDriver;
+pub use easing::Easing;
+pub use error::{Error, Result};
+pub use gamma::{Curve, GammaTable};
+pub use palette::Palette;
+pub use strip::{Mirror, PixelRange, PowerBudget, Strip, WhiteExtraction};
@@ -1,221 +1,2580 @@
 #[macro_use]
 extern crate log;
 
-use chrono::{Datelike, Local, TimeZone, Utc};
+use chrono::{Local, NaiveDate};
 
 use structopt::StructOpt;
 
-use spidev::{SpiModeFlags, Spidev, SpidevOptions};
-use std::io;
-use std::io::prelude::*;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::terminal;
+use led_strip::camera::{self, Camera, Image};
+use led_strip::config::{
+    self, Config, Layout, Preset, StripConfig, ZoneConfig, DEFAULT_EFFECT,
+    DEFAULT_TRANSITION_SECONDS,
+};
+use led_strip::crossfade::Crossfade;
+use led_strip::driver::{Backend, Chip, ColorOrder};
+use led_strip::easing::Easing;
+use led_strip::effect::{self, parse_param, Effect, Engine, Params, Rainbow, Solid, Zone, Zones};
+use led_strip::frames::FrameWriter;
+use led_strip::palette::{self, Cycler, Palette, PaletteCycle};
+use led_strip::playlist::{Entry, Player, Playlist};
+use led_strip::schedule::{self, Schedule};
+use led_strip::state::{Show, State, DEFAULT_STATE_FILE};
+use led_strip::{
+    Calibration, Color, Color16, Curve, Error, GammaTable, Mirror, PixelRange, Result, Strip,
+    WhiteExtraction, WhitePoint,
+};
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fs;
+use std::io::{self, BufRead, Read, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::signal::unix::{signal, Signal, SignalKind};
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tokio::sync::watch;
+use tokio::time::MissedTickBehavior;
 
-fn create_spi() -> io::Result<Spidev> {
-    let mut spi = Spidev::open("/dev/spidev0.0")?;
-    let options = SpidevOptions::new()
-        .bits_per_word(8)
-        .max_speed_hz(15_000_000)
-        .mode(SpiModeFlags::SPI_MODE_0)
-        .build();
-    spi.configure(&options)?;
-    Ok(spi)
+/// One frame of RGB values as an effect draws it, before brightness and
+/// gamma correction.
+type Frame = Vec<Color16>;
+
+/// Time between frames.
+const FRAME_PERIOD: Duration = Duration::from_millis(16);
+
+/// How often a running show's state is checked and written if it changed,
+/// picking up a write that failed.
+const STATE_PERIOD: Duration = Duration::from_secs(60);
+
+/// Frames that may wait for the output thread before new ones are dropped.
+const FRAME_QUEUE: usize = 2;
+
+/// Scale the blue of every pixel by `blue`, for night mode.
+fn night_filter(frame: &mut [Color16], blue: f64) {
+    if blue < 1.0 {
+        frame
+            .iter_mut()
+            .for_each(|c| c.blue = (f64::from(c.blue) * blue).round() as u16);
+    }
+}
+
+#[derive(Debug, StructOpt)]
+#[structopt(
+    name = "blink",
+    about = "Drive an addressable LED strip: P9813, APA102, WS2812 and more."
+)]
+struct Opt {
+    /// TOML file with strip, location and schedule settings; command line
+    /// options override it. SIGHUP rereads it without restarting, though
+    /// changes to a strip's driver, chip or device need a restart.
+    #[structopt(long = "config", parse(from_os_str))]
+    config: Option<PathBuf>,
+    /// Latitude used for sunrise calculations.
+    #[structopt(long = "latitude")]
+    lat: Option<f64>,
+    /// Longitude used for sunrise calculations.
+    #[structopt(long = "longitude")]
+    lon: Option<f64>,
+    /// LED chip on the strip: p9813, apa102, ws2812, lpd8806, sk6812 or
+    /// pca9685 [default: p9813].
+    #[structopt(long = "chip")]
+    chip: Option<Chip>,
+    /// Output backend: spidev, ftdi for an FT232H/FT2232H USB adapter, mock,
+    /// sim or terminal [default: spidev].
+    #[structopt(long = "driver")]
+    driver: Option<Backend>,
+    /// SPI device the strip is attached to [default: /dev/spidev0.0].
+    #[structopt(long = "spi-dev")]
+    spi_dev: Option<String>,
+    /// SPI clock in Hz; defaults to the usual rate for the chip. Slower
+    /// clocks help with level shifters and long cable runs.
+    #[structopt(long = "spi-speed-hz")]
+    spi_speed_hz: Option<u32>,
+    /// Largest single SPI transfer in bytes; defaults to the spidev module's
+    /// bufsiz. Longer frames are split into several transfers.
+    #[structopt(long = "spi-chunk-size")]
+    spi_chunk_size: Option<usize>,
+    /// USB description of the FTDI adapter used by the ftdi driver
+    /// [default: Single RS232-HS].
+    #[structopt(long = "ftdi-device", conflicts_with = "spi_dev")]
+    ftdi_device: Option<String>,
+    /// Number of LEDs on the strip [default: 76].
+    #[structopt(long = "num-leds")]
+    num_leds: Option<usize>,
+    /// Channels fed to the chip's red, green and blue outputs, e.g. rbg for a
+    /// strip with green and blue swapped [default: rgb].
+    #[structopt(long = "color-order")]
+    color_order: Option<ColorOrder>,
+    /// Gamma correction exponent for all three channels [default: 2.2].
+    #[structopt(long = "gamma")]
+    gamma: Option<f64>,
+    /// Gamma exponent for the red channel, overriding --gamma.
+    #[structopt(long = "gamma-r")]
+    gamma_red: Option<f64>,
+    /// Gamma exponent for the green channel, overriding --gamma.
+    #[structopt(long = "gamma-g")]
+    gamma_green: Option<f64>,
+    /// Gamma exponent for the blue channel, overriding --gamma.
+    #[structopt(long = "gamma-b")]
+    gamma_blue: Option<f64>,
+    /// Lightness curve: gamma for a power law with the gamma exponents, or
+    /// cie for CIE 1931 L*, which dims more smoothly near black
+    /// [default: gamma].
+    #[structopt(long = "curve")]
+    curve: Option<Curve>,
+    /// Lightness curve for the red channel, overriding --curve.
+    #[structopt(long = "curve-r")]
+    curve_red: Option<Curve>,
+    /// Lightness curve for the green channel, overriding --curve.
+    #[structopt(long = "curve-g")]
+    curve_green: Option<Curve>,
+    /// Lightness curve for the blue channel, overriding --curve.
+    #[structopt(long = "curve-b")]
+    curve_blue: Option<Curve>,
+    /// Cap on every channel after gamma correction, 0 to 255, whatever the
+    /// effect or color asks for.
+    #[structopt(long = "max-brightness")]
+    max_brightness: Option<u8>,
+    /// Current level from 0 to 31 that APA102 and SK9822 chips send with
+    /// every LED; lower levels dim by current, leaving every PWM step for
+    /// dim colors [default: 31].
+    #[structopt(long = "global-brightness")]
+    global_brightness: Option<u8>,
+    /// Color temperature to balance all output to, e.g. 2700K to match warm
+    /// household lighting; 6500K leaves colors unchanged.
+    #[structopt(long = "white-point")]
+    white_point: Option<WhitePoint>,
+    /// On RGBW strips, move the white common to red, green and blue onto
+    /// the white LED: off, min for all of it, or half [default: off].
+    #[structopt(long = "white-extraction")]
+    white_extraction: Option<WhiteExtraction>,
+    /// Color temperature of an RGBW strip's white LED, e.g. 3000K, so
+    /// extraction replaces the white it actually gives [default: 6500K].
+    #[structopt(long = "white-led")]
+    white_led: Option<WhitePoint>,
+    /// Level from 0 to 1 above which bright colors are compressed smoothly,
+    /// keeping their hue, instead of clipping each channel at full. 1 only
+    /// scales down colors that overdrive [default: 1].
+    #[structopt(long = "soft-knee")]
+    soft_knee: Option<f64>,
+    /// Current the power supply can give, in mA; frames whose estimated
+    /// draw is higher are dimmed to fit.
+    #[structopt(long = "max-milliamps")]
+    max_milliamps: Option<f64>,
+    /// Current one color channel of one LED draws at full brightness, for
+    /// --max-milliamps [default: 20].
+    #[structopt(long = "milliamps-per-channel")]
+    milliamps_per_channel: Option<f64>,
+    /// Map index 0 to the far end of the strip, so effects run the other way.
+    #[structopt(long = "reverse")]
+    reverse: bool,
+    /// Move index 0 this many LEDs along the strip, wrapping at the end, for
+    /// strips wired in a loop. Applied before --reverse.
+    #[structopt(long = "offset")]
+    offset: Option<usize>,
+    /// Reflect half the strip onto the other half: "ends" runs effects in
+    /// from both ends, "center" out from the middle [default: off].
+    #[structopt(long = "mirror")]
+    mirror: Option<Mirror>,
+    /// Palette for the effect to draw from instead of the full color wheel:
+    /// heat, ocean, forest, party, lava, halloween, diwali, valentines or one
+    /// loaded from a file.
+    #[structopt(long = "palette")]
+    palette: Option<String>,
+    /// Load a .json, .gpl or .cpt palette, named after the file without its
+    /// extension. May be repeated.
+    #[structopt(long = "palette-file", number_of_values = 1, parse(from_os_str))]
+    palette_files: Vec<PathBuf>,
+    /// Keep a range of LEDs, such as "10-20" or "7", always off. Positions
+    /// count from the data input. May be repeated.
+    #[structopt(long = "blackout", number_of_values = 1)]
+    blackout: Vec<PixelRange>,
+    /// Dither each channel from frame to frame so levels between whole
+    /// steps show on average, smoothing the dim ends of the sunrise and
+    /// sunset ramps.
+    #[structopt(long = "dither")]
+    dither: bool,
+    /// TOML file of per-LED red, green and blue scale factors and dead
+    /// LEDs, applied as frames are shown to even out mismatched LEDs.
+    #[structopt(long = "calibration", parse(from_os_str))]
+    calibration: Option<PathBuf>,
+    /// Mirror every frame to the terminal as a row of colored cells.
+    #[structopt(long = "preview")]
+    preview: bool,
+    /// Record every frame rendered, at full brightness, to this file, or to
+    /// stdout for "-", as a little endian u64 timestamp in microseconds
+    /// since the Unix epoch, u16 strip index and u16 LED count followed by
+    /// RGB bytes.
+    #[structopt(long = "tee")]
+    tee: Option<String>,
+    /// Add a strip as comma separated key=value pairs, e.g.
+    /// "path=/dev/spidev0.1,chip=apa102,leds=120". Keys are driver, chip,
+    /// path, speed, chunk, leds, order, gamma, gamma_r, gamma_g, gamma_b,
+    /// curve, curve_r, curve_g, curve_b, max_brightness, global_brightness,
+    /// white_point, white_extraction, white_led, soft_knee, max_milliamps,
+    /// milliamps_per_channel, reverse, offset, mirror, blackout (ranges
+    /// separated by ';'), dither, calibration and preview; missing keys take
+    /// the global options. May be repeated, and replaces any strips in the
+    /// config file.
+    #[structopt(long = "strip", number_of_values = 1)]
+    strips: Vec<StripConfig>,
+    /// Run a zone, a stretch of LEDs with its own effect, as its name and
+    /// comma separated key=value pairs, e.g. "window:leds=0-29,effect=rainbow".
+    /// Keys are leds, effect, color, palette and brightness (0 to 1); keys
+    /// given for a zone in the config file fill in the rest. May be
+    /// repeated.
+    #[structopt(
+        long = "zone",
+        number_of_values = 1,
+        parse(try_from_str = "parse_zone")
+    )]
+    zones: Vec<(String, ZoneConfig)>,
+    /// With several strips, "same" shows one animation on all of them while
+    /// "independent" runs a separate animation sized to each strip
+    /// [default: same].
+    #[structopt(long = "layout")]
+    layout: Option<Layout>,
+    /// Where the last color or effect shown is kept for "on".
+    #[structopt(
+        long = "state-file",
+        parse(from_os_str),
+        raw(default_value = "DEFAULT_STATE_FILE")
+    )]
+    state_file: PathBuf,
+    /// Seconds to fade over when the effect, color or power changes; 0
+    /// cuts straight over [default: 1].
+    #[structopt(long = "transition")]
+    transition: Option<f64>,
+    /// Run every effect this many times as fast, e.g. 0.5 for calm or 3 for
+    /// energetic [default: 1].
+    #[structopt(long = "speed")]
+    speed: Option<f64>,
+    /// Set an effect parameter, e.g. "density=0.3", read by the effects
+    /// that take it as "effects" lists; "palette=ocean" picks the palette.
+    /// May be repeated, and goes over parameters in the config file.
+    #[structopt(
+        long = "param",
+        number_of_values = 1,
+        parse(try_from_str = "parse_param")
+    )]
+    params: Vec<(String, String)>,
+    #[structopt(subcommand)]
+    cmd: Option<Command>,
+}
+
+#[derive(Debug, StructOpt)]
+enum Command {
+    /// Show what was shown before the last restart, or run the configured
+    /// effect until interrupted; the default without a command.
+    #[structopt(name = "run")]
+    Run,
+    /// Fill the strips with one color and exit.
+    #[structopt(name = "set")]
+    Set {
+        /// Color as "#rgb", "#rrggbb", "rgb(255, 136, 0)" or a CSS name
+        /// such as coral.
+        color: Color,
+    },
+    /// Set one LED, leaving the others as the last "set" or "pixel"
+    /// command left them.
+    #[structopt(name = "pixel")]
+    Pixel {
+        /// Index of the LED from the start of the strip.
+        index: usize,
+        /// Color as for "set".
+        color: Color,
+    },
+    /// Show solid red, green, blue and white, then a dot running along the
+    /// strips, to check wiring, color order and the power supply.
+    #[structopt(name = "test")]
+    Test {
+        /// How long each solid color is shown, in milliseconds.
+        #[structopt(long = "hold-ms", default_value = "2000")]
+        hold_ms: u64,
+    },
+    /// Flash one LED, or show every LED's index in binary, until
+    /// interrupted so positions can be matched to indices.
+    #[structopt(name = "identify")]
+    Identify {
+        /// Index of the LED to flash.
+        #[structopt(required_unless = "binary")]
+        index: Option<usize>,
+        /// Show each LED's index one bit per step, least significant first:
+        /// green for a one and red for a zero, after a blue frame marking
+        /// the start.
+        #[structopt(long = "binary")]
+        binary: bool,
+        /// Time each flash or bit is shown, in milliseconds.
+        #[structopt(long = "step-ms", default_value = "500")]
+        step_ms: u64,
+    },
+    /// Adjust each channel's gamma against gray ramps with the arrow keys
+    /// and save the result to the config file.
+    #[structopt(name = "calibrate")]
+    Calibrate {
+        /// Which strip to calibrate when several are configured.
+        #[structopt(long = "index", default_value = "0")]
+        index: usize,
+    },
+    /// Find each LED with a V4L2 webcam pointed at the strip, measure its
+    /// red, green and blue, and save scale factors that even them out to
+    /// the strip's calibration file. Lock the camera's exposure and white
+    /// balance first.
+    #[structopt(name = "camera-calibrate")]
+    CameraCalibrate {
+        /// Which strip to calibrate when several are configured.
+        #[structopt(long = "index", default_value = "0")]
+        index: usize,
+        /// Video device of the camera.
+        #[structopt(long = "device", default_value = "/dev/video0")]
+        device: String,
+        /// Level LEDs are lit at, low enough not to saturate the camera.
+        #[structopt(long = "level", default_value = "128")]
+        level: u8,
+        /// Time the LEDs and camera are given to settle before each
+        /// capture, in milliseconds.
+        #[structopt(long = "settle-ms", default_value = "300")]
+        settle_ms: u64,
+        /// Only even out the LEDs, leaving the balance of red, green and
+        /// blue alone.
+        #[structopt(long = "no-balance")]
+        no_balance: bool,
+        /// File to save to instead of the strip's calibration file.
+        #[structopt(long = "out", parse(from_os_str))]
+        out: Option<PathBuf>,
+    },
+    /// Inspect the sunrise and sunset brightness schedule.
+    #[structopt(name = "schedule")]
+    Schedule {
+        #[structopt(subcommand)]
+        cmd: ScheduleCommand,
+    },
+    /// Render an effect into an animated GIF instead of driving the strips,
+    /// one row per strip.
+    #[structopt(name = "render")]
+    Render {
+        #[structopt(parse(try_from_str = "parse_effect"))]
+        effect: String,
+        /// Length of the animation.
+        #[structopt(long = "seconds", default_value = "10")]
+        seconds: f64,
+        /// GIF file to write.
+        #[structopt(long = "out", parse(from_os_str))]
+        out: PathBuf,
+    },
+    /// Show RGB frames read from stdin, with gamma correction and pacing,
+    /// until stdin closes. A frame holds three bytes for each LED of the
+    /// longest strip.
+    #[structopt(name = "pipe")]
+    Pipe {
+        /// Frame encoding: binary, or hex with one frame per line.
+        #[structopt(long = "format", default_value = "binary")]
+        format: FrameFormat,
+        /// Most frames shown per second.
+        #[structopt(long = "fps", default_value = "60")]
+        fps: f64,
+        /// Blend from each frame to the next at the full output rate, so
+        /// frames sent slower than that move smoothly instead of stepping.
+        /// Each blend takes as long as the gap between frames, which delays
+        /// them all by one frame.
+        #[structopt(long = "interpolate")]
+        interpolate: bool,
+    },
+    /// Work with the configuration.
+    #[structopt(name = "config")]
+    Config {
+        #[structopt(subcommand)]
+        cmd: ConfigCommand,
+    },
+    /// Show the last color or effect again after "off"; runs until
+    /// interrupted when that was an effect.
+    #[structopt(name = "on")]
+    On,
+    /// Blank the strips and exit, remembering what they showed.
+    #[structopt(name = "off")]
+    Off,
+    /// Run the named effect until interrupted. Effects stack as layers,
+    /// bottom first, each blended onto those below with alpha, add,
+    /// multiply or overlay at an opacity, and may run at their own speed:
+    /// "rainbow 0.5x + twinkle add 50%".
+    #[structopt(name = "effect")]
+    Effect {
+        #[structopt(parse(try_from_str = "parse_effect"))]
+        name: String,
+    },
+    /// Fill the strips with a gradient through the given colors and hold
+    /// it until interrupted: "#ff0000 #ffaa00 #ffffff".
+    #[structopt(name = "gradient")]
+    Gradient {
+        /// Two or more colors, first LED to last, separated by spaces or
+        /// commas.
+        #[structopt(parse(try_from_str = "parse_stops"))]
+        colors: String,
+        /// Turns a minute to move the gradient along the strip and back.
+        #[structopt(long = "rotate")]
+        rotate: Option<f64>,
+    },
+    /// Run with a preset, from the config file or built in, until
+    /// interrupted.
+    #[structopt(name = "preset")]
+    Preset {
+        /// Name of a [preset.<name>] table, or of a built-in preset:
+        /// christmas, halloween, hanukkah, diwali or valentines.
+        name: String,
+    },
+    /// Save what the strips show as a named scene, or show one again.
+    #[structopt(name = "scene")]
+    Scene {
+        #[structopt(subcommand)]
+        cmd: SceneCommand,
+    },
+    /// Print the settings in use and today's schedule.
+    #[structopt(name = "status")]
+    Status,
+    /// List the effects that can be run.
+    #[structopt(name = "effects")]
+    Effects,
+}
+
+#[derive(Debug, StructOpt)]
+enum ConfigCommand {
+    /// Check the settings, print the effective ones and exit non-zero on
+    /// problems.
+    #[structopt(name = "check")]
+    Check,
+}
+
+#[derive(Debug, StructOpt)]
+enum SceneCommand {
+    /// Save the color, pixels or effect last shown under a name.
+    #[structopt(name = "save")]
+    Save { name: String },
+    /// Show a saved scene; runs until interrupted when it is an effect.
+    #[structopt(name = "load")]
+    Load { name: String },
+}
+
+#[derive(Debug, StructOpt)]
+enum ScheduleCommand {
+    /// Print the brightness across a day as a bar chart.
+    #[structopt(name = "preview")]
+    Preview {
+        /// Day to show as YYYY-MM-DD; defaults to today.
+        #[structopt(long = "date")]
+        date: Option<NaiveDate>,
+        /// Minutes between rows.
+        #[structopt(long = "step-minutes", default_value = "15")]
+        step_minutes: i64,
+    },
 }
 
-#[derive(Debug)]
-#[repr(C)]
-struct Color {
-    flag: u8,
-    blue: u8,
-    green: u8,
-    red: u8,
+/// How frames are encoded on stdin for `pipe`.
+#[derive(Debug, Clone, Copy)]
+enum FrameFormat {
+    Binary,
+    Hex,
 }
 
-impl Color {
-    fn new(red: u8, green: u8, blue: u8) -> Self {
-        let mut flag = (red & 0xC0) >> 6;
-        flag |= (green & 0xC0) >> 4;
-        flag |= (blue & 0xC0) >> 2;
-        flag = !flag;
+impl FromStr for FrameFormat {
+    type Err = String;
 
-        Color {
-            flag,
-            blue,
-            green,
-            red,
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "binary" => Ok(FrameFormat::Binary),
+            "hex" => Ok(FrameFormat::Hex),
+            _ => Err(format!("unknown frame format: {}", s)),
         }
     }
 }
 
-struct GammaTable {
-    red_table: [u8; 256],
-    green_table: [u8; 256],
-    blue_table: [u8; 256],
+/// Read gradient stops separated by spaces or commas outside parentheses,
+/// as a list the gradient effect takes.
+fn parse_stops(s: &str) -> std::result::Result<String, String> {
+    let mut stops = vec![String::new()];
+    let mut depth = 0;
+    for c in s.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ => {}
+        }
+        if depth == 0 && (c == ',' || c.is_whitespace()) {
+            stops.push(String::new());
+        } else if let Some(stop) = stops.last_mut() {
+            stop.push(c);
+        }
+    }
+    let colors = stops
+        .iter()
+        .filter(|stop| !stop.is_empty())
+        .map(|stop| stop.parse::<Color>().map(|color| color.to_string()))
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    if colors.len() < 2 {
+        return Err("a gradient needs two or more colors".to_string());
+    }
+    Ok(colors.join(","))
 }
 
-impl GammaTable {
-    fn new(red: f64, green: f64, blue: f64) -> Self {
-        let mut gamma_table = GammaTable {
-            red_table: [0u8; 256],
-            green_table: [0u8; 256],
-            blue_table: [0u8; 256],
+/// The effect the gradient command runs.
+fn gradient_effect(colors: &str, rotate: Option<f64>) -> String {
+    match rotate {
+        Some(rotate) => format!("gradient colors={} rotate={}", colors, rotate),
+        None => format!("gradient colors={}", colors),
+    }
+}
+
+fn parse_effect(s: &str) -> std::result::Result<String, String> {
+    effect::check(s).map(|_| s.to_string())
+}
+
+fn parse_zone(s: &str) -> std::result::Result<(String, ZoneConfig), String> {
+    let (name, keys) = s
+        .split_once(':')
+        .ok_or_else(|| format!("expected name:key=value,..., got {}", s))?;
+    Ok((name.trim().to_string(), keys.parse()?))
+}
+
+impl Opt {
+    /// The config file for error messages.
+    fn config_name(&self) -> String {
+        self.config
+            .as_ref()
+            .map_or_else(|| "(none)".to_string(), |p| p.display().to_string())
+    }
+
+    /// Strip settings given by the global options.
+    fn strip_defaults(&self) -> StripConfig {
+        StripConfig {
+            driver: self.driver,
+            chip: self.chip,
+            path: self.spi_dev.clone().or_else(|| self.ftdi_device.clone()),
+            spi_speed_hz: self.spi_speed_hz,
+            spi_chunk_size: self.spi_chunk_size,
+            num_leds: self.num_leds,
+            color_order: self.color_order,
+            gamma: self.gamma,
+            gamma_red: self.gamma_red,
+            gamma_green: self.gamma_green,
+            gamma_blue: self.gamma_blue,
+            curve: self.curve,
+            curve_red: self.curve_red,
+            curve_green: self.curve_green,
+            curve_blue: self.curve_blue,
+            max_brightness: self.max_brightness,
+            global_brightness: self.global_brightness,
+            white_point: self.white_point,
+            white_extraction: self.white_extraction,
+            white_led: self.white_led,
+            soft_knee: self.soft_knee,
+            max_milliamps: self.max_milliamps,
+            milliamps_per_channel: self.milliamps_per_channel,
+            reverse: if self.reverse { Some(true) } else { None },
+            offset: self.offset,
+            mirror: self.mirror,
+            blackout: if self.blackout.is_empty() {
+                None
+            } else {
+                Some(self.blackout.clone())
+            },
+            dither: if self.dither { Some(true) } else { None },
+            calibration: self.calibration.clone(),
+            preview: if self.preview { Some(true) } else { None },
+        }
+    }
+}
+
+/// The command line merged over the config file.
+struct Settings {
+    lat: Option<f64>,
+    lon: Option<f64>,
+    effect: String,
+    /// Effects run in turn instead of `effect`.
+    playlist: Option<Playlist>,
+    palette: Option<String>,
+    /// Palettes moved through in place of `palette`.
+    palette_cycle: Option<PaletteCycle>,
+    /// Palettes loaded from files, by name.
+    palettes: BTreeMap<String, Palette>,
+    layout: Layout,
+    /// Seconds changes fade over.
+    transition: f64,
+    /// Multiplies how fast the effects run.
+    speed: f64,
+    /// Parameters for the effects, less the palette.
+    params: Params,
+    schedule: Schedule,
+    strips: Vec<StripConfig>,
+    /// Zones by name, drawn over the effect.
+    zones: Vec<(String, ZoneConfig)>,
+}
+
+impl Settings {
+    /// Read the config file, if any, and apply the command line over it.
+    fn load(opt: &Opt) -> Result<Settings> {
+        let mut config = match &opt.config {
+            Some(path) => Config::load(path)?,
+            None => Config::default(),
+        };
+        // Files named in the config are relative to it.
+        let config_dir = opt.config.as_deref().and_then(Path::parent);
+        let resolve =
+            |path: &PathBuf| config_dir.map_or_else(|| path.clone(), |dir| dir.join(path));
+        for strip in config.strips.iter_mut().chain(Some(&mut config.strip)) {
+            strip.calibration = strip.calibration.as_ref().map(resolve);
+        }
+        let defaults = opt.strip_defaults().or(&config.strip);
+        let specs = if !opt.strips.is_empty() {
+            opt.strips.clone()
+        } else if !config.strips.is_empty() {
+            config.strips
+        } else {
+            vec![StripConfig::default()]
         };
-        for i in 0..256 {
-            gamma_table.red_table[i] = (((i as f64 / 255_f64).powf(red)) * 255.0 + 0.5) as u8;
-            gamma_table.green_table[i] = (((i as f64 / 255_f64).powf(blue)) * 255.0 + 0.5) as u8;
-            gamma_table.blue_table[i] = (((i as f64 / 255_f64).powf(green)) * 255.0 + 0.5) as u8;
+        let preset = match &opt.cmd {
+            Some(Command::Preset { name }) => config
+                .preset
+                .get(name)
+                .cloned()
+                .or_else(|| Preset::builtin(name))
+                .ok_or_else(|| Error::Config {
+                    path: opt.config_name(),
+                    message: format!("no preset named {}", name),
+                })?,
+            _ => Preset::default(),
+        };
+        let effect = match &opt.cmd {
+            Some(Command::Effect { name }) => Some(name.clone()),
+            _ => preset.effect,
+        };
+        let files = config
+            .palette_files
+            .iter()
+            .map(resolve)
+            .chain(opt.palette_files.iter().cloned());
+        let mut palettes: BTreeMap<String, Palette> = config
+            .palettes
+            .iter()
+            .map(|(name, colors)| (name.clone(), Palette::even(colors)))
+            .collect();
+        for path in files {
+            let name = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            palettes.insert(name, Palette::load(&path)?);
         }
-        gamma_table
+        let mut params: Params = opt.params.iter().cloned().collect();
+        let mut preset_params = preset.params;
+        // A palette given as a parameter ranks with other parameters from
+        // the same place.
+        let chosen = opt
+            .palette
+            .clone()
+            .or(params.take("palette"))
+            .or(preset_params.take("palette"))
+            .or(preset.palette);
+        // A cycle stands in for the config file's palette, starting on its
+        // first, but not for one chosen over it.
+        let palette_cycle = config.palette_cycle.filter(|_| chosen.is_none());
+        let palette = chosen
+            .or_else(|| palette_cycle.as_ref().map(|c| c.palettes[0].clone()))
+            .or(config.params.take("palette"))
+            .or(config.palette);
+        params = params.or(&preset_params.or(&config.params));
+        let cycled = palette_cycle.iter().flat_map(|c| &c.palettes);
+        for name in palette.iter().chain(cycled) {
+            if !palettes.contains_key(name) && Palette::builtin(name).is_none() {
+                let known: Vec<&str> = palette::BUILTIN
+                    .iter()
+                    .copied()
+                    .chain(palettes.keys().map(String::as_str))
+                    .collect();
+                return Err(Error::Config {
+                    path: opt.config_name(),
+                    message: format!(
+                        "unknown palette {}, expected one of {}",
+                        name,
+                        known.join(", ")
+                    ),
+                });
+            }
+        }
+        let mut zones = config.zones;
+        for (name, zone) in &opt.zones {
+            let base = zones.get(name).cloned().unwrap_or_default();
+            zones.insert(name.clone(), zone.clone().or(&base));
+        }
+        // The preset's brightness covers every strip, but not --max-brightness.
+        let max_brightness = opt.max_brightness.or(preset.max_brightness);
+        Ok(Settings {
+            lat: opt.lat.or(config.latitude),
+            lon: opt.lon.or(config.longitude),
+            playlist: config.playlist.filter(|_| effect.is_none()),
+            palette_cycle,
+            effect: effect
+                .or(config.effect)
+                .unwrap_or_else(|| DEFAULT_EFFECT.to_string()),
+            palette,
+            palettes,
+            layout: opt.layout.or(config.layout).unwrap_or_default(),
+            transition: opt
+                .transition
+                .or(config.transition_seconds)
+                .unwrap_or(DEFAULT_TRANSITION_SECONDS),
+            speed: opt.speed.or(config.speed).unwrap_or(1.0),
+            params,
+            schedule: config.schedule,
+            strips: specs
+                .into_iter()
+                .map(|spec| {
+                    let mut strip = spec.or(&defaults);
+                    strip.max_brightness = max_brightness.or(strip.max_brightness);
+                    strip
+                })
+                .collect(),
+            zones: zones.into_iter().collect(),
+        })
     }
 
-    fn correct_color(&self, red: u8, green: u8, blue: u8) -> Color {
-        Color::new(
-            self.red_table[red as usize],
-            self.green_table[green as usize],
-            self.blue_table[blue as usize],
-        )
+    /// The palette effects draw from, if one is chosen.
+    fn palette(&self) -> Option<Palette> {
+        self.palette_named(self.palette.as_deref()?)
+    }
+
+    fn palette_named(&self, name: &str) -> Option<Palette> {
+        self.palettes
+            .get(name)
+            .cloned()
+            .or_else(|| Palette::builtin(name))
+    }
+
+    /// Effect `name` set up by the parameters, falling back to the rainbow
+    /// for names that don't parse or bad parameters; `config check`
+    /// reports them.
+    fn create(&self, name: &str, palette: Option<Palette>) -> Box<dyn Effect> {
+        effect::create(name, palette.clone(), &self.params).unwrap_or_else(|e| {
+            warn!("running the rainbow: {}", e);
+            Box::new(Rainbow::new(palette))
+        })
+    }
+
+    /// Names of the effects that may run: the effect or the playlist's,
+    /// and the zones'.
+    fn effect_names(&self) -> Vec<&str> {
+        let main: Vec<&str> = match &self.playlist {
+            Some(playlist) => playlist.effects.iter().map(|e| e.effect.as_str()).collect(),
+            None => vec![self.effect.as_str()],
+        };
+        let zones = self.zones.iter().filter_map(|(_, zone)| match zone {
+            ZoneConfig { color: Some(_), .. } => None,
+            ZoneConfig {
+                effect: Some(effect),
+                ..
+            } => Some(vec![effect.as_str()]),
+            _ => Some(main.clone()),
+        });
+        let mut names: Vec<&str> = main.iter().copied().chain(zones.flatten()).collect();
+        names.sort_unstable();
+        names.dedup();
+        names
+    }
+
+    /// Effect `name` for each animation of `lengths` pixels, drawing from
+    /// the palette, with the zones drawn over it.
+    fn effects(&self, name: &str, lengths: &[usize]) -> Vec<(Box<dyn Effect>, usize)> {
+        lengths
+            .iter()
+            .map(|len| {
+                let effect = self.create(name, self.palette());
+                if self.zones.is_empty() {
+                    return (effect, *len);
+                }
+                let zones = self
+                    .zones
+                    .iter()
+                    .filter_map(|(_, zone)| {
+                        let palette = match &zone.palette {
+                            Some(name) => self.palette_named(name),
+                            None => self.palette(),
+                        };
+                        let effect: Box<dyn Effect> = match (&zone.color, &zone.effect) {
+                            (Some(color), _) => Box::new(Solid::new((*color).into())),
+                            (None, Some(effect)) => self.create(effect, palette),
+                            (None, None) => self.create(name, palette),
+                        };
+                        Some(Zone {
+                            leds: zone.leds?,
+                            effect,
+                            brightness: zone.brightness().clamp(0.0, 1.0),
+                        })
+                    })
+                    .collect();
+                (Box::new(Zones::new(effect, zones)) as Box<dyn Effect>, *len)
+            })
+            .collect()
+    }
+
+    /// A fade over the transition time; bad times, which `config check`
+    /// reports, cut straight over.
+    fn crossfade(&self) -> Crossfade {
+        Crossfade::new(Duration::try_from_secs_f64(self.transition).unwrap_or_default())
+    }
+
+    /// Pixels on the longest strip.
+    fn longest(&self) -> usize {
+        self.strips
+            .iter()
+            .map(StripConfig::pixels)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Latitude and longitude, which the sunrise schedule needs.
+    fn location(&self) -> Result<(f64, f64)> {
+        let lat = self.lat.ok_or(Error::Missing("--latitude"))?;
+        let lon = self.lon.ok_or(Error::Missing("--longitude"))?;
+        Ok((lat, lon))
     }
 }
 
-fn send_pixels(spi: &mut Spidev, pixels: &[Color]) -> io::Result<()> {
-    let bytes: &[u8] = unsafe {
-        ::std::slice::from_raw_parts(
-            (pixels.as_ptr()) as *const u8,
-            pixels.len() * ::std::mem::size_of::<Color>(),
-        )
+/// Sent by the ticker to a strip's output thread.
+enum Message {
+    /// A frame and the brightness, 0 to 1, to show it at.
+    Frame(Frame, f64),
+    /// Settings changed by a reload that don't need the driver reopened.
+    Reconfigure(Box<StripConfig>),
+    /// Fade from the frame shown now to the frames that follow.
+    Crossfade,
+}
+
+/// The ticker's end of a strip's output thread.
+struct Output {
+    num_leds: usize,
+    messages: mpsc::Sender<Message>,
+    dropped_frames: u64,
+}
+
+/// Attempts made to blank the strip on shutdown before giving up.
+const BLANK_ATTEMPTS: usize = 3;
+
+#[tokio::main]
+async fn main() {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+    let mut opt = Opt::from_args();
+    let result = match opt.cmd {
+        None | Some(Command::Run) => restore(opt).await,
+        Some(Command::Effect { .. }) | Some(Command::Preset { .. }) => run(opt).await,
+        Some(Command::Gradient { ref colors, rotate }) => {
+            // Run as the effect, so it is remembered and shown again as one.
+            let name = gradient_effect(colors, rotate);
+            opt.cmd = Some(Command::Effect { name });
+            run(opt).await
+        }
+        Some(Command::Set { color }) => set(&opt, color.into()),
+        Some(Command::Pixel { index, color }) => pixel(&opt, index, color.into()),
+        Some(Command::Test { hold_ms }) => {
+            Settings::load(&opt).and_then(|s| test_pattern(&s, Duration::from_millis(hold_ms)))
+        }
+        Some(Command::Identify {
+            index,
+            binary,
+            step_ms,
+        }) => identify(&opt, index, binary, Duration::from_millis(step_ms)).await,
+        Some(Command::Calibrate { index }) => calibrate(&opt, index),
+        Some(Command::CameraCalibrate {
+            index,
+            ref device,
+            level,
+            settle_ms,
+            no_balance,
+            ref out,
+        }) => camera_calibrate(
+            &opt,
+            index,
+            device,
+            level,
+            Duration::from_millis(settle_ms),
+            !no_balance,
+            out.as_deref(),
+        ),
+        Some(Command::Schedule {
+            cmd: ScheduleCommand::Preview { date, step_minutes },
+        }) => Settings::load(&opt).and_then(|s| preview_schedule(&s, date, step_minutes)),
+        Some(Command::Render {
+            ref effect,
+            seconds,
+            ref out,
+        }) => Settings::load(&opt).and_then(|s| render_gif(&s, effect, seconds, out)),
+        Some(Command::Pipe {
+            format,
+            fps,
+            interpolate,
+        }) => {
+            let period = Duration::from_secs_f64(1.0 / fps.max(0.1));
+            pipe(&opt, format, period, interpolate).await
+        }
+        Some(Command::Config {
+            cmd: ConfigCommand::Check,
+        }) => Settings::load(&opt).and_then(|s| check_config(&opt, &s)),
+        Some(Command::On) => on(opt).await,
+        Some(Command::Off) => off(&opt),
+        Some(Command::Scene {
+            cmd: SceneCommand::Save { ref name },
+        }) => save_scene(&opt, name),
+        Some(Command::Scene {
+            cmd: SceneCommand::Load { ref name },
+        }) => match Show::load_scene(&opt.state_file, name) {
+            Ok(scene) => show(opt, Some(scene)).await,
+            Err(e) => Err(e),
+        },
+        Some(Command::Status) => Settings::load(&opt).and_then(|s| status(&opt, &s)),
+        Some(Command::Effects) => {
+            list_effects();
+            Ok(())
+        }
+    };
+    if let Err(e) = result {
+        error!("{}", e);
+        std::process::exit(1);
+    }
+}
+
+/// Record what the strips are showing; failures are only logged since the
+/// strips are already lit.
+fn save_state(opt: &Opt, state: &State) {
+    if let Err(e) = state.save(&opt.state_file) {
+        warn!("unable to save state: {}", e);
+    }
+}
+
+fn set(opt: &Opt, color: (u8, u8, u8)) -> Result<()> {
+    let settings = Settings::load(opt)?;
+    let longest = settings.longest();
+    let from = shown_frame(&State::load(&opt.state_file)?, longest);
+    fade_strips(&settings, &from, &vec![Color16::from(color); longest])?;
+    let state = State {
+        on: true,
+        last: Some(Show::Color {
+            color: [color.0, color.1, color.2],
+        }),
+        max_brightness: opt.max_brightness,
+        ..State::default()
     };
-    trace!("pixels: {:02x?}", pixels);
-    trace!("bytes: {:02x?}", bytes);
-    spi.write_all(bytes)?;
+    save_state(opt, &state);
     Ok(())
 }
 
-fn hsv_to_rgb(hue: f64, saturation: f64, value: f64) -> (f64, f64, f64) {
-    if saturation < 1.0e-6 {
-        return (value, value, value);
+/// Show the last color or effect again, running the default effect if
+/// nothing was recorded.
+async fn on(mut opt: Opt) -> Result<()> {
+    let state = State::load(&opt.state_file)?;
+    opt.max_brightness = opt.max_brightness.or(state.max_brightness);
+    show(opt, state.last).await
+}
+
+/// Pick up where the strips were before a restart: dark if they were
+/// turned off, otherwise showing what they last showed.
+async fn restore(opt: Opt) -> Result<()> {
+    if !State::load(&opt.state_file)?.on {
+        info!("strips were turned off, leaving them off");
+        return off(&opt);
     }
+    on(opt).await
+}
 
-    let mut hue = hue;
-    hue /= 60.0;
+/// Save what the strips last showed as scene `name`.
+fn save_scene(opt: &Opt, name: &str) -> Result<()> {
+    let last = match State::load(&opt.state_file)?.last {
+        Some(last) => last,
+        None => effect_shown(opt, Settings::load(opt)?.effect),
+    };
+    last.save_scene(&opt.state_file, name)?;
+    info!("saved scene {}", name);
+    Ok(())
+}
 
-    let i = hue.floor();
-    let frac = hue - i;
-    let p = value * (1.0 - saturation);
-    let q = value * (1.0 - saturation * frac);
-    let t = value * (1.0 - saturation * (1.0 - frac));
+/// Show `last`, or run the default effect for `None`.
+async fn show(mut opt: Opt, last: Option<Show>) -> Result<()> {
+    match last {
+        Some(Show::Color { color: [r, g, b] }) => set(&opt, (r, g, b)),
+        Some(Show::Pixels { pixels }) => {
+            let settings = Settings::load(&opt)?;
+            let from = shown_frame(&State::load(&opt.state_file)?, settings.longest());
+            fade_strips(&settings, &from, &pixel_frame(&pixels))?;
+            let state = State {
+                on: true,
+                last: Some(Show::Pixels { pixels }),
+                max_brightness: opt.max_brightness,
+                ..State::default()
+            };
+            save_state(&opt, &state);
+            Ok(())
+        }
+        Some(Show::Effect {
+            name,
+            palette,
+            speed,
+            params,
+        }) => {
+            let name = parse_effect(&name).map_err(|e| Error::State {
+                path: opt.state_file.display().to_string(),
+                message: e,
+            })?;
+            opt.cmd = Some(Command::Effect { name });
+            // What is given on the command line goes over what was saved.
+            opt.palette = opt.palette.or(palette);
+            opt.speed = opt.speed.or(speed);
+            opt.params = params.into_iter().chain(opt.params).collect();
+            run(opt).await
+        }
+        Some(Show::Preset { name }) => {
+            opt.cmd = Some(Command::Preset { name });
+            run(opt).await
+        }
+        None => run(opt).await,
+    }
+}
 
-    let color = match i as u8 {
-        0 => (value, t, p),
-        1 => (q, value, p),
-        2 => (p, value, t),
-        3 => (p, q, value),
-        4 => (t, p, value),
-        _ => (value, p, q),
+/// Set one pixel on top of the last color or pixels shown.
+fn pixel(opt: &Opt, index: usize, color: (u8, u8, u8)) -> Result<()> {
+    let settings = Settings::load(opt)?;
+    let longest = settings.longest();
+    if index >= longest {
+        return Err(Error::Device(format!(
+            "pixel {} is past the end of the strip ({} LEDs)",
+            index, longest
+        )));
+    }
+    // An effect's frames aren't recorded, so after one start from black.
+    let state = State::load(&opt.state_file)?;
+    let from = shown_frame(&state, longest);
+    let mut pixels = match state.last {
+        Some(Show::Color { color }) if state.on => vec![color; longest],
+        Some(Show::Pixels { pixels }) if state.on => pixels,
+        _ => Vec::new(),
     };
+    pixels.resize(longest, [0, 0, 0]);
+    pixels[index] = [color.0, color.1, color.2];
+    fade_strips(&settings, &from, &pixel_frame(&pixels))?;
+    let state = State {
+        on: true,
+        last: Some(Show::Pixels { pixels }),
+        max_brightness: opt.max_brightness,
+        ..State::default()
+    };
+    save_state(opt, &state);
+    Ok(())
+}
 
-    (color.0, color.1, color.2)
+/// Blank the strips, remembering what they showed for `on`.
+fn off(opt: &Opt) -> Result<()> {
+    let settings = Settings::load(opt)?;
+    let mut state = State::load(&opt.state_file)?;
+    let longest = settings.longest();
+    fade_strips(
+        &settings,
+        &shown_frame(&state, longest),
+        &vec![Color16::default(); longest],
+    )?;
+    state.on = false;
+    save_state(opt, &state);
+    Ok(())
 }
 
-fn hue_to_pixels(hue: &[f64], gamma_table: &GammaTable, gamma: f64) -> Vec<Color> {
-    let mut pixels = hue
+/// What the strips show according to `state`, as `len` pixels. After an
+/// effect they are dark, since effects fade out as they stop.
+fn shown_frame(state: &State, len: usize) -> Frame {
+    let mut frame = match &state.last {
+        Some(Show::Color { color: [r, g, b] }) if state.on => {
+            vec![Color16::from((*r, *g, *b)); len]
+        }
+        Some(Show::Pixels { pixels }) if state.on => pixel_frame(pixels),
+        _ => Vec::new(),
+    };
+    frame.resize(len, Color16::default());
+    frame
+}
+
+fn pixel_frame(pixels: &[[u8; 3]]) -> Frame {
+    pixels
         .iter()
-        .map(|h| {
-            let (r, g, b) = hsv_to_rgb(*h, 1.0, 1.0);
-            gamma_table.correct_color((r * gamma) as u8, (g * gamma) as u8, (b * gamma) as u8)
-        })
-        .collect::<Vec<Color>>();
-    pixels.insert(
-        0,
-        Color {
-            flag: 0,
-            red: 0,
-            green: 0,
-            blue: 0,
-        },
+        .map(|[r, g, b]| Color16::from((*r, *g, *b)))
+        .collect()
+}
+
+/// Open every strip and fade it from `from` to `to` over the transition
+/// time, pixel `i` of each frame going to pixel `i` of every strip.
+fn fade_strips(settings: &Settings, from: &[Color16], to: &[Color16]) -> Result<()> {
+    let mut strips = open_strips(settings)?;
+    let mut fade = settings.crossfade();
+    if from != to {
+        fade.start(from.to_vec(), Instant::now());
+    }
+    loop {
+        let mut frame = to.to_vec();
+        let fading = fade.apply(&mut frame, Instant::now());
+        for strip in strips.iter_mut() {
+            for (i, color) in frame.iter().take(strip.len()).enumerate() {
+                strip.set_pixel16(i, *color);
+            }
+            strip.show()?;
+        }
+        if !fading {
+            return Ok(());
+        }
+        thread::sleep(FRAME_PERIOD);
+    }
+}
+
+/// Steps of the test pattern before the moving dot.
+const TEST_COLORS: &[(&str, (u8, u8, u8))] = &[
+    ("red", (255, 0, 0)),
+    ("green", (0, 255, 0)),
+    ("blue", (0, 0, 255)),
+    ("white", (255, 255, 255)),
+];
+
+/// Run the test pattern once, printing the frames written for each step.
+fn test_pattern(settings: &Settings, hold: Duration) -> Result<()> {
+    let mut strips = open_strips(settings)?;
+    let longest = strips.iter().map(Strip::len).max().unwrap_or(0);
+    let mut total = 0;
+
+    for (name, (red, green, blue)) in TEST_COLORS {
+        let start = Instant::now();
+        let mut frames = 0;
+        while start.elapsed() < hold {
+            for strip in strips.iter_mut() {
+                strip.fill(*red, *green, *blue);
+                strip.show()?;
+            }
+            frames += 1;
+            thread::sleep(FRAME_PERIOD);
+        }
+        println!("{}: {} frames", name, frames);
+        total += frames;
+    }
+
+    for i in 0..longest {
+        for strip in strips.iter_mut() {
+            strip.clear();
+            strip.set_pixel(i, 255, 255, 255);
+            strip.show()?;
+        }
+        thread::sleep(FRAME_PERIOD);
+    }
+    println!("dot: {} frames", longest);
+    total += longest;
+
+    for strip in strips.iter_mut() {
+        strip.clear();
+        strip.show()?;
+    }
+    println!("total: {} frames on {} strips", total, strips.len());
+    Ok(())
+}
+
+/// Frame for `step` of the identify pattern on a strip of `num_leds`.
+fn identify_frame(
+    index: Option<usize>,
+    binary: bool,
+    num_leds: usize,
+    step: usize,
+) -> Vec<(u8, u8, u8)> {
+    if binary {
+        // One start frame followed by a frame per bit of the largest index.
+        let bits = (usize::BITS - num_leds.saturating_sub(1).leading_zeros()).max(1) as usize;
+        let step = step % (bits + 1);
+        (0..num_leds)
+            .map(|i| match step {
+                0 => (0, 0, 255),
+                _ if i >> (step - 1) & 1 == 1 => (0, 255, 0),
+                _ => (255, 0, 0),
+            })
+            .collect()
+    } else {
+        let mut frame = vec![(0, 0, 0); num_leds];
+        if let Some(pixel) = index.and_then(|i| frame.get_mut(i)) {
+            if step.is_multiple_of(2) {
+                *pixel = (255, 255, 255);
+            }
+        }
+        frame
+    }
+}
+
+async fn identify(opt: &Opt, index: Option<usize>, binary: bool, step: Duration) -> Result<()> {
+    let mut strips = open_strips(&Settings::load(opt)?)?;
+    let mut shutdown = shutdown_signal()?;
+    let mut interval = tokio::time::interval(step);
+    for n in 0.. {
+        tokio::select! {
+            _ = interval.tick() => {}
+            _ = shutdown.changed() => break,
+        }
+        for strip in strips.iter_mut() {
+            let frame = identify_frame(index, binary, strip.len(), n);
+            for (i, (r, g, b)) in frame.into_iter().enumerate() {
+                strip.set_pixel(i, r, g, b);
+            }
+            strip.show()?;
+        }
+    }
+    for strip in strips.iter_mut() {
+        strip.clear();
+        strip.show()?;
+    }
+    Ok(())
+}
+
+/// Patterns shown while calibrating, cycled with tab.
+const RAMPS: &[&str] = &["full ramp", "dark ramp", "mid gray"];
+
+/// Gray level of LED `i` of `num_leds` for `RAMPS[ramp]`.
+fn ramp_level(ramp: usize, i: usize, num_leds: usize) -> u8 {
+    let t = if num_leds > 1 {
+        i as f64 / (num_leds - 1) as f64
+    } else {
+        1.0
+    };
+    match ramp {
+        0 => (t * 255.0) as u8,
+        1 => (t * 64.0) as u8,
+        _ => 128,
+    }
+}
+
+/// Change in gamma for each arrow key press.
+const GAMMA_STEP: f64 = 0.05;
+
+/// Restores the terminal when calibration ends, however it ends.
+struct RawMode;
+
+impl RawMode {
+    fn enable() -> Result<RawMode> {
+        terminal::enable_raw_mode()?;
+        Ok(RawMode)
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+fn calibrate(opt: &Opt, index: usize) -> Result<()> {
+    let path = opt
+        .config
+        .as_ref()
+        .ok_or(Error::Unsupported("calibrate needs --config to save to"))?;
+    let settings = Settings::load(opt)?;
+    let spec = settings
+        .strips
+        .get(index)
+        .ok_or_else(|| Error::Device(format!("no strip {}", index)))?;
+    let mut strip = spec.open()?;
+    let (red, green, blue) = spec.gammas();
+    let mut gammas = [red, green, blue];
+    // Tune against the curves the strip runs with; CIE channels ignore
+    // their gamma.
+    let (red_curve, green_curve, blue_curve) = spec.curves();
+    let curves = [red_curve, green_curve, blue_curve];
+    let mut channel = 0;
+    let mut ramp = 0;
+
+    println!("left/right: channel, up/down: gamma, tab: pattern, enter: save, q: quit");
+    let raw = RawMode::enable()?;
+    let save = loop {
+        strip.set_gamma_table(GammaTable::with_curves(
+            (curves[0], gammas[0]),
+            (curves[1], gammas[1]),
+            (curves[2], gammas[2]),
+        ));
+        let num_leds = strip.len();
+        for i in 0..num_leds {
+            let level = ramp_level(ramp, i, num_leds);
+            strip.set_pixel(i, level, level, level);
+        }
+        strip.show()?;
+
+        let line = ["red", "green", "blue"]
+            .iter()
+            .zip(gammas.iter().zip(&curves))
+            .enumerate()
+            .map(|(i, (name, (gamma, curve)))| {
+                let value = match curve {
+                    Curve::Gamma => format!("{:.2}", gamma),
+                    Curve::Cie => "cie".to_string(),
+                };
+                if i == channel {
+                    format!("[{} {}]", name, value)
+                } else {
+                    format!(" {} {} ", name, value)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        print!("\r{:<10} {}\x1b[K", RAMPS[ramp], line);
+        io::stdout().flush()?;
+
+        match event::read()? {
+            Event::Key(key) if key.kind != KeyEventKind::Release => match key.code {
+                KeyCode::Left => channel = (channel + 2) % 3,
+                KeyCode::Right => channel = (channel + 1) % 3,
+                KeyCode::Up => gammas[channel] = (gammas[channel] + GAMMA_STEP).min(5.0),
+                KeyCode::Down => gammas[channel] = (gammas[channel] - GAMMA_STEP).max(0.1),
+                KeyCode::Tab => ramp = (ramp + 1) % RAMPS.len(),
+                KeyCode::Enter => break true,
+                KeyCode::Esc | KeyCode::Char('q') => break false,
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => break false,
+                _ => {}
+            },
+            _ => {}
+        }
+    };
+    drop(raw);
+    println!();
+
+    strip.clear();
+    strip.show()?;
+    if save {
+        config::write_gammas(path, index, (gammas[0], gammas[1], gammas[2]))?;
+        println!("saved to {}", path.display());
+    }
+    Ok(())
+}
+
+/// LEDs this far apart are lit together when measuring, so their light
+/// doesn't mix in the camera.
+const MEASURE_STRIDE: usize = 4;
+
+/// Summed channels an LED must raise the brightest camera pixel by to be
+/// found.
+const LOCATE_THRESHOLD: f32 = 60.0;
+
+/// Pixels around the brightest one that an LED's position is taken from.
+const LOCATE_RADIUS: f32 = 12.0;
+
+/// Show `frame` and capture it once the LEDs and the camera have settled.
+fn snapshot(
+    camera: &mut Camera,
+    strip: &mut Strip,
+    frame: &[(u8, u8, u8)],
+    settle: Duration,
+) -> Result<Image> {
+    for (i, (r, g, b)) in frame.iter().enumerate() {
+        strip.set_pixel(i, *r, *g, *b);
+    }
+    strip.show()?;
+    thread::sleep(settle);
+    // Frames already queued show the LEDs as they were.
+    for _ in 0..camera.buffers() {
+        camera.capture()?;
+    }
+    camera.capture()
+}
+
+fn camera_calibrate(
+    opt: &Opt,
+    index: usize,
+    device: &str,
+    level: u8,
+    settle: Duration,
+    balance: bool,
+    out: Option<&Path>,
+) -> Result<()> {
+    let settings = Settings::load(opt)?;
+    let spec = settings
+        .strips
+        .get(index)
+        .ok_or_else(|| Error::Device(format!("no strip {}", index)))?;
+    let path = out
+        .map(Path::to_path_buf)
+        .or_else(|| spec.calibration.clone())
+        .ok_or(Error::Unsupported(
+            "camera-calibrate needs --out or a calibration file for the strip",
+        ))?;
+    let mut camera = Camera::open(device)?;
+    let mut strip = spec.open()?;
+    // Light LEDs by their place on the wire, without the corrections being
+    // measured for.
+    strip.set_calibration(Calibration::default());
+    strip.set_white_point(WhitePoint::NEUTRAL);
+    strip.set_mirror(Mirror::Off);
+    strip.set_reverse(false);
+    strip.set_offset(0);
+    strip.set_blackout(&[]);
+    strip.set_dither(false);
+    let num_leds = strip.len();
+
+    let black = vec![(0, 0, 0); num_leds];
+    let dark = snapshot(&mut camera, &mut strip, &black, settle)?;
+    let mut spots = Vec::with_capacity(num_leds);
+    for i in 0..num_leds {
+        let mut frame = black.clone();
+        frame[i] = (level, level, level);
+        let lit = snapshot(&mut camera, &mut strip, &frame, settle)?.difference(&dark)?;
+        spots.push(lit.locate(LOCATE_THRESHOLD, LOCATE_RADIUS));
+        print!("\rlocating LEDs: {}/{}", i + 1, num_leds);
+        io::stdout().flush()?;
+    }
+    println!();
+
+    let radius = camera::spot_radius(&spots, LOCATE_RADIUS);
+    let mut levels: Vec<Option<[f32; 3]>> = spots.iter().map(|s| s.map(|_| [0.0; 3])).collect();
+    for channel in 0..3 {
+        for group in 0..MEASURE_STRIDE {
+            let mut frame = black.clone();
+            for pixel in frame.iter_mut().skip(group).step_by(MEASURE_STRIDE) {
+                let mut color = [0; 3];
+                color[channel] = level;
+                *pixel = (color[0], color[1], color[2]);
+            }
+            let lit = snapshot(&mut camera, &mut strip, &frame, settle)?.difference(&dark)?;
+            for i in (group..num_leds).step_by(MEASURE_STRIDE) {
+                if let (Some(at), Some(measured)) = (spots[i], levels[i].as_mut()) {
+                    measured[channel] = lit.measure(at, radius)[channel];
+                }
+            }
+        }
+    }
+    strip.clear();
+    strip.show()?;
+
+    let calibration = Calibration::from_levels(&levels, balance);
+    calibration.save(&path)?;
+    println!(
+        "{} LEDs found, {} not; saved to {}",
+        num_leds - calibration.dead.len(),
+        calibration.dead.len(),
+        path.display()
     );
-    pixels.push(Color {
-        flag: 0,
-        red: 0,
-        green: 0,
-        blue: 0,
-    });
-    pixels.push(Color {
-        flag: 0,
-        red: 0,
-        green: 0,
-        blue: 0,
-    });
-    pixels
+    Ok(())
 }
 
-#[derive(Debug, StructOpt)]
-#[structopt(name = "blink", about = "Control for TCL p9813 LED chip.")]
-struct Opt {
-    /// Latitude used for sunrise calculations.
-    #[structopt(long = "latitude")]
-    lat: f64,
-    /// Longitude used for sunrise calculations.
-    #[structopt(long = "longitude")]
-    lon: f64,
+/// Width of the bar for full brightness in the schedule preview.
+const PREVIEW_WIDTH: usize = 50;
+
+fn preview_schedule(settings: &Settings, date: Option<NaiveDate>, step_minutes: i64) -> Result<()> {
+    let (lat, lon) = settings.location()?;
+    let date = date.unwrap_or_else(|| Local::today().naive_local());
+    let (sunrise, sunset) = schedule::sun_times(lat, lon, date);
+    println!(
+        "{} at {}, {}: sunrise {}, sunset {}",
+        date,
+        lat,
+        lon,
+        sunrise.with_timezone(&Local).format("%H:%M"),
+        sunset.with_timezone(&Local).format("%H:%M")
+    );
+    let step = chrono::Duration::minutes(step_minutes.max(1));
+    for (time, gamma) in settings.schedule.day_curve(lat, lon, date, step) {
+        let gamma = gamma.clamp(0.0, 255.0);
+        let bar = (gamma * PREVIEW_WIDTH as f64 / 255.0).round() as usize;
+        println!(
+            "{}  {:>3.0}  {}",
+            time.format("%H:%M"),
+            gamma,
+            "#".repeat(bar)
+        );
+    }
+    Ok(())
 }
 
-fn main() {
-    let opt = Opt::from_args();
+/// Side of the square drawn for each LED in a rendered GIF.
+const GIF_CELL: usize = 8;
 
-    let running = Arc::new(AtomicBool::new(true));
-    let r = running.clone();
-    ctrlc::set_handler(move || {
-        r.store(false, Ordering::SeqCst);
-    })
-    .expect("Error setting Ctrl-C handler");
+/// Time between GIF frames in hundredths of a second, the format's unit.
+const GIF_DELAY: u16 = 4;
 
-    let mut spi = create_spi().unwrap();
+fn render_gif(settings: &Settings, effect: &str, seconds: f64, out: &Path) -> Result<()> {
+    if out.extension().and_then(|e| e.to_str()) != Some("gif") {
+        return Err(Error::Unsupported("only .gif output is supported"));
+    }
+    let lengths: Vec<usize> = settings.strips.iter().map(StripConfig::pixels).collect();
+    let columns = lengths.iter().copied().max().unwrap_or(0).max(1);
+    let width = columns * GIF_CELL;
+    let height = lengths.len().max(1) * GIF_CELL;
+    let gif_size =
+        |n: usize| u16::try_from(n).map_err(|_| Error::Unsupported("strips too long for a GIF"));
 
-    const NUM_LEDS: usize = 76;
-    let gamma_table = GammaTable::new(2.2, 2.2, 2.2);
+    let file = fs::File::create(out).map_err(|source| Error::Open {
+        path: out.display().to_string(),
+        source,
+    })?;
+    let mut encoder = gif::Encoder::new(
+        io::BufWriter::new(file),
+        gif_size(width)?,
+        gif_size(height)?,
+        &[],
+    )
+    .map_err(io::Error::other)?;
+    encoder
+        .set_repeat(gif::Repeat::Infinite)
+        .map_err(io::Error::other)?;
+
+    // Render as the ticker would, at full brightness, one strip a row.
+    let frame_time = Duration::from_millis(u64::from(GIF_DELAY) * 10);
+    let count = (seconds / frame_time.as_secs_f64()).ceil() as u32;
+    let start = Instant::now();
+    let mut engine = Engine::new(settings.effects(effect, &lengths), start);
+    engine.set_speed(settings.speed);
+    let mut pixels = vec![0u8; width * height * 3];
+    info!("rendering {} frames of {}", count, effect);
+    for n in 0..count {
+        engine.tick(start + frame_time * n, 255.0, 0.0);
+        pixels.iter_mut().for_each(|p| *p = 0);
+        for (row, frame) in engine.frames().iter().enumerate() {
+            for (column, color) in frame.iter().enumerate() {
+                let rgb: [u8; 3] = color.to_color().into();
+                for y in row * GIF_CELL..(row + 1) * GIF_CELL {
+                    for x in column * GIF_CELL..(column + 1) * GIF_CELL {
+                        let i = (y * width + x) * 3;
+                        pixels[i..i + 3].copy_from_slice(&rgb);
+                    }
+                }
+            }
+        }
+        let mut frame =
+            gif::Frame::from_rgb_speed(gif_size(width)?, gif_size(height)?, &pixels, 10);
+        frame.delay = GIF_DELAY;
+        encoder.write_frame(&frame).map_err(io::Error::other)?;
+    }
+    Ok(())
+}
+
+/// Print the settings in use and today's schedule.
+fn status(opt: &Opt, settings: &Settings) -> Result<()> {
+    let location = settings.location()?;
+    let state = State::load(&opt.state_file)?;
+    println!("power: {}", if state.on { "on" } else { "off" });
+    match &state.last {
+        Some(Show::Color { color: [r, g, b] }) => {
+            println!("last shown: #{:02x}{:02x}{:02x}", r, g, b)
+        }
+        Some(Show::Pixels { pixels }) => {
+            let lit = pixels.iter().filter(|p| **p != [0, 0, 0]).count();
+            println!("last shown: {} of {} pixels set", lit, pixels.len())
+        }
+        Some(Show::Effect { name, .. }) => println!("last shown: {}", name),
+        Some(Show::Preset { name }) => println!("last shown: preset {}", name),
+        None => println!("last shown: {}", settings.effect),
+    }
+    print_settings(settings, Some(location));
+    Ok(())
+}
+
+fn list_effects() {
+    let width = effect::names().iter().map(|n| n.len()).max().unwrap_or(0);
+    for registration in effect::REGISTRY {
+        println!(
+            "{:width$}  {}",
+            registration.name,
+            registration.description,
+            width = width
+        );
+        for param in registration.params {
+            println!(
+                "{:width$}    {}: {}",
+                "",
+                param.name,
+                param.description,
+                width = width
+            );
+        }
+    }
+    println!(
+        "\nstack effects with +, blending each with {} and an opacity, \
+         speed any up with a multiple such as 2x and give any its own \
+         key=value parameters",
+        effect::BLEND_NAMES.join(", ")
+    );
+}
+
+/// Print the effective settings, with today's schedule when the location
+/// is known.
+fn print_settings(settings: &Settings, location: Option<(f64, f64)>) {
+    if let Some((lat, lon)) = location {
+        let (sunrise, sunset) = schedule::sun_times(lat, lon, Local::today().naive_local());
+        println!("location: {}, {}", lat, lon);
+        println!(
+            "sunrise: {}, sunset: {}",
+            sunrise.with_timezone(&Local).format("%H:%M"),
+            sunset.with_timezone(&Local).format("%H:%M")
+        );
+        println!(
+            "brightness: {:.0}/255",
+            settings
+                .schedule
+                .brightness_at(lat, lon, Local::now())
+                .clamp(0.0, 255.0)
+        );
+    }
+    println!(
+        "ramps: {} minutes before sunrise, {} after sunset, {} easing",
+        settings.schedule.sunrise_ramp_minutes,
+        settings.schedule.sunset_ramp_minutes,
+        settings.schedule.ramp_easing
+    );
+    if let Some((start, end)) = settings.schedule.night() {
+        println!(
+            "night: blue down to {:.0}% from {} to {}, now {:.0}%",
+            settings.schedule.night_blue * 100.0,
+            start.format("%H:%M"),
+            end.format("%H:%M"),
+            settings.schedule.blue_at(Local::now()) * 100.0
+        );
+    }
+    match &settings.playlist {
+        Some(playlist) => {
+            let entries: Vec<String> = playlist.effects.iter().map(Entry::to_string).collect();
+            println!(
+                "playlist: {}{}",
+                entries.join(", "),
+                if playlist.shuffle { ", shuffled" } else { "" }
+            );
+        }
+        None => println!("effect: {}", settings.effect),
+    }
+    match &settings.palette_cycle {
+        Some(cycle) => println!(
+            "palettes: {}, {}s each{}",
+            cycle.palettes.join(", "),
+            cycle.seconds,
+            if cycle.shuffle { ", shuffled" } else { "" }
+        ),
+        None => {
+            if let Some(palette) = &settings.palette {
+                println!("palette: {}", palette);
+            }
+        }
+    }
+    if !settings.params.is_empty() {
+        println!("params: {}", settings.params);
+    }
+    for (name, zone) in &settings.zones {
+        let leds = zone
+            .leds
+            .map_or_else(|| "none".to_string(), |l| l.to_string());
+        let shows = match (&zone.color, &zone.effect) {
+            (Some(color), _) => color.to_string(),
+            (None, Some(effect)) => effect.clone(),
+            (None, None) => "the effect".to_string(),
+        };
+        println!(
+            "zone {}: LEDs {}, {} at {:.0}%",
+            name,
+            leds,
+            shows,
+            zone.brightness() * 100.0
+        );
+    }
+    println!("layout: {}", settings.layout);
+    println!("transition: {} seconds", settings.transition);
+    println!("speed: {}x", settings.speed);
+    for (i, strip) in settings.strips.iter().enumerate() {
+        let (red, green, blue) = strip.gammas();
+        let (red_curve, green_curve, blue_curve) = strip.curves();
+        let curve = |curve: Curve, gamma: f64| match curve {
+            Curve::Gamma => gamma.to_string(),
+            Curve::Cie => curve.to_string(),
+        };
+        println!(
+            "strip {}: {} {} on {}, {} LEDs, gamma {}/{}/{}, order {}",
+            i,
+            strip.chip(),
+            strip.driver(),
+            strip.path(),
+            strip.num_leds(),
+            curve(red_curve, red),
+            curve(green_curve, green),
+            curve(blue_curve, blue),
+            strip.color_order.unwrap_or_default()
+        );
+    }
+}
 
-    // Set starting color of all the pixels.
-    let mut hue = [0f64; NUM_LEDS];
-    hue.iter_mut().enumerate().for_each(|(i, v)| {
-        *v = (i as f64 * 360f64) / NUM_LEDS as f64;
+/// Problems with the settings that would stop the strips working.
+fn check_settings(settings: &Settings) -> Vec<String> {
+    let mut problems = Vec::new();
+    match settings.lat {
+        Some(lat) if !(-90.0..=90.0).contains(&lat) => {
+            problems.push(format!("latitude {} is outside -90 to 90", lat))
+        }
+        Some(_) => {}
+        None => problems.push(Error::Missing("--latitude").to_string()),
+    }
+    match settings.lon {
+        Some(lon) if !(-180.0..=180.0).contains(&lon) => {
+            problems.push(format!("longitude {} is outside -180 to 180", lon))
+        }
+        Some(_) => {}
+        None => problems.push(Error::Missing("--longitude").to_string()),
+    }
+    if Duration::try_from_secs_f64(settings.transition).is_err() {
+        problems.push(format!(
+            "transition must be 0 or more seconds, not {}",
+            settings.transition
+        ));
+    }
+    if !(0.0..=effect::MAX_SPEED).contains(&settings.speed) {
+        problems.push(format!(
+            "speed must be from 0 to {}, not {}",
+            effect::MAX_SPEED,
+            settings.speed
+        ));
+    }
+    let names = settings.effect_names();
+    for name in &names {
+        if let Err(e) = effect::create(name, None, &settings.params) {
+            problems.push(e);
+        }
+    }
+    for key in effect::unused_params(&names, &settings.params) {
+        problems.push(format!(
+            "parameter {} isn't read by {}",
+            key,
+            names.join(", ")
+        ));
+    }
+    problems.extend(settings.schedule.problems());
+    if let Some(cycle) = &settings.palette_cycle {
+        problems.extend(cycle.problems());
+    }
+    for (i, strip) in settings.strips.iter().enumerate() {
+        problems.extend(
+            strip
+                .problems()
+                .into_iter()
+                .map(|p| format!("strip {}: {}", i, p)),
+        );
+    }
+    for (name, zone) in &settings.zones {
+        problems.extend(
+            zone.problems()
+                .into_iter()
+                .map(|p| format!("zone {}: {}", name, p)),
+        );
+        if let Some(palette) = &zone.palette {
+            if settings.palette_named(palette).is_none() {
+                problems.push(format!("zone {}: unknown palette {}", name, palette));
+            }
+        }
+    }
+    problems
+}
+
+fn check_config(opt: &Opt, settings: &Settings) -> Result<()> {
+    let problems = check_settings(settings);
+    let location = settings.location().ok();
+    print_settings(settings, location);
+    if problems.is_empty() {
+        println!("ok");
+        return Ok(());
+    }
+    for problem in &problems {
+        println!("problem: {}", problem);
+    }
+    Err(Error::Config {
+        path: opt
+            .config
+            .as_ref()
+            .map_or_else(|| "(command line)".to_string(), |p| p.display().to_string()),
+        message: format!("{} problems found", problems.len()),
+    })
+}
+
+/// A receiver that changes once ctrl-c or SIGTERM is received.
+fn shutdown_signal() -> Result<watch::Receiver<bool>> {
+    let mut terminate = signal(SignalKind::terminate()).map_err(Error::Signal)?;
+    let (shutdown_tx, shutdown) = watch::channel(false);
+    tokio::spawn(async move {
+        tokio::select! {
+            result = tokio::signal::ctrl_c() => {
+                if let Err(e) = result {
+                    error!("unable to wait for ctrl-c: {}", e);
+                }
+            }
+            _ = terminate.recv() => {}
+        }
+        let _ = shutdown_tx.send(true);
     });
+    Ok(shutdown)
+}
+
+fn open_strips(settings: &Settings) -> Result<Vec<Strip>> {
+    settings.strips.iter().map(StripConfig::open).collect()
+}
+
+async fn run(opt: Opt) -> Result<()> {
+    let settings = Settings::load(&opt)?;
+    let location = settings.location()?;
+    let strips = open_strips(&settings)?;
+    let hangup = signal(SignalKind::hangup()).map_err(Error::Signal)?;
+    let shutdown = shutdown_signal()?;
+
+    let from = shown_frame(
+        &State::load(&opt.state_file).unwrap_or_default(),
+        settings.longest(),
+    );
+    let (outputs, threads) = spawn_outputs(strips, &settings.crossfade(), &from)?;
+    match &settings.playlist {
+        Some(playlist) => info!("running a playlist of {}", playlist.effects.len()),
+        None => info!("running {}", settings.effect),
+    }
+    let tee = open_tee(&opt)?;
+    ticker_task(&opt, settings, location, outputs, shutdown, hangup, tee).await;
+    join_outputs(threads)
+}
+
+fn open_tee(opt: &Opt) -> Result<Option<FrameWriter>> {
+    opt.tee.as_deref().map(FrameWriter::create).transpose()
+}
+
+/// Record `frame` for strip `index`; the tee is dropped after an error so
+/// a closed pipe doesn't stop the strips.
+fn record(tee: &mut Option<FrameWriter>, index: usize, frame: &[Color16]) {
+    if let Some(writer) = tee {
+        let frame: Vec<(u8, u8, u8)> = frame.iter().map(|c| c.to_color().into()).collect();
+        if let Err(e) = writer.write(SystemTime::now(), index as u16, &frame) {
+            warn!("unable to write frame record, stopping: {}", e);
+            *tee = None;
+        }
+    }
+}
+
+fn flush_record(tee: &mut Option<FrameWriter>) {
+    if let Some(writer) = tee {
+        if let Err(e) = writer.flush() {
+            warn!("unable to write frame record, stopping: {}", e);
+            *tee = None;
+        }
+    }
+}
+
+type OutputThread = JoinHandle<Result<()>>;
+
+/// Start an output thread for each strip, fading in from `from`.
+fn spawn_outputs(
+    strips: Vec<Strip>,
+    fade: &Crossfade,
+    from: &[Color16],
+) -> Result<(Vec<Output>, Vec<OutputThread>)> {
+    let mut outputs = Vec::new();
+    let mut threads = Vec::new();
+    for (i, strip) in strips.into_iter().enumerate() {
+        let mut fade = fade.clone();
+        fade.start(
+            from.iter().take(strip.len()).copied().collect(),
+            Instant::now(),
+        );
+        let (messages_tx, messages) = mpsc::channel(FRAME_QUEUE);
+        outputs.push(Output {
+            num_leds: strip.len(),
+            messages: messages_tx,
+            dropped_frames: 0,
+        });
+        threads.push(
+            thread::Builder::new()
+                .name(format!("output-{}", i))
+                .spawn(move || output_thread(strip, messages, fade))?,
+        );
+    }
+    Ok((outputs, threads))
+}
+
+/// Wait for the output threads to blank their strips, once their `Output`s
+/// have been dropped.
+fn join_outputs(threads: Vec<OutputThread>) -> Result<()> {
+    let mut result = Ok(());
+    for thread in threads {
+        match tokio::task::block_in_place(|| thread.join()) {
+            Ok(r) => result = result.and(r),
+            Err(panic) => std::panic::resume_unwind(panic),
+        }
+    }
+    result
+}
+
+/// Read frames from stdin, one every `period` at most, and show them until
+/// stdin closes or shutdown is requested.
+async fn pipe(opt: &Opt, format: FrameFormat, period: Duration, interpolate: bool) -> Result<()> {
+    let settings = Settings::load(opt)?;
+    let strips = open_strips(&settings)?;
+    let num_leds = strips.iter().map(Strip::len).max().unwrap_or(0);
+    let shutdown = shutdown_signal()?;
+    let from = shown_frame(&State::load(&opt.state_file).unwrap_or_default(), num_leds);
+    let (outputs, threads) = spawn_outputs(strips, &settings.crossfade(), &from)?;
+    let tee = open_tee(opt)?;
+
+    // Reading blocks, so it gets a thread; the one frame queue holds back
+    // a generator that runs ahead of the pacing.
+    let (frames_tx, frames) = mpsc::channel(1);
+    thread::Builder::new()
+        .name("stdin".to_string())
+        .spawn(move || read_frames(format, num_leds, frames_tx))?;
+
+    let mut pipe = Pipe {
+        frames,
+        outputs,
+        tee,
+        shutdown,
+        night: settings.schedule,
+        period,
+    };
+    let result = if interpolate {
+        pipe.interpolate().await
+    } else {
+        pipe.pace().await
+    };
+    drop(pipe);
+    result.and(join_outputs(threads))
+}
+
+/// Frames from stdin on their way to the output threads.
+struct Pipe {
+    frames: mpsc::Receiver<Result<Frame>>,
+    outputs: Vec<Output>,
+    tee: Option<FrameWriter>,
+    shutdown: watch::Receiver<bool>,
+    /// The schedule, for night mode.
+    night: Schedule,
+    /// Shortest time between frames.
+    period: Duration,
+}
+
+impl Pipe {
+    /// Show each frame as it arrives, waiting until `period` has passed
+    /// since the one before.
+    async fn pace(&mut self) -> Result<()> {
+        let mut interval = tokio::time::interval(self.period);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            let frame: Frame = tokio::select! {
+                frame = self.frames.recv() => match frame {
+                    Some(frame) => frame?,
+                    None => return Ok(()),
+                },
+                _ = self.shutdown.changed() => return Ok(()),
+            };
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = self.shutdown.changed() => return Ok(()),
+            }
+            if !self.send(&frame).await {
+                return Ok(());
+            }
+        }
+    }
 
-    while running.load(Ordering::SeqCst) {
+    /// Show a frame every `FRAME_PERIOD`, blending towards the latest one
+    /// over the time it took to arrive.
+    async fn interpolate(&mut self) -> Result<()> {
+        let mut ticks = tokio::time::interval(FRAME_PERIOD);
+        ticks.set_missed_tick_behavior(MissedTickBehavior::Skip);
+        let mut blend = Crossfade::new(self.period).with_easing(Easing::Linear);
+        let mut target: Option<Frame> = None;
+        let mut shown = Frame::new();
+        let mut arrived: Option<Instant> = None;
+        let mut accept_at = Instant::now();
+        loop {
+            tokio::select! {
+                frame = self.frames.recv(), if Instant::now() >= accept_at => match frame {
+                    Some(frame) => {
+                        let now = Instant::now();
+                        if let Some(arrived) = arrived {
+                            blend.set_duration(now - arrived);
+                        }
+                        arrived = Some(now);
+                        accept_at = now + self.period;
+                        if !shown.is_empty() {
+                            blend.start(shown.clone(), now);
+                        }
+                        target = Some(frame?);
+                    }
+                    None => return Ok(()),
+                },
+                _ = ticks.tick() => {
+                    if let Some(target) = &target {
+                        let mut frame = target.clone();
+                        blend.apply(&mut frame, Instant::now());
+                        if !self.send(&frame).await {
+                            return Ok(());
+                        }
+                        shown = frame;
+                    }
+                }
+                _ = self.shutdown.changed() => return Ok(()),
+            }
+        }
+    }
+
+    /// Send `frame` to every output, returning false once one has closed.
+    async fn send(&mut self, frame: &[Color16]) -> bool {
+        let mut frame = frame.to_vec();
+        night_filter(&mut frame, self.night.blue_at(Local::now()));
+        for (i, output) in self.outputs.iter().enumerate() {
+            let frame = frame[..output.num_leds].to_vec();
+            record(&mut self.tee, i, &frame);
+            // A closed channel means the thread failed; join reports why.
+            if output
+                .messages
+                .send(Message::Frame(frame, 1.0))
+                .await
+                .is_err()
+            {
+                return false;
+            }
+        }
+        flush_record(&mut self.tee);
+        true
+    }
+}
+
+/// Parse frames of `num_leds` RGB triples from stdin and send them on until
+/// it closes or the receiver goes away.
+fn read_frames(format: FrameFormat, num_leds: usize, frames: mpsc::Sender<Result<Frame>>) {
+    let stdin = io::stdin();
+    let mut input = stdin.lock();
+    let mut bytes = vec![0u8; num_leds * 3];
+    let mut line = String::new();
+    loop {
+        let read = match format {
+            FrameFormat::Binary => match input.read_exact(&mut bytes) {
+                Ok(()) => Ok(true),
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+                Err(e) => Err(Error::Io(e)),
+            },
+            FrameFormat::Hex => {
+                line.clear();
+                match input.read_line(&mut line) {
+                    Ok(0) => Ok(false),
+                    Ok(_) => parse_hex_frame(&line, &mut bytes).map(|()| true),
+                    Err(e) => Err(Error::Io(e)),
+                }
+            }
+        };
+        let frame = match read {
+            Ok(true) => Ok(bytes
+                .chunks(3)
+                .map(|c| Color16::from((c[0], c[1], c[2])))
+                .collect()),
+            Ok(false) => return,
+            Err(e) => Err(e),
+        };
+        let failed = frame.is_err();
+        if frames.blocking_send(frame).is_err() || failed {
+            return;
+        }
+    }
+}
+
+/// Fill `bytes` from a line of hex digits; whitespace is ignored.
+fn parse_hex_frame(line: &str, bytes: &mut [u8]) -> Result<()> {
+    let digits: String = line.chars().filter(|c| !c.is_whitespace()).collect();
+    // Checked by hand, as from_str_radix also takes a leading sign.
+    if let Some(bad) = digits.chars().find(|c| !c.is_ascii_hexdigit()) {
+        return Err(Error::Input(format!("bad hex digit {:?}", bad)));
+    }
+    if digits.len() != bytes.len() * 2 {
+        return Err(Error::Input(format!(
+            "expected {} hex digits per frame, got {}",
+            bytes.len() * 2,
+            digits.len()
+        )));
+    }
+    for (byte, pair) in bytes.iter_mut().zip(digits.as_bytes().chunks(2)) {
+        let pair = std::str::from_utf8(pair).unwrap_or_default();
+        *byte = u8::from_str_radix(pair, 16).unwrap_or_default();
+    }
+    Ok(())
+}
+
+/// Pixels in each animation; with the same layout a single animation
+/// covers the longest strip and the rest show its start.
+fn animation_lengths(layout: Layout, outputs: &[Output]) -> Vec<usize> {
+    match layout {
+        Layout::Same => vec![outputs.iter().map(|o| o.num_leds).max().unwrap_or(0)],
+        Layout::Independent => outputs.iter().map(|o| o.num_leds).collect(),
+    }
+}
+
+/// Apply reloaded settings, fading to a changed effect or palette, and
+/// return whether the effects changed. Strips whose driver settings changed
+/// keep their old configuration until the process is restarted.
+async fn reload(settings: &mut Settings, new: Settings, outputs: &mut [Output]) -> bool {
+    // A palette cycle that carries on keeps its place.
+    let cycling = new.palette_cycle.is_some() && new.palette_cycle == settings.palette_cycle;
+    let changed = new.effect != settings.effect
+        || new.playlist != settings.playlist
+        || new.palette_cycle != settings.palette_cycle
+        || (!cycling && new.palette() != settings.palette())
+        || new.layout != settings.layout
+        || new.zones != settings.zones
+        || new.params != settings.params;
+    if new.strips.len() != outputs.len() {
+        warn!("number of strips changed, restart to apply");
+    } else {
+        for (i, (output, (old, strip))) in outputs
+            .iter_mut()
+            .zip(settings.strips.iter_mut().zip(new.strips))
+            .enumerate()
+        {
+            if strip.needs_reopen(old) {
+                warn!("driver settings for strip {} changed, restart to apply", i);
+                continue;
+            }
+            if strip != *old {
+                let message = Message::Reconfigure(Box::new(strip.clone()));
+                // A closed channel means the thread failed; the ticker
+                // notices on the next frame.
+                let _ = output.messages.send(message).await;
+                output.num_leds = strip.pixels();
+                *old = strip;
+            }
+        }
+    }
+    settings.lat = new.lat;
+    settings.lon = new.lon;
+    settings.effect = new.effect;
+    settings.playlist = new.playlist;
+    if !cycling {
+        settings.palette = new.palette;
+    }
+    settings.palette_cycle = new.palette_cycle;
+    settings.palettes = new.palettes;
+    settings.layout = new.layout;
+    settings.transition = new.transition;
+    settings.speed = new.speed;
+    settings.params = new.params;
+    settings.schedule = new.schedule;
+    settings.zones = new.zones;
+    if changed {
+        for output in outputs.iter() {
+            let _ = output.messages.send(Message::Crossfade).await;
+        }
+    }
+    changed
+}
+
+/// What `run` shows for the command in `opt`.
+fn shown_by(opt: &Opt) -> Option<Show> {
+    match &opt.cmd {
+        Some(Command::Preset { name }) => Some(Show::Preset { name: name.clone() }),
+        Some(Command::Effect { name }) => Some(effect_shown(opt, name.clone())),
+        _ => None,
+    }
+}
+
+/// Effect `name` as run with the palette, speed and parameters of `opt`.
+fn effect_shown(opt: &Opt, name: String) -> Show {
+    Show::Effect {
+        name,
+        palette: opt.palette.clone(),
+        speed: opt.speed,
+        params: opt.params.iter().cloned().collect(),
+    }
+}
+
+/// The state to record while running `last`, with where the playlist and
+/// palette cycle have got to.
+fn running_state(
+    opt: &Opt,
+    last: &Option<Show>,
+    player: &Option<Player>,
+    cycler: &Option<Cycler>,
+) -> State {
+    State {
+        on: true,
+        last: last.clone(),
+        max_brightness: opt.max_brightness,
+        playing: player.as_ref().map(|p| p.current().effect.clone()),
+        palette: cycler.as_ref().map(|c| c.current().to_string()),
+    }
+}
+
+/// Write `state` unless it is what was last written to `saved`.
+fn keep_state(opt: &Opt, saved: &mut Option<State>, state: State) {
+    if saved.as_ref() == Some(&state) {
+        return;
+    }
+    match state.save(&opt.state_file) {
+        Ok(()) => *saved = Some(state),
+        Err(e) => warn!("unable to save state: {}", e),
+    }
+}
+
+fn start_playlist(settings: &Settings) -> Option<Player> {
+    let playlist = settings.playlist.clone()?;
+    let player = Player::new(playlist, Instant::now());
+    info!("playing {}", player.current());
+    Some(player)
+}
+
+fn start_cycle(settings: &Settings) -> Option<Cycler> {
+    let cycle = settings.palette_cycle.clone()?;
+    Some(Cycler::new(cycle, Instant::now()))
+}
+
+/// The effect playing: the playlist's current one, if there is a playlist.
+fn running<'a>(settings: &'a Settings, player: &'a Option<Player>) -> &'a str {
+    player
+        .as_ref()
+        .map_or(&settings.effect, |p| &p.current().effect)
+}
+
+/// Render a frame for every strip each `FRAME_PERIOD` until shutdown is
+/// requested, reloading the settings on SIGHUP. The state file follows the
+/// playlist and palette cycle as they move on, and is written once more on
+/// the way out.
+async fn ticker_task(
+    opt: &Opt,
+    mut settings: Settings,
+    mut location: (f64, f64),
+    mut outputs: Vec<Output>,
+    mut shutdown: watch::Receiver<bool>,
+    mut hangup: Signal,
+    mut tee: Option<FrameWriter>,
+) {
+    let mut lengths = animation_lengths(settings.layout, &outputs);
+    let mut player = start_playlist(&settings);
+    let mut cycler = start_cycle(&settings);
+    // Carry on where the same show got to before a restart.
+    let last = shown_by(opt);
+    let previous = State::load(&opt.state_file).unwrap_or_default();
+    if previous.last == last {
+        if let (Some(player), Some(effect)) = (player.as_mut(), &previous.playing) {
+            if player.skip_to(effect, Instant::now()) {
+                info!("playing {}", player.current());
+            }
+        }
+        if let (Some(cycler), Some(palette)) = (cycler.as_mut(), &previous.palette) {
+            if cycler.skip_to(palette, Instant::now()) {
+                settings.palette = Some(palette.clone());
+            }
+        }
+    }
+    let mut saved = None;
+    keep_state(opt, &mut saved, running_state(opt, &last, &player, &cycler));
+    let mut engine = Engine::new(
+        settings.effects(running(&settings, &player), &lengths),
+        Instant::now(),
+    );
+    engine.set_speed(settings.speed);
+
+    let mut interval = tokio::time::interval(FRAME_PERIOD);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    let mut state_interval = tokio::time::interval(STATE_PERIOD);
+    state_interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {}
+            _ = state_interval.tick() => {
+                keep_state(opt, &mut saved, running_state(opt, &last, &player, &cycler));
+                continue;
+            }
+            _ = hangup.recv() => {
+                match Settings::load(opt).and_then(|new| Ok((new.location()?, new))) {
+                    Ok((new_location, new)) => {
+                        location = new_location;
+                        let changed = reload(&mut settings, new, &mut outputs).await;
+                        let new_lengths = animation_lengths(settings.layout, &outputs);
+                        // Effects carry on through other changes, such as
+                        // to their speed.
+                        if changed || new_lengths != lengths {
+                            lengths = new_lengths;
+                            player = start_playlist(&settings);
+                            cycler = start_cycle(&settings);
+                            engine.set_effects(
+                                settings.effects(running(&settings, &player), &lengths),
+                                Instant::now(),
+                            );
+                        }
+                        engine.set_speed(settings.speed);
+                        keep_state(opt, &mut saved, running_state(opt, &last, &player, &cycler));
+                        info!("settings reloaded");
+                    }
+                    Err(e) => warn!("keeping current settings: {}", e),
+                }
+                continue;
+            }
+            _ = shutdown.changed() => break,
+        }
+
+        let tick = Instant::now();
+        if let Some(entry) = player.as_mut().and_then(|p| p.advance(tick)) {
+            info!("playing {}", entry);
+            let name = entry.effect.clone();
+            engine.set_effects(settings.effects(&name, &lengths), tick);
+            for output in outputs.iter() {
+                let _ = output.messages.send(Message::Crossfade).await;
+            }
+            keep_state(opt, &mut saved, running_state(opt, &last, &player, &cycler));
+        }
+        if let Some(palette) = cycler.as_mut().and_then(|c| c.advance(tick)) {
+            // The animation carries on, fading into the new colors.
+            info!("palette {}", palette);
+            settings.palette = Some(palette.to_string());
+            engine.swap_effects(settings.effects(running(&settings, &player), &lengths));
+            for output in outputs.iter() {
+                let _ = output.messages.send(Message::Crossfade).await;
+            }
+            keep_state(opt, &mut saved, running_state(opt, &last, &player, &cycler));
+        }
         let now = Local::now();
-        let (sunrise, sunset) =
-            sunrise::sunrise_sunset(opt.lat, opt.lon, now.year(), now.month(), now.day());
-        let sunrise = Utc.timestamp(sunrise, 0);
-        let sunset = Utc.timestamp(sunset, 0);
+        engine.tick(
+            tick,
+            settings.schedule.brightness_at(location.0, location.1, now),
+            settings.schedule.smoothing_seconds,
+        );
+        let level = engine.brightness();
+        let blue = settings.schedule.blue_at(now);
 
-        let now = Utc::now();
+        let frames = engine.frames();
+        for (i, output) in outputs.iter_mut().enumerate() {
+            let mut frame = match settings.layout {
+                Layout::Same => frames[0][..output.num_leds].to_vec(),
+                Layout::Independent => frames[i].clone(),
+            };
+            night_filter(&mut frame, blue);
+            record(&mut tee, i, &frame);
+            // Never wait on a slow strip; drop the frame and keep to the clock.
+            match output.messages.try_send(Message::Frame(frame, level)) {
+                Ok(()) => {}
+                Err(TrySendError::Full(_)) => {
+                    output.dropped_frames += 1;
+                    debug!(
+                        "output {} behind, {} frames dropped",
+                        i, output.dropped_frames
+                    );
+                }
+                Err(TrySendError::Closed(_)) => return,
+            }
+        }
+        flush_record(&mut tee);
+    }
+    save_state(opt, &running_state(opt, &last, &player, &cycler));
+    // Dropping the senders lets the output threads blank the strips and finish.
+}
 
-        let mut gamma: f64 = 255.0;
+/// Write frames to the strip as they arrive, through `fade`, then fade
+/// out and blank it once the ticker stops.
+fn output_thread(
+    mut strip: Strip,
+    mut messages: mpsc::Receiver<Message>,
+    mut fade: Crossfade,
+) -> Result<()> {
+    // Consecutive frames that failed to write; errors are logged once and
+    // the frame retried so a transient fault doesn't freeze the strip.
+    let mut failed_frames = 0;
+    let mut shown = Frame::new();
+    while let Some(message) = messages.blocking_recv() {
+        let mut frame = match message {
+            Message::Frame(frame, brightness) => {
+                strip.set_brightness(brightness);
+                frame
+            }
+            Message::Reconfigure(spec) => {
+                if let Err(e) = spec.apply(&mut strip) {
+                    warn!("unable to blank removed LEDs: {}", e);
+                }
+                continue;
+            }
+            Message::Crossfade => {
+                fade.start(shown.clone(), Instant::now());
+                continue;
+            }
+        };
+        fade.apply(&mut frame, Instant::now());
+        frame
+            .iter()
+            .enumerate()
+            .for_each(|(i, color)| strip.set_pixel16(i, *color));
+        match strip.show() {
+            Ok(()) if failed_frames > 0 => {
+                info!("recovered after {} failed frames", failed_frames);
+                failed_frames = 0;
+            }
+            Ok(()) => {}
+            Err(e) => {
+                if failed_frames == 0 {
+                    warn!("unable to write frame, retrying: {}", e);
+                }
+                failed_frames += 1;
+            }
+        }
+        shown = frame;
+    }
 
-        if now > sunrise && now < sunset {
-            // Lights don't operate during the day.
-            gamma = 0.0
-        } else if now < sunrise {
-            let delta = sunrise - now;
-            const TWO_HOURS: f64 = (60 * 60 * 2) as f64;
-            gamma = 255.0 - ((delta.num_seconds() as f64 * 255.0) / TWO_HOURS);
-        } else if now > sunset {
-            let delta = now - sunset;
-            const THREE_HOURS: f64 = (60 * 60 * 3) as f64;
-            gamma = 255.0 - ((delta.num_seconds() as f64 * 255.0) / THREE_HOURS);
+    if shown.iter().any(|c| *c != Color16::default()) {
+        fade.start(shown, Instant::now());
+        loop {
+            let mut frame = vec![Color16::default(); strip.len()];
+            if !fade.apply(&mut frame, Instant::now()) {
+                break;
+            }
+            frame
+                .iter()
+                .enumerate()
+                .for_each(|(i, color)| strip.set_pixel16(i, *color));
+            // Blanking below retries, so a failed frame ends the fade.
+            if strip.show().is_err() {
+                break;
+            }
+            thread::sleep(FRAME_PERIOD);
+        }
+    }
+    strip.clear();
+    let mut attempt = 1;
+    loop {
+        match strip.show() {
+            Ok(()) => return Ok(()),
+            Err(e) if attempt < BLANK_ATTEMPTS => {
+                warn!("unable to blank strip, retrying: {}", e);
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
         }
+    }
+}
 
-        hue.iter_mut().for_each(|v| {
-            *v += 0.20;
-            if *v >= 360.0 {
-                *v = 0.0;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identify_blinks_one_pixel() {
+        const OFF: (u8, u8, u8) = (0, 0, 0);
+        const WHITE: (u8, u8, u8) = (255, 255, 255);
+        assert_eq!(identify_frame(Some(2), false, 4, 0), [OFF, OFF, WHITE, OFF]);
+        assert_eq!(identify_frame(Some(2), false, 4, 1), [OFF; 4]);
+        assert_eq!(identify_frame(Some(2), false, 4, 2), [OFF, OFF, WHITE, OFF]);
+        // Past the end of the strip nothing lights.
+        assert_eq!(identify_frame(Some(4), false, 4, 0), [OFF; 4]);
+        assert_eq!(identify_frame(Some(99), false, 4, 0), [OFF; 4]);
+    }
+
+    #[test]
+    fn identify_shows_indices_in_binary() {
+        const BLUE: (u8, u8, u8) = (0, 0, 255);
+        const GREEN: (u8, u8, u8) = (0, 255, 0);
+        const RED: (u8, u8, u8) = (255, 0, 0);
+        for &(num_leds, bits) in &[(1, 1), (2, 1), (76, 7)] {
+            // A blue start frame, then green for each set bit and red for
+            // each clear one, lowest first, over and over.
+            assert_eq!(
+                identify_frame(None, true, num_leds, 0),
+                vec![BLUE; num_leds]
+            );
+            assert_eq!(
+                identify_frame(None, true, num_leds, bits + 1),
+                vec![BLUE; num_leds]
+            );
+            let mut read = vec![0; num_leds];
+            for bit in 0..bits {
+                let frame = identify_frame(None, true, num_leds, bit + 1);
+                for (i, pixel) in frame.iter().enumerate() {
+                    match *pixel {
+                        GREEN => read[i] |= 1 << bit,
+                        RED => {}
+                        other => panic!("{} LEDs, bit {}: {:?}", num_leds, bit, other),
+                    }
+                }
             }
-        });
+            assert_eq!(read, (0..num_leds).collect::<Vec<_>>());
+        }
+        assert_eq!(identify_frame(None, true, 76, 7)[64], GREEN);
+        assert_eq!(identify_frame(None, true, 76, 7)[63], RED);
+    }
 
-        let pixels = hue_to_pixels(&hue[..], &gamma_table, gamma);
-        send_pixels(&mut spi, &pixels).unwrap();
-        std::thread::sleep(std::time::Duration::from_millis(16));
+    #[test]
+    fn parses_hex_frames() {
+        let mut bytes = [0u8; 6];
+        parse_hex_frame("ff8000 0A0b0c\n", &mut bytes).unwrap();
+        assert_eq!(bytes, [0xff, 0x80, 0x00, 0x0a, 0x0b, 0x0c]);
+        parse_hex_frame(" 01 02\t03 0405 06 ", &mut bytes).unwrap();
+        assert_eq!(bytes, [1, 2, 3, 4, 5, 6]);
+
+        let error = |line: &str| {
+            let mut bytes = [0u8; 6];
+            match parse_hex_frame(line, &mut bytes) {
+                Err(Error::Input(message)) => message,
+                other => panic!("{:?} gave {:?}", line, other),
+            }
+        };
+        assert_eq!(error("ff8000"), "expected 12 hex digits per frame, got 6");
+        assert_eq!(
+            error("ff8000 0a0b0c0d"),
+            "expected 12 hex digits per frame, got 14"
+        );
+        assert_eq!(error(""), "expected 12 hex digits per frame, got 0");
+        assert_eq!(error("ff8000 0a0b0g"), "bad hex digit 'g'");
+        assert_eq!(error("+f+f+f 000000"), "bad hex digit '+'");
+        assert_eq!(error("-1ffff 000000"), "bad hex digit '-'");
+        assert_eq!(error("ff8000 0a0b0é"), "bad hex digit 'é'");
     }
 
-    let pixels = hue_to_pixels(&hue[..], &gamma_table, 0.0);
-    send_pixels(&mut spi, &pixels).unwrap();
+    #[test]
+    fn reports_every_settings_problem() {
+        let config =
+            std::env::temp_dir().join(format!("led-strip-check-{}.toml", std::process::id()));
+        fs::write(
+            &config,
+            "[schedule]\nsunrise_ramp_minutes = 0\n\n\
+             [palette_cycle]\npalettes = [\"ocean\"]\nseconds = 0\n",
+        )
+        .unwrap();
+        let opt = Opt::from_iter(&[
+            "led-strip",
+            "--config",
+            config.to_str().unwrap(),
+            "--driver",
+            "mock",
+            "--num-leds",
+            "0",
+            "--latitude",
+            "95",
+            "--transition=-1",
+            "--speed",
+            "1000",
+            "--param",
+            "span=-1",
+            "--param",
+            "sparkle=1",
+            "--zone",
+            "window:leds=0-3,color=red,effect=fire,brightness=2",
+            "--zone",
+            "shelf:leds=4-5,palette=nowhere",
+        ]);
+        let settings = Settings::load(&opt).unwrap();
+        fs::remove_file(&config).unwrap();
+        let problems = check_settings(&settings);
+        let expected = [
+            "latitude 95 is outside -90 to 90",
+            "--longitude",
+            "transition must be 0 or more seconds, not -1",
+            "speed must be from 0 to",
+            "rainbow: span must be from 0 to 100, not -1",
+            "parameter sparkle isn't read by rainbow",
+            "sunrise_ramp_minutes must be above 0, not 0",
+            "palette_cycle seconds must be above 0, not 0",
+            "strip 0: num_leds must be above 0",
+            "zone window: has both an effect and a color",
+            "zone window: brightness must be from 0 to 1, not 2",
+            "zone shelf: unknown palette nowhere",
+        ];
+        for message in &expected {
+            assert!(
+                problems.iter().any(|p| p.contains(message)),
+                "{} not in {:#?}",
+                message,
+                problems
+            );
+        }
+        assert_eq!(problems.len(), expected.len(), "{:#?}", problems);
+    }
 }
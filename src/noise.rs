@@ -0,0 +1,200 @@
+//! Coherent gradient noise, Ken Perlin's improved noise, for effects that
+//! should look organic: clouds, fire, lava, aurora.
+//!
+//! The `f64` functions return about -1 to 1 and are zero at every integer
+//! point. The `inoise8` functions follow FastLED: coordinates are 8.8
+//! fixed point, so 256 is one lattice step, and results run from 0 to 255.
+
+/// Perlin's permutation of 0 to 255, which hashes lattice points.
+const PERMUTATION: [u8; 256] = [
+    151, 160, 137, 91, 90, 15, 131, 13, 201, 95, 96, 53, 194, 233, 7, 225, 140, 36, 103, 30, 69,
+    142, 8, 99, 37, 240, 21, 10, 23, 190, 6, 148, 247, 120, 234, 75, 0, 26, 197, 62, 94, 252, 219,
+    203, 117, 35, 11, 32, 57, 177, 33, 88, 237, 149, 56, 87, 174, 20, 125, 136, 171, 168, 68, 175,
+    74, 165, 71, 134, 139, 48, 27, 166, 77, 146, 158, 231, 83, 111, 229, 122, 60, 211, 133, 230,
+    220, 105, 92, 41, 55, 46, 245, 40, 244, 102, 143, 54, 65, 25, 63, 161, 1, 216, 80, 73, 209, 76,
+    132, 187, 208, 89, 18, 169, 200, 196, 135, 130, 116, 188, 159, 86, 164, 100, 109, 198, 173,
+    186, 3, 64, 52, 217, 226, 250, 124, 123, 5, 202, 38, 147, 118, 126, 255, 82, 85, 212, 207, 206,
+    59, 227, 47, 16, 58, 17, 182, 189, 28, 42, 223, 183, 170, 213, 119, 248, 152, 2, 44, 154, 163,
+    70, 221, 153, 101, 155, 167, 43, 172, 9, 129, 22, 39, 253, 19, 98, 108, 110, 79, 113, 224, 232,
+    178, 185, 112, 104, 218, 246, 97, 228, 251, 34, 242, 193, 238, 210, 144, 12, 191, 179, 162,
+    241, 81, 51, 145, 235, 249, 14, 239, 107, 49, 192, 214, 31, 181, 199, 106, 157, 184, 84, 204,
+    176, 115, 121, 50, 45, 127, 4, 150, 254, 138, 236, 205, 93, 222, 114, 67, 29, 24, 72, 243, 141,
+    128, 195, 78, 66, 215, 61, 156, 180,
+];
+
+fn hash(i: i64) -> i64 {
+    i64::from(PERMUTATION[(i & 255) as usize])
+}
+
+/// Perlin's smootherstep, 6t^5 - 15t^4 + 10t^3.
+fn fade(t: f64) -> f64 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(t: f64, a: f64, b: f64) -> f64 {
+    a + t * (b - a)
+}
+
+/// The lattice cell holding `v` and how far into it `v` is.
+fn cell(v: f64) -> (i64, f64) {
+    let floor = v.floor();
+    (floor as i64, v - floor)
+}
+
+/// Slopes of eight sizes in either direction.
+fn grad1(hash: i64, x: f64) -> f64 {
+    let slope = ((hash & 7) + 1) as f64 / 8.0;
+    if hash & 8 == 0 {
+        slope * x
+    } else {
+        -slope * x
+    }
+}
+
+/// Eight directions: the diagonals and the axes.
+fn grad2(hash: i64, x: f64, y: f64) -> f64 {
+    match hash & 7 {
+        0 => x + y,
+        1 => -x + y,
+        2 => x - y,
+        3 => -x - y,
+        4 => x,
+        5 => -x,
+        6 => y,
+        _ => -y,
+    }
+}
+
+/// The twelve cube edge directions from Perlin's improved noise.
+fn grad3(hash: i64, x: f64, y: f64, z: f64) -> f64 {
+    let h = hash & 15;
+    let u = if h < 8 { x } else { y };
+    let v = if h < 4 {
+        y
+    } else if h == 12 || h == 14 {
+        x
+    } else {
+        z
+    };
+    let u = if h & 1 == 0 { u } else { -u };
+    let v = if h & 2 == 0 { v } else { -v };
+    u + v
+}
+
+/// Noise along a line, -1 to 1, one lattice step per unit of `x`.
+pub fn noise1(x: f64) -> f64 {
+    let (xi, xf) = cell(x);
+    let n = lerp(fade(xf), grad1(hash(xi), xf), grad1(hash(xi + 1), xf - 1.0));
+    // Slopes of at most 1 meet at no more than a half.
+    (n * 2.0).clamp(-1.0, 1.0)
+}
+
+/// Noise over a plane, -1 to 1, one lattice step per unit of `x` and `y`.
+pub fn noise2(x: f64, y: f64) -> f64 {
+    let (xi, xf) = cell(x);
+    let (yi, yf) = cell(y);
+    let (u, v) = (fade(xf), fade(yf));
+    let corner = |dx: i64, dy: i64| {
+        let h = hash(hash(xi + dx) + yi + dy);
+        grad2(h, xf - dx as f64, yf - dy as f64)
+    };
+    let n = lerp(
+        v,
+        lerp(u, corner(0, 0), corner(1, 0)),
+        lerp(u, corner(0, 1), corner(1, 1)),
+    );
+    n.clamp(-1.0, 1.0)
+}
+
+/// Noise through space, -1 to 1, one lattice step per unit of each axis.
+pub fn noise3(x: f64, y: f64, z: f64) -> f64 {
+    let (xi, xf) = cell(x);
+    let (yi, yf) = cell(y);
+    let (zi, zf) = cell(z);
+    let (u, v, w) = (fade(xf), fade(yf), fade(zf));
+    let corner = |dx: i64, dy: i64, dz: i64| {
+        let h = hash(hash(hash(xi + dx) + yi + dy) + zi + dz);
+        grad3(h, xf - dx as f64, yf - dy as f64, zf - dz as f64)
+    };
+    let layer = |dz: i64| {
+        lerp(
+            v,
+            lerp(u, corner(0, 0, dz), corner(1, 0, dz)),
+            lerp(u, corner(0, 1, dz), corner(1, 1, dz)),
+        )
+    };
+    lerp(w, layer(0), layer(1)).clamp(-1.0, 1.0)
+}
+
+/// Noise summed over `octaves`, each at twice the frequency and half the
+/// weight of the one before, for more detail; still about -1 to 1.
+pub fn fractal3(x: f64, y: f64, z: f64, octaves: u32) -> f64 {
+    let mut sum = 0.0;
+    let mut weight = 1.0;
+    let mut total = 0.0;
+    let mut scale = 1.0;
+    for _ in 0..octaves.max(1) {
+        sum += weight * noise3(x * scale, y * scale, z * scale);
+        total += weight;
+        weight /= 2.0;
+        scale *= 2.0;
+    }
+    sum / total
+}
+
+/// An 8.8 fixed point coordinate in lattice steps.
+fn fixed(v: u16) -> f64 {
+    f64::from(v) / 256.0
+}
+
+fn to_u8(n: f64) -> u8 {
+    ((n + 1.0) * 127.5).round() as u8
+}
+
+/// `noise1` at 8.8 fixed point `x`, 0 to 255 with 128 on the lattice.
+pub fn inoise8_1d(x: u16) -> u8 {
+    to_u8(noise1(fixed(x)))
+}
+
+/// `noise2` at 8.8 fixed point `x` and `y`, 0 to 255 with 128 on the lattice.
+pub fn inoise8_2d(x: u16, y: u16) -> u8 {
+    to_u8(noise2(fixed(x), fixed(y)))
+}
+
+/// `noise3` at 8.8 fixed point coordinates, 0 to 255 with 128 on the lattice.
+pub fn inoise8_3d(x: u16, y: u16, z: u16) -> u8 {
+    to_u8(noise3(fixed(x), fixed(y), fixed(z)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zero_on_the_lattice_and_smooth_between() {
+        for i in -3..3 {
+            let v = f64::from(i);
+            assert_eq!(noise1(v), 0.0);
+            assert_eq!(noise2(v, v + 1.0), 0.0);
+            assert_eq!(noise3(v, 2.0, v), 0.0);
+        }
+        assert_eq!(inoise8_3d(256, 512, 0), 128);
+
+        let (mut low, mut high) = (0.0f64, 0.0f64);
+        for i in 0..2000 {
+            let t = f64::from(i) * 0.013;
+            for &(a, b) in &[
+                (noise1(t), noise1(t + 0.001)),
+                (noise2(t, t * 0.7), noise2(t + 0.001, t * 0.7)),
+                (noise3(t, t * 0.3, 1.5), noise3(t + 0.001, t * 0.3, 1.5)),
+            ] {
+                assert!((a - b).abs() < 0.01, "jump at {}", t);
+                low = low.min(a);
+                high = high.max(a);
+            }
+        }
+        // It actually varies rather than staying near zero.
+        assert!(low < -0.3 && high > 0.3, "{} {}", low, high);
+        assert_eq!(noise3(0.3, 0.6, 0.9), noise3(0.3, 0.6, 0.9));
+    }
+}
@@ -0,0 +1,397 @@
+//! Gradients of colors for effects to draw from.
+//!
+//! Besides the built-in palettes, gradients can be loaded from WLED-style
+//! JSON, GIMP `.gpl` and cpt-city/GMT `.cpt` files.
+
+use crate::color::Color;
+use crate::error::{Error, Result};
+use crate::random::Rng;
+
+use serde::Deserialize;
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Names of the built-in palettes.
+pub const BUILTIN: &[&str] = &[
+    "heat",
+    "ocean",
+    "forest",
+    "party",
+    "lava",
+    "halloween",
+    "diwali",
+    "valentines",
+];
+
+/// Colors placed along a gradient from 0 to 1.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Palette {
+    stops: Vec<(f64, Color)>,
+}
+
+impl Palette {
+    /// A palette from `(position, color)` stops; positions are clamped to
+    /// 0 to 1 and may come in any order.
+    pub fn new(stops: Vec<(f64, Color)>) -> Self {
+        let mut stops: Vec<_> = stops
+            .into_iter()
+            .map(|(at, color)| (at.clamp(0.0, 1.0), color))
+            .collect();
+        stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Palette { stops }
+    }
+
+    /// A palette with `colors` spaced evenly from 0 to 1.
+    pub fn even(colors: &[Color]) -> Self {
+        let last = colors.len().saturating_sub(1).max(1) as f64;
+        Palette::new(
+            colors
+                .iter()
+                .enumerate()
+                .map(|(i, color)| (i as f64 / last, *color))
+                .collect(),
+        )
+    }
+
+    /// The built-in palette called `name`.
+    pub fn builtin(name: &str) -> Option<Palette> {
+        let colors: &[u32] = match name {
+            "heat" => &[0x000000, 0x800000, 0xff0000, 0xff8000, 0xffff00, 0xffffff],
+            "ocean" => &[0x000040, 0x0000a0, 0x0060c0, 0x00a0a0, 0x40e0d0, 0xc0ffff],
+            "forest" => &[0x003000, 0x006400, 0x228b22, 0x6b8e23, 0x9acd32, 0x2e8b57],
+            "party" => &[0x5500ab, 0x84007c, 0xb5004b, 0xe5001b, 0xe86010, 0xffab00],
+            "lava" => &[0x100000, 0x600000, 0xc01000, 0xff4000, 0xff9000, 0xffd040],
+            "halloween" => &[0xff5000, 0xff5000, 0x6800b0, 0x6800b0],
+            "diwali" => &[0xffc020, 0xff8000, 0xff3000, 0xd00060, 0xff8000],
+            "valentines" => &[0xff0018, 0xff4070, 0xffb0c0, 0xff4070],
+            _ => return None,
+        };
+        let colors: Vec<Color> = colors.iter().copied().map(Color::from_rgb).collect();
+        Some(Palette::even(&colors))
+    }
+
+    /// Read a palette file, choosing its format from the extension: .json,
+    /// .gpl or .cpt.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Palette> {
+        let path = path.as_ref();
+        let name = path.display().to_string();
+        let text = fs::read_to_string(path).map_err(|e| Error::open(&name, e))?;
+        let parsed = match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => parse_json(&text),
+            Some("gpl") => parse_gpl(&text),
+            Some("cpt") => parse_cpt(&text),
+            _ => Err("expected a .json, .gpl or .cpt file".to_string()),
+        };
+        match parsed {
+            Ok(palette) if !palette.stops.is_empty() => Ok(palette),
+            Ok(_) => Err(Error::Config {
+                path: name,
+                message: "palette has no colors".to_string(),
+            }),
+            Err(message) => Err(Error::Config {
+                path: name,
+                message,
+            }),
+        }
+    }
+
+    /// The color at `t` along the gradient, clamped to 0 to 1.
+    pub fn sample(&self, t: f64) -> Color {
+        let t = t.clamp(0.0, 1.0);
+        let next = self.stops.iter().position(|(at, _)| *at >= t);
+        match next {
+            None => self.stops.last().map_or(Color::BLACK, |s| s.1),
+            Some(0) => self.stops[0].1,
+            Some(i) => {
+                let (from, start) = self.stops[i - 1];
+                let (to, end) = self.stops[i];
+                let span = to - from;
+                if span <= 0.0 {
+                    end
+                } else {
+                    start.lerp(end, (t - from) / span)
+                }
+            }
+        }
+    }
+
+    /// Sample the gradient out and back as `t` runs from 0 to 1, so
+    /// effects that cycle through it never jump from one end to the other.
+    pub fn sample_cyclic(&self, t: f64) -> Color {
+        let t = t.rem_euclid(1.0) * 2.0;
+        self.sample(if t > 1.0 { 2.0 - t } else { t })
+    }
+}
+
+/// A channel value from a palette file.
+fn parse_channel(s: &str) -> std::result::Result<u8, String> {
+    s.parse().map_err(|_| format!("bad color channel {:?}", s))
+}
+
+/// WLED's custom palette JSON: `{"palette": [...]}` holding positions from
+/// 0 to 255, each followed by either a color string such as "rrggbb" or
+/// red, green and blue numbers.
+fn parse_json(text: &str) -> std::result::Result<Palette, String> {
+    let value: Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+    let items = value
+        .get("palette")
+        .and_then(Value::as_array)
+        .ok_or("expected a \"palette\" array")?;
+    let number = |v: Option<&Value>| {
+        v.and_then(Value::as_u64)
+            .filter(|n| *n <= 255)
+            .ok_or("expected numbers from 0 to 255".to_string())
+    };
+    let mut stops = Vec::new();
+    let mut items = items.iter();
+    while let Some(position) = items.next() {
+        let at = number(Some(position))? as f64 / 255.0;
+        let color = match items.next() {
+            Some(Value::String(color)) => color.parse()?,
+            red => Color::new(
+                number(red)? as u8,
+                number(items.next())? as u8,
+                number(items.next())? as u8,
+            ),
+        };
+        stops.push((at, color));
+    }
+    Ok(Palette::new(stops))
+}
+
+/// A GIMP palette: a "GIMP Palette" header and then one "red green blue
+/// name" line per color, spaced evenly along the gradient.
+fn parse_gpl(text: &str) -> std::result::Result<Palette, String> {
+    let mut lines = text.lines();
+    if lines.next().map(str::trim) != Some("GIMP Palette") {
+        return Err("missing \"GIMP Palette\" header".to_string());
+    }
+    let mut colors = Vec::new();
+    for line in lines {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.contains(':') {
+            continue;
+        }
+        let channels: Vec<&str> = line.split_whitespace().take(3).collect();
+        if channels.len() < 3 {
+            return Err(format!("bad color line {:?}", line));
+        }
+        colors.push(Color::new(
+            parse_channel(channels[0])?,
+            parse_channel(channels[1])?,
+            parse_channel(channels[2])?,
+        ));
+    }
+    Ok(Palette::even(&colors))
+}
+
+/// A cpt-city/GMT color table: one "z red green blue z red green blue"
+/// line per slice, with the z values scaled to run from 0 to 1. Channels
+/// may also be written "red/green/blue".
+fn parse_cpt(text: &str) -> std::result::Result<Palette, String> {
+    let mut slices = Vec::new();
+    for line in text.lines() {
+        let line = line.trim();
+        // Comments, and the background, foreground and NaN colors.
+        if line.is_empty() || line.starts_with(['#', 'B', 'F', 'N']) {
+            continue;
+        }
+        let fields: Vec<&str> = line
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .filter(|f| !f.is_empty())
+            .collect();
+        if fields.len() < 8 {
+            return Err(format!("bad slice line {:?}", line));
+        }
+        let z = |f: &str| {
+            f.parse::<f64>()
+                .map_err(|_| format!("bad position {:?}", f))
+        };
+        let color = |f: &[&str]| -> std::result::Result<Color, String> {
+            Ok(Color::new(
+                parse_channel(f[0])?,
+                parse_channel(f[1])?,
+                parse_channel(f[2])?,
+            ))
+        };
+        slices.push((z(fields[0])?, color(&fields[1..4])?));
+        slices.push((z(fields[4])?, color(&fields[5..8])?));
+    }
+    let low = slices.iter().map(|s| s.0).fold(f64::INFINITY, f64::min);
+    let high = slices.iter().map(|s| s.0).fold(f64::NEG_INFINITY, f64::max);
+    let span = if high > low { high - low } else { 1.0 };
+    Ok(Palette::new(
+        slices
+            .into_iter()
+            .map(|(z, color)| ((z - low) / span, color))
+            .collect(),
+    ))
+}
+
+/// Palettes moved through in place of a single one, fading to the next
+/// every so often.
+///
+/// ```toml
+/// [palette_cycle]
+/// palettes = ["ocean", "forest", "party"]
+/// seconds = 300
+/// shuffle = true
+/// ```
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PaletteCycle {
+    /// Names of the palettes, built in or loaded.
+    pub palettes: Vec<String>,
+    /// Seconds each palette is shown for.
+    pub seconds: f64,
+    /// Move to any other palette at random instead of the next in turn.
+    pub shuffle: bool,
+}
+
+impl Default for PaletteCycle {
+    fn default() -> Self {
+        PaletteCycle {
+            palettes: Vec::new(),
+            seconds: 300.0,
+            shuffle: false,
+        }
+    }
+}
+
+impl PaletteCycle {
+    /// Settings that can't work, described for the user.
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if !(self.seconds > 0.0 && self.seconds.is_finite()) {
+            problems.push(format!(
+                "palette_cycle seconds must be above 0, not {}",
+                self.seconds
+            ));
+        }
+        problems
+    }
+}
+
+/// Where a palette cycle has got to.
+#[derive(Debug, Clone)]
+pub struct Cycler {
+    cycle: PaletteCycle,
+    position: usize,
+    started: Instant,
+    rng: Rng,
+}
+
+impl Cycler {
+    /// Start `cycle`, which must not be empty, at `now` on its first
+    /// palette.
+    pub fn new(cycle: PaletteCycle, now: Instant) -> Self {
+        Cycler {
+            cycle,
+            position: 0,
+            started: now,
+            rng: Rng::from_time(),
+        }
+    }
+
+    pub fn current(&self) -> &str {
+        &self.cycle.palettes[self.position]
+    }
+
+    /// Carry on from `palette` at `now`, returning whether the cycle has
+    /// it.
+    pub fn skip_to(&mut self, palette: &str, now: Instant) -> bool {
+        match self.cycle.palettes.iter().position(|p| p == palette) {
+            Some(position) => {
+                self.position = position;
+                self.started = now;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Move on once the current palette's time is up at `now`, giving the
+    /// palette that follows. Times that can't work, which `config check`
+    /// reports, never move on.
+    pub fn advance(&mut self, now: Instant) -> Option<&str> {
+        let shown = Duration::try_from_secs_f64(self.cycle.seconds)
+            .ok()
+            .filter(|d| !d.is_zero())
+            .unwrap_or(Duration::MAX);
+        if now.saturating_duration_since(self.started) < shown {
+            return None;
+        }
+        let len = self.cycle.palettes.len();
+        self.position = if self.cycle.shuffle && len > 1 {
+            // Any palette but this one.
+            (self.position + 1 + self.rng.below(len - 1)) % len
+        } else {
+            (self.position + 1) % len
+        };
+        self.started = now;
+        Some(self.current())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sample_blends_between_stops() {
+        let palette = Palette::new(vec![
+            (1.0, Color::new(0, 0, 200)),
+            (0.0, Color::new(200, 0, 0)),
+            (0.5, Color::new(0, 200, 0)),
+        ]);
+        assert_eq!(palette.sample(-1.0), Color::new(200, 0, 0));
+        assert_eq!(palette.sample(0.25), Color::new(100, 100, 0));
+        assert_eq!(palette.sample(0.5), Color::new(0, 200, 0));
+        assert_eq!(palette.sample(2.0), Color::new(0, 0, 200));
+        assert_eq!(palette.sample_cyclic(0.25), palette.sample_cyclic(0.75));
+        assert!(BUILTIN.iter().all(|name| Palette::builtin(name).is_some()));
+    }
+
+    #[test]
+    fn cycles_through_palettes() {
+        let now = Instant::now();
+        let mut cycle = PaletteCycle {
+            palettes: vec!["heat".to_string(), "ocean".to_string(), "lava".to_string()],
+            seconds: 60.0,
+            shuffle: false,
+        };
+        let mut cycler = Cycler::new(cycle.clone(), now);
+        assert_eq!(cycler.current(), "heat");
+        assert_eq!(cycler.advance(now + Duration::from_secs(59)), None);
+        let later = now + Duration::from_secs(60);
+        assert_eq!(cycler.advance(later), Some("ocean"));
+        assert_eq!(
+            cycler.advance(later + Duration::from_secs(60)),
+            Some("lava")
+        );
+        assert!(cycler.skip_to("ocean", now));
+        assert!(!cycler.skip_to("forest", now));
+        assert_eq!(cycler.current(), "ocean");
+
+        cycle.shuffle = true;
+        let mut cycler = Cycler::new(cycle, now);
+        for minute in 1..20 {
+            let last = cycler.current().to_string();
+            let next = cycler.advance(now + Duration::from_secs(minute * 60));
+            assert_ne!(next, Some(last.as_str()));
+        }
+    }
+
+    #[test]
+    fn file_formats() {
+        let json = parse_json(r#"{"palette": [0, "ff0000", 255, 0, 0, 255]}"#).unwrap();
+        let gpl = parse_gpl("GIMP Palette\nName: test\n#\n255 0 0 red\n  0 0 255 blue\n").unwrap();
+        let cpt = parse_cpt("# test\n-1 255 0 0 1 0/0/255\nB 0 0 0\n").unwrap();
+        let expected = Palette::even(&[Color::new(255, 0, 0), Color::new(0, 0, 255)]);
+        assert_eq!(json, expected);
+        assert_eq!(gpl, expected);
+        assert_eq!(cpt, expected);
+    }
+}
@@ -0,0 +1,191 @@
+//! Effects run one after another, each for a while.
+//!
+//! ```toml
+//! [playlist]
+//! # Seconds, minutes or hours; a bare number is seconds.
+//! effects = ["rainbow: 5m", "rainbow: 90s"]
+//! shuffle = true
+//! ```
+
+use crate::random::Rng;
+
+use serde::Deserialize;
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+/// One effect in a playlist and how long it runs for.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub effect: String,
+    pub duration: Duration,
+}
+
+/// Read "90", "90s", "1.5m" or "2h".
+fn parse_duration(s: &str) -> std::result::Result<Duration, String> {
+    let s = s.trim();
+    let (number, unit) = match s.find(|c: char| c.is_ascii_alphabetic()) {
+        Some(at) => s.split_at(at),
+        None => (s, "s"),
+    };
+    let scale = match unit {
+        "s" => 1.0,
+        "m" => 60.0,
+        "h" => 3600.0,
+        _ => {
+            return Err(format!(
+                "unknown unit {} in {}, expected s, m or h",
+                unit, s
+            ))
+        }
+    };
+    number
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .and_then(|n| Duration::try_from_secs_f64(n * scale).ok())
+        .filter(|d| !d.is_zero())
+        .ok_or_else(|| format!("bad duration {}", s))
+}
+
+impl FromStr for Entry {
+    type Err = String;
+
+    /// Read "effect: duration".
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let (effect, duration) = s
+            .split_once(':')
+            .ok_or_else(|| format!("expected effect: duration, not {}", s))?;
+        Ok(Entry {
+            effect: effect.trim().to_string(),
+            duration: parse_duration(duration)?,
+        })
+    }
+}
+
+impl fmt::Display for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let seconds = self.duration.as_secs_f64();
+        if seconds % 3600.0 == 0.0 {
+            write!(f, "{}: {}h", self.effect, seconds / 3600.0)
+        } else if seconds % 60.0 == 0.0 {
+            write!(f, "{}: {}m", self.effect, seconds / 60.0)
+        } else {
+            write!(f, "{}: {}s", self.effect, seconds)
+        }
+    }
+}
+
+/// Effects to cycle through in place of a single one.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Playlist {
+    pub effects: Vec<Entry>,
+    /// Play the effects in a new random order each time round.
+    pub shuffle: bool,
+}
+
+/// Where a playlist has got to.
+#[derive(Debug, Clone)]
+pub struct Player {
+    playlist: Playlist,
+    /// Indices into the playlist in the order they play this time round.
+    order: Vec<usize>,
+    position: usize,
+    started: Instant,
+    rng: Rng,
+}
+
+impl Player {
+    /// Start `playlist`, which must not be empty, at `now`.
+    pub fn new(playlist: Playlist, now: Instant) -> Self {
+        let mut player = Player {
+            order: (0..playlist.effects.len()).collect(),
+            playlist,
+            position: 0,
+            started: now,
+            rng: Rng::from_time(),
+        };
+        if player.playlist.shuffle {
+            player.rng.shuffle(&mut player.order);
+        }
+        player
+    }
+
+    pub fn current(&self) -> &Entry {
+        &self.playlist.effects[self.order[self.position]]
+    }
+
+    /// Carry on from the first entry running `effect` this time round, at
+    /// `now`, returning whether there is one.
+    pub fn skip_to(&mut self, effect: &str, now: Instant) -> bool {
+        let effects = &self.playlist.effects;
+        match self.order.iter().position(|&i| effects[i].effect == effect) {
+            Some(position) => {
+                self.position = position;
+                self.started = now;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Move on once the current effect's time is up at `now`, giving the
+    /// effect that follows.
+    pub fn advance(&mut self, now: Instant) -> Option<&Entry> {
+        if now.saturating_duration_since(self.started) < self.current().duration {
+            return None;
+        }
+        let last = self.order[self.position];
+        self.position += 1;
+        if self.position == self.order.len() {
+            self.position = 0;
+            if self.playlist.shuffle {
+                self.rng.shuffle(&mut self.order);
+                // Don't play the same effect twice running.
+                if self.order.len() > 1 && self.order[0] == last {
+                    self.order.swap(0, 1);
+                }
+            }
+        }
+        self.started = now;
+        Some(self.current())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cycles_through_entries() {
+        let entries: Vec<Entry> = ["rainbow: 5m", "fire:90", "twinkle: 1.5h"]
+            .iter()
+            .map(|s| s.parse().unwrap())
+            .collect();
+        assert_eq!(entries[1].duration, Duration::from_secs(90));
+        assert_eq!(entries[2].to_string(), "twinkle: 90m");
+        assert_eq!(entries[1].to_string(), "fire: 90s");
+        assert!("rainbow".parse::<Entry>().is_err());
+        assert!("rainbow: 5d".parse::<Entry>().is_err());
+        assert!("rainbow: 0s".parse::<Entry>().is_err());
+
+        let now = Instant::now();
+        let playlist = Playlist {
+            effects: entries,
+            shuffle: false,
+        };
+        let mut player = Player::new(playlist, now);
+        assert_eq!(player.current().effect, "rainbow");
+        assert!(player.advance(now + Duration::from_secs(299)).is_none());
+        let later = now + Duration::from_secs(300);
+        assert_eq!(player.advance(later).unwrap().effect, "fire");
+        let later = later + Duration::from_secs(90);
+        assert_eq!(player.advance(later).unwrap().effect, "twinkle");
+        let later = later + Duration::from_secs(5400);
+        assert_eq!(player.advance(later).unwrap().effect, "rainbow");
+        assert!(player.skip_to("twinkle", later));
+        assert!(!player.skip_to("snow", later));
+        assert_eq!(player.current().effect, "twinkle");
+    }
+}
@@ -0,0 +1,80 @@
+//! A small, fast pseudo-random generator for effects and shuffling; not for
+//! anything that must be unpredictable.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Vigna's xorshift64*.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    /// The same `seed` always gives the same numbers.
+    pub fn new(seed: u64) -> Self {
+        // SplitMix64 spreads similar seeds apart and never leaves the
+        // state zero, which xorshift can't leave.
+        let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        Rng {
+            state: (z ^ (z >> 31)).max(1),
+        }
+    }
+
+    /// Seeded from the clock, for a different sequence each run.
+    pub fn from_time() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64);
+        Rng::new(nanos)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// A number from 0 to just under 1.
+    pub fn unit(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// A number from 0 to `n - 1`; 0 when `n` is 0.
+    pub fn below(&mut self, n: usize) -> usize {
+        ((u128::from(self.next_u64()) * n as u128) >> 64) as usize
+    }
+
+    /// Put `items` in a random order.
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.below(i + 1));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeatable_and_in_range() {
+        let (mut a, mut b) = (Rng::new(7), Rng::new(7));
+        assert_eq!(a.next_u64(), b.next_u64());
+        assert_ne!(Rng::new(0).next_u64(), Rng::new(1).next_u64());
+        for _ in 0..1000 {
+            let unit = a.unit();
+            assert!((0.0..1.0).contains(&unit));
+            assert!(a.below(3) < 3);
+        }
+        assert_eq!(a.below(0), 0);
+
+        let mut items: Vec<usize> = (0..10).collect();
+        a.shuffle(&mut items);
+        assert_ne!(items, (0..10).collect::<Vec<_>>());
+        items.sort();
+        assert_eq!(items, (0..10).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,338 @@
+//! Brightness that follows sunrise and sunset.
+
+use crate::easing::Easing;
+
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, NaiveTime, TimeZone, Utc};
+use serde::de::{self, Deserializer};
+use serde::Deserialize;
+
+/// Default end of night mode when only its start is set.
+const NIGHT_END: (u32, u32) = (7, 0);
+
+/// How brightness follows the sun.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Schedule {
+    /// Minutes before sunrise over which the strip fades in, reaching full
+    /// brightness at sunrise before turning off for the day.
+    pub sunrise_ramp_minutes: f64,
+    /// Minutes after sunset over which the strip fades out.
+    pub sunset_ramp_minutes: f64,
+    /// Shape of the sunrise, sunset and night ramps.
+    pub ramp_easing: Easing,
+    /// Seconds over which changes in brightness are smoothed, so its steps
+    /// don't show at low levels; 0 follows the schedule exactly.
+    pub smoothing_seconds: f64,
+    /// Local time, such as "21:00", after which blue is faded down for the
+    /// night; without one colors are left alone.
+    #[serde(deserialize_with = "deserialize_time")]
+    pub night_start: Option<NaiveTime>,
+    /// Local time blue is back to full the next morning [default: 07:00].
+    #[serde(deserialize_with = "deserialize_time")]
+    pub night_end: Option<NaiveTime>,
+    /// Minutes blue takes to fade down after `night_start` and back up
+    /// before `night_end`.
+    pub night_ramp_minutes: f64,
+    /// Fraction of blue left at night before gamma correction, 0 to 1.
+    pub night_blue: f64,
+}
+
+/// `duration` in seconds, to the millisecond.
+fn seconds(duration: Duration) -> f64 {
+    duration.num_milliseconds() as f64 / 1000.0
+}
+
+/// Brightness eased towards the schedule's, frame by frame.
+#[derive(Debug, Clone, Default)]
+pub struct Smoothed {
+    value: Option<f64>,
+}
+
+impl Smoothed {
+    /// Move towards `target`, clamped to 0 to 255, `elapsed` seconds after
+    /// the last update, closing about two thirds of the gap every
+    /// `time_constant` seconds. The first target is taken as it is.
+    pub fn update(&mut self, target: f64, elapsed: f64, time_constant: f64) -> f64 {
+        let target = target.clamp(0.0, 255.0);
+        let value = match self.value {
+            Some(value) if time_constant > 0.0 => {
+                value + (target - value) * (1.0 - (-elapsed / time_constant).exp())
+            }
+            _ => target,
+        };
+        self.value = Some(value);
+        value
+    }
+}
+
+/// Read a local time written "HH:MM".
+fn deserialize_time<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Option<NaiveTime>, D::Error> {
+    let s = String::deserialize(deserializer)?;
+    NaiveTime::parse_from_str(&s, "%H:%M")
+        .map(Some)
+        .map_err(|e| de::Error::custom(format!("bad time {}: {}", s, e)))
+}
+
+impl Default for Schedule {
+    fn default() -> Self {
+        Schedule {
+            sunrise_ramp_minutes: 120.0,
+            sunset_ramp_minutes: 180.0,
+            ramp_easing: Easing::default(),
+            smoothing_seconds: 2.0,
+            night_start: None,
+            night_end: None,
+            night_ramp_minutes: 60.0,
+            night_blue: 0.3,
+        }
+    }
+}
+
+/// Sunrise and sunset on `date` at the given location.
+pub fn sun_times(lat: f64, lon: f64, date: NaiveDate) -> (DateTime<Utc>, DateTime<Utc>) {
+    let (sunrise, sunset) =
+        sunrise::sunrise_sunset(lat, lon, date.year(), date.month(), date.day());
+    (Utc.timestamp(sunrise, 0), Utc.timestamp(sunset, 0))
+}
+
+impl Schedule {
+    /// Settings that can't work, described for the user.
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.sunrise_ramp_minutes <= 0.0 || self.sunrise_ramp_minutes.is_nan() {
+            problems.push(format!(
+                "sunrise_ramp_minutes must be above 0, not {}",
+                self.sunrise_ramp_minutes
+            ));
+        }
+        if self.sunset_ramp_minutes <= 0.0 || self.sunset_ramp_minutes.is_nan() {
+            problems.push(format!(
+                "sunset_ramp_minutes must be above 0, not {}",
+                self.sunset_ramp_minutes
+            ));
+        }
+        if !(self.smoothing_seconds >= 0.0 && self.smoothing_seconds.is_finite()) {
+            problems.push(format!(
+                "smoothing_seconds must be 0 or more, not {}",
+                self.smoothing_seconds
+            ));
+        }
+        if self.night_start.is_some() {
+            if self.night_ramp_minutes <= 0.0 || self.night_ramp_minutes.is_nan() {
+                problems.push(format!(
+                    "night_ramp_minutes must be above 0, not {}",
+                    self.night_ramp_minutes
+                ));
+            }
+            if !(0.0..=1.0).contains(&self.night_blue) {
+                problems.push(format!(
+                    "night_blue must be from 0 to 1, not {}",
+                    self.night_blue
+                ));
+            }
+        }
+        problems
+    }
+
+    /// When night mode runs, if it's on.
+    pub fn night(&self) -> Option<(NaiveTime, NaiveTime)> {
+        let end = NaiveTime::from_hms(NIGHT_END.0, NIGHT_END.1, 0);
+        Some((self.night_start?, self.night_end.unwrap_or(end)))
+    }
+
+    /// Multiplier for blue at `now`: 1 during the day, easing down to
+    /// `night_blue` over the ramp after night starts and back up before it
+    /// ends.
+    pub fn blue_at(&self, now: DateTime<Local>) -> f64 {
+        let (start, end) = match self.night() {
+            Some(night) => night,
+            None => return 1.0,
+        };
+        // Seconds into the night and its length, wrapping past midnight.
+        let day = 24 * 60 * 60;
+        let into = now
+            .time()
+            .signed_duration_since(start)
+            .num_seconds()
+            .rem_euclid(day);
+        let length = end
+            .signed_duration_since(start)
+            .num_seconds()
+            .rem_euclid(day);
+        if into >= length {
+            return 1.0;
+        }
+        let from_edge = into.min(length - into) as f64;
+        let depth = self
+            .ramp_easing
+            .apply(from_edge / (self.night_ramp_minutes * 60.0));
+        1.0 - depth * (1.0 - self.night_blue.clamp(0.0, 1.0))
+    }
+
+    /// Brightness from 0 to 255 at `now`, using that local day's sunrise and
+    /// sunset. The value goes negative once a ramp has finished; within a
+    /// ramp it follows `ramp_easing`.
+    pub fn brightness_at(&self, lat: f64, lon: f64, now: DateTime<Local>) -> f64 {
+        let (sunrise, sunset) = sun_times(lat, lon, now.naive_local().date());
+        let now = now.with_timezone(&Utc);
+
+        let mut brightness: f64 = 255.0;
+
+        if now > sunrise && now < sunset {
+            // Lights don't operate during the day.
+            brightness = 0.0
+        } else if now < sunrise {
+            let delta = sunrise - now;
+            let ramp = self.sunrise_ramp_minutes * 60.0;
+            brightness = 255.0 - ((seconds(delta) * 255.0) / ramp);
+        } else if now > sunset {
+            let delta = now - sunset;
+            let ramp = self.sunset_ramp_minutes * 60.0;
+            brightness = 255.0 - ((seconds(delta) * 255.0) / ramp);
+        }
+        if (0.0..=255.0).contains(&brightness) {
+            brightness = 255.0 * self.ramp_easing.apply(brightness / 255.0);
+        }
+        brightness
+    }
+
+    /// Brightness from local midnight on `date`, every `step`.
+    pub fn day_curve(
+        &self,
+        lat: f64,
+        lon: f64,
+        date: NaiveDate,
+        step: Duration,
+    ) -> Vec<(DateTime<Local>, f64)> {
+        let start = match Local.from_local_datetime(&date.and_hms(0, 0, 0)).earliest() {
+            Some(start) => start,
+            None => return Vec::new(),
+        };
+        let end = start + Duration::days(1);
+        let mut curve = Vec::new();
+        let mut time = start;
+        while time < end {
+            curve.push((time, self.brightness_at(lat, lon, time)));
+            time = time + step;
+        }
+        curve
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Offset;
+
+    /// Local time `hour:minute` on `day` of January 2024.
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Local> {
+        let time = NaiveDate::from_ymd(2024, 1, day).and_hms(hour, minute, 0);
+        Local.from_local_datetime(&time).earliest().unwrap()
+    }
+
+    #[test]
+    fn brightness_ramps_around_the_night() {
+        let schedule = Schedule {
+            sunrise_ramp_minutes: 120.0,
+            sunset_ramp_minutes: 180.0,
+            ramp_easing: Easing::Linear,
+            ..Schedule::default()
+        };
+        // On the equator at the longitude of the local time zone, the sun
+        // is up from about 06:00 to 18:00 local time.
+        let date = NaiveDate::from_ymd(2024, 1, 20);
+        let offset = at(20, 12, 0).offset().fix().local_minus_utc();
+        let (lat, lon) = (0.0, f64::from(offset) / 240.0);
+        let (sunrise, sunset) = sun_times(lat, lon, date);
+        let (sunrise, sunset) = (sunrise.with_timezone(&Local), sunset.with_timezone(&Local));
+        assert_eq!(
+            (sunrise.naive_local().date(), sunset.naive_local().date()),
+            (date, date)
+        );
+        let brightness = |time: DateTime<Local>| schedule.brightness_at(lat, lon, time);
+        let near = |time, wanted: f64| {
+            let got = brightness(time);
+            assert!((got - wanted).abs() < 0.1, "{} {} {}", time, got, wanted);
+        };
+
+        // Rising to full as sunrise gets closer.
+        assert!(brightness(sunrise - Duration::hours(3)) < 0.0);
+        near(sunrise - Duration::minutes(90), 63.75);
+        near(sunrise - Duration::minutes(60), 127.5);
+        near(sunrise - Duration::seconds(1), 255.0);
+        // Off through the day.
+        assert_eq!(brightness(sunrise + Duration::seconds(1)), 0.0);
+        assert_eq!(brightness(at(20, 12, 0)), 0.0);
+        assert_eq!(brightness(sunset - Duration::seconds(1)), 0.0);
+        // Falling from full after sunset.
+        near(sunset + Duration::seconds(1), 255.0);
+        near(sunset + Duration::minutes(90), 127.5);
+        near(sunset + Duration::minutes(135), 63.75);
+        assert!(brightness(sunset + Duration::hours(4)) < 0.0);
+    }
+
+    #[test]
+    fn smoothing_closes_in_without_overshooting() {
+        let mut smoothed = Smoothed::default();
+        assert_eq!(smoothed.update(40.0, 0.0, 2.0), 40.0);
+        // At 60 frames a second over ten time constants it gets closer
+        // every frame and never passes the target.
+        let mut last = 40.0;
+        for _ in 0..1200 {
+            let value = smoothed.update(200.0, 1.0 / 60.0, 2.0);
+            assert!(value > last && value <= 200.0, "{} {}", last, value);
+            last = value;
+        }
+        assert!(200.0 - last < 0.01, "{}", last);
+        // About two thirds of the way after one time constant.
+        let value = smoothed.update(0.0, 2.0, 2.0);
+        assert!((value - 200.0 / 1f64.exp()).abs() < 0.01, "{}", value);
+        // However long the gap it lands on the target, not past it.
+        assert!(smoothed.update(100.0, 1.0e6, 2.0) <= 100.0);
+
+        assert_eq!(smoothed.update(300.0, 0.0, 2.0), 100.0);
+        assert_eq!(smoothed.update(300.0, 0.1, 0.0), 255.0);
+        assert_eq!(smoothed.update(-5.0, 0.1, 0.0), 0.0);
+    }
+
+    #[test]
+    fn blue_fades_over_the_night() {
+        let mut schedule = Schedule {
+            ramp_easing: Easing::Linear,
+            night_start: Some(NaiveTime::from_hms(21, 0, 0)),
+            night_end: Some(NaiveTime::from_hms(7, 0, 0)),
+            night_ramp_minutes: 60.0,
+            night_blue: 0.3,
+            ..Schedule::default()
+        };
+        for &((day, hour, minute), blue) in &[
+            ((15, 12, 0), 1.0),
+            ((15, 20, 59), 1.0),
+            ((15, 21, 0), 1.0),
+            ((15, 21, 30), 0.65),
+            ((15, 22, 0), 0.3),
+            // Past midnight the same night carries on.
+            ((16, 0, 0), 0.3),
+            ((16, 2, 0), 0.3),
+            ((16, 6, 0), 0.3),
+            ((16, 6, 30), 0.65),
+            ((16, 7, 0), 1.0),
+            ((16, 9, 0), 1.0),
+        ] {
+            let got = schedule.blue_at(at(day, hour, minute));
+            assert!((got - blue).abs() < 1e-9, "{}:{:02} {}", hour, minute, got);
+        }
+
+        // A night that doesn't cross midnight stays within the day.
+        schedule.night_start = Some(NaiveTime::from_hms(1, 0, 0));
+        schedule.night_end = Some(NaiveTime::from_hms(5, 0, 0));
+        assert_eq!(schedule.blue_at(at(15, 23, 0)), 1.0);
+        assert!((schedule.blue_at(at(15, 3, 0)) - 0.3).abs() < 1e-9);
+        assert_eq!(schedule.blue_at(at(15, 6, 0)), 1.0);
+
+        schedule.night_start = None;
+        assert_eq!(schedule.blue_at(at(15, 3, 0)), 1.0);
+    }
+}
@@ -0,0 +1,206 @@
+//! What the strips were last showing, kept on disk between commands.
+
+use crate::error::{Error, Result};
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+/// State file used when none is given.
+pub const DEFAULT_STATE_FILE: &str = "/var/lib/blink/state.toml";
+
+/// Something the strips can show.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "lowercase")]
+pub enum Show {
+    /// A solid color as red, green and blue.
+    Color { color: [u8; 3] },
+    /// Individually set pixels, shown on every strip.
+    Pixels { pixels: Vec<[u8; 3]> },
+    /// A running effect, with the --palette, --speed and --param values it
+    /// was run with.
+    Effect {
+        name: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        palette: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        speed: Option<f64>,
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        params: BTreeMap<String, String>,
+    },
+    /// A preset from the config file.
+    Preset { name: String },
+}
+
+impl Show {
+    /// Read the scene `name` saved beside `state_file`.
+    pub fn load_scene<P: AsRef<Path>>(state_file: P, name: &str) -> Result<Show> {
+        let path = scene_path(state_file.as_ref(), name)?;
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                return Err(Error::State {
+                    path: path.display().to_string(),
+                    message: format!("no scene named {}", name),
+                })
+            }
+            Err(e) => return Err(Error::open(&path.display().to_string(), e)),
+        };
+        toml::from_str(&text).map_err(|e| Error::State {
+            path: path.display().to_string(),
+            message: e.to_string(),
+        })
+    }
+
+    /// Save this as the scene `name` beside `state_file`, replacing any
+    /// scene of that name.
+    pub fn save_scene<P: AsRef<Path>>(&self, state_file: P, name: &str) -> Result<()> {
+        let path = scene_path(state_file.as_ref(), name)?;
+        write_toml(&path, self)
+    }
+}
+
+/// Scenes are kept one per file in a "scenes" directory beside the state
+/// file.
+fn scene_path(state_file: &Path, name: &str) -> Result<PathBuf> {
+    if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\']) {
+        return Err(Error::State {
+            path: state_file.display().to_string(),
+            message: format!("bad scene name: {:?}", name),
+        });
+    }
+    Ok(state_file
+        .with_file_name("scenes")
+        .join(name)
+        .with_extension("toml"))
+}
+
+/// Write `value` as TOML to `path`, replacing it atomically.
+fn write_toml<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    let text = toml::to_string(value).map_err(|e| Error::State {
+        path: path.display().to_string(),
+        message: e.to_string(),
+    })?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, text)?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// The contents of a state file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct State {
+    /// False once the strips have been turned off.
+    pub on: bool,
+    /// The last color or effect shown, restored when turned back on; none
+    /// for the configured effect.
+    pub last: Option<Show>,
+    /// The --max-brightness it was shown with.
+    pub max_brightness: Option<u8>,
+    /// The playlist effect playing, for a restart to carry on from.
+    pub playing: Option<String>,
+    /// The palette a palette cycle had reached, for a restart to carry on
+    /// from.
+    pub palette: Option<String>,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        State {
+            on: true,
+            last: None,
+            max_brightness: None,
+            playing: None,
+            palette: None,
+        }
+    }
+}
+
+impl State {
+    /// Read the state file; a missing one gives the default state.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<State> {
+        let path = path.as_ref();
+        let name = path.display().to_string();
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(State::default()),
+            Err(e) => return Err(Error::open(&name, e)),
+        };
+        toml::from_str(&text).map_err(|e| Error::State {
+            path: name,
+            message: e.to_string(),
+        })
+    }
+
+    /// Write the state file, replacing it atomically.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        write_toml(path.as_ref(), self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saves_and_loads_state() {
+        let dir = std::env::temp_dir().join(format!("led-strip-state-{}", std::process::id()));
+        let path = dir.join("state.toml");
+        assert_eq!(State::load(&path).unwrap(), State::default());
+
+        let state = State {
+            on: false,
+            last: Some(Show::Effect {
+                name: "rainbow + twinkle".to_string(),
+                palette: Some("ocean".to_string()),
+                speed: Some(2.0),
+                params: [("density".to_string(), "0.9".to_string())].into(),
+            }),
+            max_brightness: Some(64),
+            playing: Some("fire".to_string()),
+            palette: Some("ocean".to_string()),
+        };
+        state.save(&path).unwrap();
+        assert_eq!(State::load(&path).unwrap(), state);
+        // Only the finished file is left behind.
+        assert!(!path.with_extension("tmp").exists());
+
+        fs::write(&path, "on = \"yes\"").unwrap();
+        assert!(State::load(&path).is_err());
+        fs::write(&path, "mode = [").unwrap();
+        assert!(State::load(&path).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn saves_and_loads_scenes() {
+        let dir = std::env::temp_dir().join(format!("led-strip-scenes-{}", std::process::id()));
+        let state_file = dir.join("state.toml");
+        let party = Show::Effect {
+            name: "twinkle".to_string(),
+            palette: Some("ocean".to_string()),
+            speed: Some(2.0),
+            params: [("density".to_string(), "0.9".to_string())].into(),
+        };
+        party.save_scene(&state_file, "party").unwrap();
+        assert!(dir.join("scenes").join("party.toml").exists());
+        assert_eq!(Show::load_scene(&state_file, "party").unwrap(), party);
+        // Saving again replaces the scene.
+        let red = Show::Color { color: [255, 0, 0] };
+        red.save_scene(&state_file, "party").unwrap();
+        assert_eq!(Show::load_scene(&state_file, "party").unwrap(), red);
+        assert!(Show::load_scene(&state_file, "movie").is_err());
+
+        for name in &["", ".x", "a/b", "..\\x"] {
+            assert!(scene_path(&state_file, name).is_err(), "{:?}", name);
+            assert!(red.save_scene(&state_file, name).is_err(), "{:?}", name);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,765 @@
+use crate::calibration::Calibration;
+use crate::color::{Color, Color16, ColorRgbw, WhitePoint};
+use crate::driver::LedDriver;
+use crate::error::Result;
+use crate::gamma::GammaTable;
+use crate::tonemap::SoftClip;
+
+use std::fmt;
+use std::str::FromStr;
+
+/// Current drawn by a strip, used to dim frames that would overload its
+/// power supply.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PowerBudget {
+    /// Most current the supply can give.
+    pub max_milliamps: f64,
+    /// Current drawn by one color channel at full brightness.
+    pub milliamps_per_channel: f64,
+    /// Current drawn by each LED while dark.
+    pub idle_milliamps: f64,
+}
+
+impl PowerBudget {
+    /// Typical figures for 5 V addressable LEDs: 20 mA per channel and 1 mA
+    /// quiescent.
+    pub fn new(max_milliamps: f64) -> Self {
+        PowerBudget {
+            max_milliamps,
+            milliamps_per_channel: 20.0,
+            idle_milliamps: 1.0,
+        }
+    }
+}
+
+/// How one half of a strip is reflected onto the other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Mirror {
+    /// Every LED has its own index.
+    #[default]
+    Off,
+    /// Effects run in from both ends and meet in the middle.
+    Ends,
+    /// Effects run out from the middle towards both ends.
+    Center,
+}
+
+impl FromStr for Mirror {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(Mirror::Off),
+            "ends" => Ok(Mirror::Ends),
+            "center" => Ok(Mirror::Center),
+            _ => Err(format!("unknown mirror mode: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for Mirror {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Mirror::Off => "off",
+            Mirror::Ends => "ends",
+            Mirror::Center => "center",
+        })
+    }
+}
+
+/// How much of a color's common white is moved to the white LED on RGBW
+/// strips.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WhiteExtraction {
+    /// The white LED only lights for colors given with a white component.
+    #[default]
+    Off,
+    /// All of it, which draws the least power for a given color.
+    Min,
+    /// Half of it, keeping the color LEDs lit for brighter, fuller whites.
+    Half,
+}
+
+impl FromStr for WhiteExtraction {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "off" => Ok(WhiteExtraction::Off),
+            "min" => Ok(WhiteExtraction::Min),
+            "half" => Ok(WhiteExtraction::Half),
+            _ => Err(format!("unknown white extraction: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for WhiteExtraction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            WhiteExtraction::Off => "off",
+            WhiteExtraction::Min => "min",
+            WhiteExtraction::Half => "half",
+        })
+    }
+}
+
+/// An inclusive range of LED positions, written "10-20" or "7".
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PixelRange {
+    pub first: usize,
+    pub last: usize,
+}
+
+impl FromStr for PixelRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let parse = |v: &str| {
+            v.trim()
+                .parse::<usize>()
+                .map_err(|e| format!("bad pixel range {}: {}", s, e))
+        };
+        let (first, last) = match s.find('-') {
+            Some(i) => (parse(&s[..i])?, parse(&s[i + 1..])?),
+            None => (parse(s)?, parse(s)?),
+        };
+        if last < first {
+            return Err(format!("pixel range {} runs backwards", s));
+        }
+        Ok(PixelRange { first, last })
+    }
+}
+
+impl fmt::Display for PixelRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.first == self.last {
+            write!(f, "{}", self.first)
+        } else {
+            write!(f, "{}-{}", self.first, self.last)
+        }
+    }
+}
+
+/// A strip of LEDs attached to some output driver.
+///
+/// Pixels are corrected as they are set, in stages: the effect's color is
+/// scaled by the brightness, then looked up in the gamma table, then
+/// ordered for the wire by the driver. They are only written to the
+/// hardware when `show` is called.
+pub struct Strip {
+    driver: Box<dyn LedDriver>,
+    gamma_table: GammaTable,
+    /// Scale on every channel before gamma correction, 0 to 1.
+    brightness: f64,
+    pixels: Vec<Color>,
+    /// Corrected white channel; only used when the driver has a white LED.
+    white: Vec<u8>,
+    rgbw: Vec<ColorRgbw>,
+    /// Cap on every channel after gamma correction.
+    max_brightness: u8,
+    /// Multipliers applied to red, green and blue before gamma correction.
+    white_balance: (f64, f64, f64),
+    /// Brings colors overdriven by white folded into them back into range.
+    soft_clip: SoftClip,
+    white_extraction: WhiteExtraction,
+    /// Color of the white LED as red, green and blue levels, at most 1.
+    white_tint: [f32; 3],
+    power_budget: Option<PowerBudget>,
+    /// Index 0 is the far end of the strip.
+    reverse: bool,
+    /// LEDs index 0 is moved along the strip, wrapping at the end.
+    offset: usize,
+    mirror: Mirror,
+    /// LED positions that are always off, counted from the data input.
+    blackout: Vec<PixelRange>,
+    /// Per-LED scale factors and dead LEDs, counted from the data input.
+    calibration: Calibration,
+    /// Frame dimmed to fit the power budget.
+    dimmed: Vec<Color>,
+    /// Carry rounding errors from frame to frame so levels between whole
+    /// steps show on average.
+    dither: bool,
+    /// Corrected colors before rounding.
+    exact: Vec<[f32; 3]>,
+    /// Rounding error carried to the next frame when dithering.
+    residual: Vec<[f32; 3]>,
+}
+
+impl Strip {
+    pub fn new<D: LedDriver + 'static>(
+        driver: D,
+        num_leds: usize,
+        gamma_table: GammaTable,
+    ) -> Self {
+        Strip {
+            driver: Box::new(driver),
+            gamma_table,
+            brightness: 1.0,
+            pixels: vec![Color::default(); num_leds],
+            white: vec![0; num_leds],
+            rgbw: Vec::new(),
+            max_brightness: 255,
+            white_balance: (1.0, 1.0, 1.0),
+            soft_clip: SoftClip::default(),
+            white_extraction: WhiteExtraction::Off,
+            white_tint: [1.0; 3],
+            power_budget: None,
+            reverse: false,
+            offset: 0,
+            mirror: Mirror::Off,
+            blackout: Vec::new(),
+            calibration: Calibration::default(),
+            dimmed: Vec::new(),
+            dither: false,
+            exact: vec![[0.0; 3]; num_leds],
+            residual: vec![[0.0; 3]; num_leds],
+        }
+    }
+
+    /// Number of pixels effects can set; half the LEDs, rounded up, when
+    /// mirrored.
+    pub fn len(&self) -> usize {
+        match self.mirror {
+            Mirror::Off => self.pixels.len(),
+            Mirror::Ends | Mirror::Center => self.pixels.len().div_ceil(2),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pixels.is_empty()
+    }
+
+    /// Change the number of LEDs driven. LEDs dropped from the end are
+    /// blanked first so they don't hold their last color.
+    pub fn resize(&mut self, num_leds: usize) -> Result<()> {
+        let blanked = if num_leds < self.pixels.len() {
+            let (color, white, exact) = self.correct(Color16::default(), 0);
+            self.pixels[num_leds..].iter_mut().for_each(|c| *c = color);
+            self.white[num_leds..].iter_mut().for_each(|w| *w = white);
+            self.exact[num_leds..].iter_mut().for_each(|e| *e = exact);
+            self.show()
+        } else {
+            Ok(())
+        };
+        self.pixels.resize(num_leds, Color::default());
+        self.white.resize(num_leds, 0);
+        self.exact.resize(num_leds, [0.0; 3]);
+        self.residual.resize(num_leds, [0.0; 3]);
+        blanked
+    }
+
+    /// Scale colors by `brightness`, 0 to 1, before gamma correction, so
+    /// half brightness looks half as bright; applies to pixels set from
+    /// now on.
+    pub fn set_brightness(&mut self, brightness: f64) {
+        self.brightness = brightness.clamp(0.0, 1.0);
+    }
+
+    /// Limit every channel to `max_brightness` after gamma correction;
+    /// applies to pixels set from now on.
+    pub fn set_max_brightness(&mut self, max_brightness: u8) {
+        self.max_brightness = max_brightness;
+    }
+
+    /// Set the 5-bit current level of chips that send one with every LED,
+    /// such as the APA102, from 0 to 31; other chips ignore it.
+    pub fn set_global_brightness(&mut self, brightness: u8) {
+        self.driver.set_global_brightness(brightness);
+    }
+
+    /// Shift colors towards `white_point` before gamma correction; applies
+    /// to pixels set from now on.
+    pub fn set_white_point(&mut self, white_point: WhitePoint) {
+        self.white_balance = white_point.balance();
+    }
+
+    /// Move the white common to red, green and blue onto the white LED of
+    /// RGBW strips. `white_led` is the white LED's color temperature, so
+    /// that warm white LEDs replace warm rather than neutral white.
+    pub fn set_white_extraction(&mut self, extraction: WhiteExtraction, white_led: WhitePoint) {
+        let (red, green, blue) = white_led.balance();
+        self.white_extraction = extraction;
+        self.white_tint = [red as f32, green as f32, blue as f32];
+    }
+
+    /// Compress colors above `soft_clip`'s knee, before gamma correction;
+    /// applies to pixels set from now on.
+    pub fn set_soft_clip(&mut self, soft_clip: SoftClip) {
+        self.soft_clip = soft_clip;
+    }
+
+    /// Dither the color channels over time so levels between whole steps,
+    /// common at low brightness, show on average instead of rounding away.
+    pub fn set_dither(&mut self, dither: bool) {
+        self.dither = dither;
+        self.residual.iter_mut().for_each(|r| *r = [0.0; 3]);
+    }
+
+    /// Dim frames as they are shown so their estimated draw stays within
+    /// `budget`; `None` removes the limit.
+    pub fn set_power_budget(&mut self, budget: Option<PowerBudget>) {
+        self.power_budget = budget;
+    }
+
+    /// Estimated current for the current pixels under `budget`'s model.
+    pub fn estimate_milliamps(&self, budget: &PowerBudget) -> f64 {
+        let levels: u32 = self
+            .pixels
+            .iter()
+            .zip(&self.white)
+            .map(|(c, w)| u32::from(c.red) + u32::from(c.green) + u32::from(c.blue) + u32::from(*w))
+            .sum();
+        self.pixels.len() as f64 * budget.idle_milliamps
+            + f64::from(levels) / 255.0 * budget.milliamps_per_channel
+    }
+
+    /// Factor the pixels must be dimmed by to fit the power budget.
+    fn power_scale(&self) -> f64 {
+        let budget = match &self.power_budget {
+            Some(budget) => budget,
+            None => return 1.0,
+        };
+        let idle = self.pixels.len() as f64 * budget.idle_milliamps;
+        let draw = self.estimate_milliamps(budget);
+        if draw <= budget.max_milliamps {
+            return 1.0;
+        }
+        let scale = ((budget.max_milliamps - idle) / (draw - idle)).clamp(0.0, 1.0);
+        debug!("estimated {:.0} mA, dimming to {:.0}%", draw, scale * 100.0);
+        scale
+    }
+
+    /// Replace the gamma table; applies to pixels set from now on.
+    pub fn set_gamma_table(&mut self, gamma_table: GammaTable) {
+        self.gamma_table = gamma_table;
+    }
+
+    /// Set the pixel at `index`; out of range indices are ignored.
+    pub fn set_pixel(&mut self, index: usize, red: u8, green: u8, blue: u8) {
+        self.set_pixel_rgbw(index, red, green, blue, 0);
+    }
+
+    /// Set the pixel at `index` including the white LED. On strips without
+    /// one the white component is mixed into the color channels.
+    pub fn set_pixel_rgbw(&mut self, index: usize, red: u8, green: u8, blue: u8, white: u8) {
+        let color = Color16::from((red, green, blue));
+        self.set_pixel16_white(index, color, u16::from(white) * 257);
+    }
+
+    /// Set the pixel at `index` from a 16-bit color; out of range indices
+    /// are ignored.
+    pub fn set_pixel16(&mut self, index: usize, color: Color16) {
+        self.set_pixel16_white(index, color, 0);
+    }
+
+    fn set_pixel16_white(&mut self, index: usize, color: Color16, white: u16) {
+        if index >= self.len() {
+            return;
+        }
+        let (color, white, exact) = self.correct(color, white);
+        let (first, second) = self.mirrored(index);
+        for index in std::iter::once(first).chain(second) {
+            let index = self.physical(index);
+            self.pixels[index] = color;
+            self.white[index] = white;
+            self.exact[index] = exact;
+        }
+    }
+
+    /// Reflect one half of the strip onto the other; `len` becomes the
+    /// size of a half.
+    pub fn set_mirror(&mut self, mirror: Mirror) {
+        self.mirror = mirror;
+    }
+
+    /// Keep the LEDs in `ranges` off whatever is drawn, e.g. ones hidden
+    /// from view. Positions count from the data input, ignoring the
+    /// mirror, offset and reverse mappings.
+    pub fn set_blackout(&mut self, ranges: &[PixelRange]) {
+        self.blackout = ranges.to_vec();
+    }
+
+    /// Scale each LED's channels by its entry in `calibration` as frames
+    /// are shown, and keep its dead LEDs off, to even out LEDs that are
+    /// brighter, dimmer or tinted compared with the rest of the strip.
+    pub fn set_calibration(&mut self, calibration: Calibration) {
+        self.calibration = calibration;
+    }
+
+    /// The one or two strip positions pixel `index` is shown at.
+    fn mirrored(&self, index: usize) -> (usize, Option<usize>) {
+        let len = self.pixels.len();
+        let (first, second) = match self.mirror {
+            Mirror::Off => return (index, None),
+            Mirror::Ends => (index, len - 1 - index),
+            // With an odd length pixel 0 is the single middle LED.
+            Mirror::Center => (len / 2 + index, (len - 1) / 2 - index),
+        };
+        (first, Some(second).filter(|s| *s != first))
+    }
+
+    /// Run effects from the far end of the strip, e.g. when its data line
+    /// comes in from the right.
+    pub fn set_reverse(&mut self, reverse: bool) {
+        self.reverse = reverse;
+    }
+
+    /// Start effects `offset` LEDs along the strip, for strips wired in a
+    /// loop. Applied before `set_reverse`.
+    pub fn set_offset(&mut self, offset: usize) {
+        self.offset = offset;
+    }
+
+    /// Position on the wire of strip position `index`.
+    fn physical(&self, index: usize) -> usize {
+        let len = self.pixels.len();
+        let index = (index + self.offset) % len;
+        if self.reverse {
+            len - 1 - index
+        } else {
+            index
+        }
+    }
+
+    pub fn fill(&mut self, red: u8, green: u8, blue: u8) {
+        self.fill_rgbw(red, green, blue, 0);
+    }
+
+    pub fn fill_rgbw(&mut self, red: u8, green: u8, blue: u8, white: u8) {
+        let (color, white, exact) =
+            self.correct(Color16::from((red, green, blue)), u16::from(white) * 257);
+        self.pixels.iter_mut().for_each(|c| *c = color);
+        self.white.iter_mut().for_each(|w| *w = white);
+        self.exact.iter_mut().for_each(|e| *e = exact);
+    }
+
+    pub fn clear(&mut self) {
+        self.fill(0, 0, 0);
+    }
+
+    /// Dim and gamma correct a color, giving the rounded color, white level
+    /// and the color before rounding.
+    fn correct(&self, color: Color16, white: u16) -> (Color, u8, [f32; 3]) {
+        // Without a white LED white is added to the color, which can
+        // overdrive it.
+        let (extra, white) = if self.driver.has_white() {
+            (0.0, white)
+        } else {
+            (f64::from(white), 0)
+        };
+        let level = |v: u16| (f64::from(v) + extra) / 65535.0;
+        let [red, green, blue] =
+            self.soft_clip
+                .apply([level(color.red), level(color.green), level(color.blue)]);
+        let widen = |v: f64| (v * self.brightness * 65535.0).round() as u16;
+        let rgb = Color16::new(widen(red), widen(green), widen(blue));
+        let (r, g, b) = self.white_balance;
+        let balance = |v: u16, by: f64| (f64::from(v) * by).round() as u16;
+        let mut exact = self.gamma_table.correct_exact(
+            balance(rgb.red, r),
+            balance(rgb.green, g),
+            balance(rgb.blue, b),
+        );
+        let mut white = self
+            .gamma_table
+            .correct_white((f64::from(white) * self.brightness / 257.0).round() as u8);
+        if self.driver.has_white() && self.white_extraction != WhiteExtraction::Off {
+            // Work on corrected levels, which are proportional to light output.
+            let common = exact
+                .iter()
+                .zip(&self.white_tint)
+                .map(|(e, tint)| e / tint.max(f32::EPSILON))
+                .fold(f32::INFINITY, f32::min);
+            let moved = match self.white_extraction {
+                WhiteExtraction::Half => common / 2.0,
+                _ => common,
+            }
+            .min(255.0 - f32::from(white));
+            for (e, tint) in exact.iter_mut().zip(&self.white_tint) {
+                *e = (*e - moved * tint).max(0.0);
+            }
+            white = (f32::from(white) + moved + 0.5) as u8;
+        }
+        if self.max_brightness != 255 {
+            let max = f32::from(self.max_brightness) / 255.0;
+            exact.iter_mut().for_each(|e| *e *= max);
+            white = (u16::from(white) * u16::from(self.max_brightness) / 255) as u8;
+        }
+        let round = |e: f32| (e + 0.5) as u8;
+        let color = Color::new(round(exact[0]), round(exact[1]), round(exact[2]));
+        (color, white, exact)
+    }
+
+    /// Round the exact colors, scaled by the calibration, into the pixels.
+    /// When dithering each channel's rounding error is carried into the
+    /// next frame.
+    fn quantize_pixels(&mut self) {
+        for (i, ((pixel, exact), residual)) in self
+            .pixels
+            .iter_mut()
+            .zip(&self.exact)
+            .zip(self.residual.iter_mut())
+            .enumerate()
+        {
+            let scale = self.calibration.scale_at(i);
+            let mut levels = [0u8; 3];
+            for c in 0..3 {
+                let mut wanted = exact[c] * scale[c];
+                if self.dither {
+                    wanted += residual[c];
+                }
+                let level = wanted.round().clamp(0.0, 255.0);
+                if self.dither {
+                    residual[c] = wanted - level;
+                }
+                levels[c] = level as u8;
+            }
+            *pixel = Color::from(levels);
+        }
+    }
+
+    /// Write the current pixels to the strip.
+    pub fn show(&mut self) -> Result<()> {
+        if self.dither || !self.calibration.scale.is_empty() {
+            self.quantize_pixels();
+        }
+        let len = self.pixels.len();
+        for range in &self.blackout {
+            let end = (range.last + 1).min(len);
+            for i in range.first.min(end)..end {
+                self.pixels[i] = Color::BLACK;
+                self.white[i] = 0;
+            }
+        }
+        for &i in self.calibration.dead.iter().filter(|i| **i < len) {
+            self.pixels[i] = Color::BLACK;
+            self.white[i] = 0;
+        }
+        let scale = self.power_scale();
+        // Rounding down keeps the dimmed frame under the budget.
+        let dim = |v: u8| (f64::from(v) * scale) as u8;
+        if self.driver.has_white() {
+            self.rgbw.clear();
+            self.rgbw.extend(
+                self.pixels
+                    .iter()
+                    .zip(&self.white)
+                    .map(|(c, w)| c.scale(scale).with_white(dim(*w))),
+            );
+            self.driver.write_frame_rgbw(&self.rgbw)
+        } else if scale < 1.0 {
+            self.dimmed.clear();
+            self.dimmed
+                .extend(self.pixels.iter().map(|c| c.scale(scale)));
+            self.driver.write_frame(&self.dimmed)
+        } else {
+            self.driver.write_frame(&self.pixels)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::driver::{Chip, MockBus, Sk6812, P9813};
+
+    /// A strip of `num_leds` with linear gamma on a mock P9813, and its bus.
+    fn strip(num_leds: usize) -> (Strip, MockBus) {
+        let bus = MockBus::validating(Chip::P9813);
+        let gamma_table = GammaTable::new(1.0, 1.0, 1.0);
+        (
+            Strip::new(P9813::new(bus.clone()), num_leds, gamma_table),
+            bus,
+        )
+    }
+
+    /// The colors of the last P9813 frame on `bus`.
+    fn shown(bus: &MockBus) -> Vec<Color> {
+        let frame = bus.last_frame().unwrap();
+        frame[4..frame.len() - 8]
+            .chunks(4)
+            .map(|p| Color::new(p[3], p[2], p[1]))
+            .collect()
+    }
+
+    #[test]
+    fn dims_frames_over_the_power_budget() {
+        let budget = PowerBudget::new(200.0);
+        let (mut strip, bus) = strip(10);
+        strip.set_power_budget(Some(budget));
+        strip.fill(255, 255, 255);
+        // 10 mA idle and 600 mA lit is more than the supply gives.
+        assert_eq!(strip.estimate_milliamps(&budget), 610.0);
+        strip.show().unwrap();
+        let levels: u32 = shown(&bus)
+            .iter()
+            .map(|c| u32::from(c.red) + u32::from(c.green) + u32::from(c.blue))
+            .sum();
+        let draw = 10.0 + f64::from(levels) / 255.0 * budget.milliamps_per_channel;
+        assert!((190.0..=200.0).contains(&draw), "{} mA", draw);
+
+        // A frame within the budget is left alone, as is any without one.
+        strip.fill(40, 30, 20);
+        strip.show().unwrap();
+        assert_eq!(shown(&bus), vec![Color::new(40, 30, 20); 10]);
+        strip.set_power_budget(None);
+        strip.fill(255, 255, 255);
+        strip.show().unwrap();
+        assert_eq!(shown(&bus), vec![Color::WHITE; 10]);
+    }
+
+    /// Where a red pixel at `index` lands on the wire.
+    fn lit(strip: &mut Strip, bus: &MockBus, index: usize) -> Vec<usize> {
+        strip.clear();
+        strip.set_pixel(index, 255, 0, 0);
+        strip.show().unwrap();
+        let frame = shown(bus);
+        (0..frame.len()).filter(|i| frame[*i].red == 255).collect()
+    }
+
+    #[test]
+    fn maps_pixels_onto_the_strip() {
+        let (mut strip, bus) = strip(5);
+        assert_eq!(lit(&mut strip, &bus, 1), [1]);
+        strip.set_reverse(true);
+        assert_eq!(lit(&mut strip, &bus, 0), [4]);
+        // The offset wraps, and is taken before reversing.
+        strip.set_offset(2);
+        assert_eq!(lit(&mut strip, &bus, 0), [2]);
+        assert_eq!(lit(&mut strip, &bus, 4), [3]);
+        strip.set_reverse(false);
+        assert_eq!(lit(&mut strip, &bus, 4), [1]);
+    }
+
+    #[test]
+    fn mirrors_half_the_strip() {
+        let (mut strip, bus) = strip(5);
+        strip.set_mirror(Mirror::Ends);
+        assert_eq!(strip.len(), 3);
+        assert_eq!(lit(&mut strip, &bus, 0), [0, 4]);
+        assert_eq!(lit(&mut strip, &bus, 2), [2]);
+        // Pixels past the half are ignored.
+        assert!(lit(&mut strip, &bus, 3).is_empty());
+        strip.set_mirror(Mirror::Center);
+        assert_eq!(lit(&mut strip, &bus, 0), [2]);
+        assert_eq!(lit(&mut strip, &bus, 2), [0, 4]);
+        strip.resize(6).unwrap();
+        assert_eq!(lit(&mut strip, &bus, 0), [2, 3]);
+    }
+
+    #[test]
+    fn blacks_out_hidden_leds() {
+        let (mut strip, bus) = strip(6);
+        // Positions past the end are ignored.
+        strip.set_blackout(&["1-2".parse().unwrap(), "9".parse().unwrap()]);
+        // Positions count from the data input whatever the mapping.
+        strip.set_reverse(true);
+        strip.fill(255, 255, 255);
+        strip.show().unwrap();
+        let (black, white) = (Color::BLACK, Color::WHITE);
+        assert_eq!(shown(&bus), [white, black, black, white, white, white]);
+
+        strip.set_blackout(&[]);
+        strip.fill(255, 255, 255);
+        strip.show().unwrap();
+        assert_eq!(shown(&bus), vec![white; 6]);
+    }
+
+    #[test]
+    fn masks_dead_leds() {
+        let (mut strip, bus) = strip(6);
+        strip.set_calibration(Calibration {
+            dead: vec![4, 10],
+            ..Calibration::default()
+        });
+        strip.set_reverse(true);
+        strip.fill(255, 255, 255);
+        strip.show().unwrap();
+        let (black, white) = (Color::BLACK, Color::WHITE);
+        assert_eq!(shown(&bus), [white, white, white, white, black, white]);
+
+        strip.set_calibration(Calibration::default());
+        strip.fill(255, 255, 255);
+        strip.show().unwrap();
+        assert_eq!(shown(&bus), vec![white; 6]);
+    }
+
+    #[test]
+    fn dithering_averages_to_the_16_bit_level() {
+        const FRAMES: usize = 64;
+        let (mut strip, bus) = strip(2);
+        // About 10.5 steps of 255 for red, a quarter for green and 3 for blue.
+        let color = Color16::new(2699, 64, 771);
+        let wanted = [2699.0, 64.0, 771.0].map(|v: f64| v / 257.0);
+        strip.set_dither(true);
+        strip.set_pixel16(0, color);
+        let mut totals = [0u32; 3];
+        for _ in 0..FRAMES {
+            strip.show().unwrap();
+            let pixel = shown(&bus)[0];
+            for (total, level) in totals.iter_mut().zip(&[pixel.red, pixel.green, pixel.blue]) {
+                *total += u32::from(*level);
+            }
+        }
+        for (total, wanted) in totals.iter().zip(&wanted) {
+            let mean = f64::from(*total) / FRAMES as f64;
+            assert!(
+                (mean - wanted).abs() < 1.0 / FRAMES as f64,
+                "{} {}",
+                mean,
+                wanted
+            );
+        }
+
+        // Without it every frame is rounded the same way.
+        strip.set_dither(false);
+        strip.set_pixel16(0, color);
+        strip.show().unwrap();
+        assert_eq!(shown(&bus)[0], Color::new(11, 0, 3));
+    }
+
+    /// The red, green, blue and white of the first pixel of the last SK6812
+    /// frame on `bus`.
+    fn shown_rgbw(bus: &MockBus) -> [u8; 4] {
+        let frame = bus.last_frame().unwrap();
+        // Each data bit is the middle one of its three SPI bits.
+        let byte = |at: usize| {
+            let bits = u32::from(frame[at]) << 16
+                | u32::from(frame[at + 1]) << 8
+                | u32::from(frame[at + 2]);
+            (0..8).fold(0, |byte, k| byte << 1 | ((bits >> (22 - 3 * k)) & 1) as u8)
+        };
+        let (green, red, blue, white) = (byte(1), byte(4), byte(7), byte(10));
+        [red, green, blue, white]
+    }
+
+    #[test]
+    fn moves_common_white_to_the_white_led() {
+        let bus = MockBus::validating(Chip::Sk6812);
+        let gamma_table = GammaTable::new(1.0, 1.0, 1.0);
+        let mut strip = Strip::new(Sk6812::new(bus.clone()), 1, gamma_table);
+        let mut show = |extraction, white_led| {
+            strip.set_white_extraction(extraction, white_led);
+            strip.set_pixel(0, 200, 100, 50);
+            strip.show().unwrap();
+            shown_rgbw(&bus)
+        };
+        let neutral = WhitePoint::NEUTRAL;
+        assert_eq!(show(WhiteExtraction::Off, neutral), [200, 100, 50, 0]);
+        assert_eq!(show(WhiteExtraction::Min, neutral), [150, 50, 0, 50]);
+        assert_eq!(show(WhiteExtraction::Half, neutral), [175, 75, 25, 25]);
+        // A warm white LED's light, added back, makes up the same color.
+        let warm = WhitePoint::new(2700.0);
+        let [red, green, blue, white] = show(WhiteExtraction::Min, warm);
+        let (r, g, b) = warm.balance();
+        let white = f64::from(white);
+        for (level, wanted) in &[
+            (f64::from(red) + white * r, 200.0),
+            (f64::from(green) + white * g, 100.0),
+            (f64::from(blue) + white * b, 50.0),
+        ] {
+            assert!((level - wanted).abs() <= 1.0, "{} {}", level, wanted);
+        }
+        assert!(white > 50.0);
+    }
+}
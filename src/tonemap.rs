@@ -0,0 +1,66 @@
+//! Bringing colors that overdrive their channels back into range.
+
+/// Compresses colors whose brightest channel passes a knee, scaling every
+/// channel by the same factor. Hue and saturation hold, where clipping each
+/// channel on its own would wash bright colors out towards white.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SoftClip {
+    knee: f64,
+}
+
+impl Default for SoftClip {
+    fn default() -> Self {
+        SoftClip { knee: 1.0 }
+    }
+}
+
+impl SoftClip {
+    /// Levels up to `knee`, from 0 to 1, pass unchanged; above it they are
+    /// eased smoothly towards full. A knee of 1 only scales down colors
+    /// that overdrive.
+    pub fn new(knee: f64) -> Self {
+        SoftClip {
+            knee: knee.clamp(0.0, 1.0),
+        }
+    }
+
+    pub fn knee(&self) -> f64 {
+        self.knee
+    }
+
+    /// Map `levels`, where 1 is full and more overdrives, into 0 to 1.
+    pub fn apply(self, levels: [f64; 3]) -> [f64; 3] {
+        let max = levels.iter().copied().fold(0.0, f64::max);
+        if max <= self.knee {
+            return levels;
+        }
+        let room = 1.0 - self.knee;
+        // Leaves the knee with a slope of 1 and approaches full.
+        let compressed = if room > 0.0 {
+            self.knee + room * (1.0 - (-(max - self.knee) / room).exp())
+        } else {
+            1.0
+        };
+        levels.map(|level| level * compressed / max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_hue_when_overdriven() {
+        let clip = SoftClip::default();
+        assert_eq!(clip.apply([0.5, 1.0, 0.0]), [0.5, 1.0, 0.0]);
+        assert_eq!(clip.apply([2.0, 1.0, 0.0]), [1.0, 0.5, 0.0]);
+
+        let soft = SoftClip::new(0.5);
+        assert_eq!(soft.apply([0.5, 0.25, 0.0]), [0.5, 0.25, 0.0]);
+        let [red, green, blue] = soft.apply([4.0, 2.0, 1.0]);
+        assert!(red < 1.0 && red > 0.99);
+        assert!((green - red / 2.0).abs() < 1.0e-9 && (blue - red / 4.0).abs() < 1.0e-9);
+        // Brighter input stays brighter.
+        assert!(soft.apply([0.8, 0.0, 0.0])[0] < soft.apply([0.9, 0.0, 0.0])[0]);
+    }
+}